@@ -1,15 +1,18 @@
 use std::{
     ffi::OsString,
+    fmt::Display,
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use anyhow::bail;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use path_clean::PathClean;
 
-
-
 const CONFIG_FILE_NAME: &str = "config.json";
+// These are the extensions of the formats a config file can be written in.
+const CONFIG_FILE_EXTENSIONS: [&str; 4] = ["json", "toml", "yaml", "yml"];
+const EXPECTED_EXTENSIONS: &str = "json, toml, yaml or yml";
 
 #[derive(Debug, Parser)]
 #[command(name = "sma")]
@@ -44,15 +47,46 @@ pub enum Commands {
         /// Applications to start.
         #[arg(required = true, num_args = 1..)]
         start: Vec<String>,
-        /// Specify an index which will be used to index in to `start`, to exit all
-        /// started applications on if the application at that index stops running.
-        #[arg(short, long)]
-        exit_on: Option<u8>,
-        // TODO: Add cwd.
-        // TODO: Add cascade_kill.
+        /// Specify an application by its index in `start` or its `--name`, to exit
+        /// all started applications on if that application stops running. Can be
+        /// given more than once, then it is enough that any of them stops.
+        #[arg(short, long, value_name = "APP")]
+        exit_on: Vec<String>,
+        /// Specify an application by its index in `start` or its `--name`, to exit
+        /// all started applications once all the given applications have stopped
+        /// running. Can be given more than once.
+        #[arg(long, value_name = "APP")]
+        exit_on_all: Vec<String>,
+        /// Exit all started applications if any of them stops with a failure.
+        #[arg(long)]
+        exit_on_any_failed: bool,
+        /// Exit once all started applications have stopped running.
+        #[arg(long)]
+        exit_on_all_exited: bool,
+        /// Write the stdout and stderr of every application to log files. `{name}`
+        /// in the path is replaced with the `--name` of the application, or its
+        /// index in `start` if it has none, `{index}` with its index and `{stream}`
+        /// with `stdout` or `stderr`, e.g. `logs/{name}.{stream}.log`.
+        #[arg(long, value_name = "PATH_TEMPLATE")]
+        log_path: Option<String>,
+        /// Rotate a log file once it is bigger than this, e.g. `10MB`.
+        #[arg(long, value_name = "SIZE", requires = "log_path")]
+        log_max_size: Option<String>,
+        /// How many rotated log files are kept for every log file.
+        #[arg(long, value_name = "COUNT", requires = "log_path")]
+        log_keep: Option<u32>,
+        /// Write the config that is given by the arguments to this file instead of
+        /// starting it, the format of the file is chosen by its extension.
+        #[arg(long, value_name = "PATH", value_parser = cli_config_create_config_validator)]
+        write_config: Option<PathBuf>,
+        #[command(flatten)]
+        output: OutputArgs,
+        #[command(flatten)]
+        options: Box<StartOptions>,
     },
 
     /// Specify the config file for SMA.
+    #[command(args_conflicts_with_subcommands = true)]
     Config {
         /// The file path to the config file.
         #[arg(
@@ -60,6 +94,17 @@ pub enum Commands {
             default_value = CONFIG_FILE_NAME
         )]
         file_path: PathBuf,
+
+        #[command(flatten)]
+        output: OutputArgs,
+
+        /// Prints the config with the files it `extends` and `include` merged
+        /// into it, instead of running it.
+        #[arg(long)]
+        print_resolved: bool,
+
+        #[command(subcommand)]
+        command: Option<ConfigCommands>,
     },
 
     /// Creates an empty config file.
@@ -75,6 +120,366 @@ pub enum Commands {
         #[arg(short, long)]
         force_overide: bool,
     },
+
+    /// Writes a config file in another format, the format of a file is chosen by
+    /// its extension.
+    ///
+    /// When a YAML config is written over a YAML file only the comments on their
+    /// own line above a top level key are kept, comments at the end of a line or
+    /// in a list are lost.
+    Convert {
+        /// The file path to the config file to convert.
+        #[arg(value_parser = cli_config_file_path_validator)]
+        input: PathBuf,
+        /// The path to where the converted config file should be created.
+        #[arg(value_parser = cli_config_create_config_validator)]
+        output: PathBuf,
+        /// Flag to force a override of the output file.
+        #[arg(short, long)]
+        force_overide: bool,
+    },
+
+    /// Checks a config file without running it, and shows every problem it has.
+    Validate {
+        /// The file path to the config file.
+        #[arg(
+            value_parser = cli_config_file_path_validator,
+            default_value = CONFIG_FILE_NAME
+        )]
+        file_path: PathBuf,
+        /// How the problems are shown.
+        #[arg(long, value_enum, default_value_t)]
+        format: DiagnosticsFormat,
+    },
+
+    /// Prints the JSON Schema of the config file, editors use it to check and
+    /// complete a config.
+    Schema,
+
+    /// Shows the applications of a running config.
+    Status {
+        /// The file path to the config file the running sma was started with.
+        #[arg(
+            value_parser = cli_config_file_path_validator,
+            default_value = CONFIG_FILE_NAME
+        )]
+        file_path: PathBuf,
+    },
+
+    /// Stops a running config and all of its applications.
+    Stop {
+        /// The file path to the config file the running sma was started with.
+        #[arg(
+            value_parser = cli_config_file_path_validator,
+            default_value = CONFIG_FILE_NAME
+        )]
+        file_path: PathBuf,
+    },
+
+    /// Stops the applications that were left running by a config, when sma was
+    /// killed before it could stop them.
+    Cleanup {
+        /// The file path to the config file.
+        #[arg(
+            value_parser = cli_config_file_path_validator,
+            default_value = CONFIG_FILE_NAME
+        )]
+        file_path: PathBuf,
+    },
+
+    /// Restarts an application of a running config.
+    Restart {
+        /// The name or the index in `start` of the application.
+        app: String,
+        /// The file path to the config file the running sma was started with.
+        #[arg(
+            value_parser = cli_config_file_path_validator,
+            default_value = CONFIG_FILE_NAME
+        )]
+        file_path: PathBuf,
+    },
+
+    /// Shows the last lines of the log files of an application of a running config.
+    Logs {
+        /// The name or the index in `start` of the application.
+        app: String,
+        /// The file path to the config file the running sma was started with.
+        #[arg(
+            value_parser = cli_config_file_path_validator,
+            default_value = CONFIG_FILE_NAME
+        )]
+        file_path: PathBuf,
+        /// How many lines to show of every log file.
+        #[arg(short = 'n', long, default_value_t = 20)]
+        lines: usize,
+    },
+}
+
+/// How the output of the started applications is shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputMode {
+    /// The applications are detached from sma and their output is not shown.
+    #[default]
+    Detached,
+    /// The applications write straight to the console of sma.
+    Inherit,
+    /// The output of all applications is shown in the console of sma, where every
+    /// line is prefixed with the name of the application it came from.
+    Prefixed,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Args)]
+pub struct OutputArgs {
+    /// How the output of the applications is shown.
+    #[arg(long, value_enum, default_value_t)]
+    pub output: OutputMode,
+    /// Add the time (UTC) to every line of output in the `prefixed` output mode.
+    #[arg(long)]
+    pub timestamps: bool,
+}
+
+/// The settings of a config file that can be given to `start`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Args)]
+pub struct StartOptions {
+    /// The current working directory the applications are started in.
+    #[arg(long)]
+    pub cwd: Option<PathBuf>,
+    /// Also stop every process the applications started when they are stopped.
+    #[arg(long)]
+    pub cascade_kill: bool,
+    /// Put the applications in cgroups of their own (Linux only).
+    #[arg(long, value_enum)]
+    pub cgroups: Option<CgroupsArg>,
+    /// The signal the applications are asked to exit with.
+    #[arg(long, value_enum)]
+    pub stop_signal: Option<StopSignalArg>,
+    /// How many milliseconds the applications get to exit before they are killed.
+    #[arg(long, value_name = "MILLISECONDS")]
+    pub stop_timeout: Option<u64>,
+    /// An environment variable given to all applications. Can be given more than
+    /// once.
+    #[arg(long, value_name = "NAME=VALUE", value_parser = parse_name_value)]
+    pub env: Vec<(String, String)>,
+    /// A dotenv file with environment variables for all applications. Can be given
+    /// more than once.
+    #[arg(long, value_name = "PATH")]
+    pub env_file: Vec<PathBuf>,
+    /// Start the applications from an empty environment instead of the one of sma.
+    #[arg(long)]
+    pub clean_env: bool,
+    /// An environment variable of sma that is still given to the applications with
+    /// `--clean-env`. Can be given more than once.
+    #[arg(long, value_name = "NAME", requires = "clean_env")]
+    pub env_allow: Vec<String>,
+    /// A variable that can be used as `${NAME}` in `start` and `--cwd`. Can be given
+    /// more than once.
+    #[arg(long, value_name = "NAME=VALUE", value_parser = parse_name_value)]
+    pub var: Vec<(String, String)>,
+    /// What happens when the config is started while sma is already running it.
+    #[arg(long, value_enum)]
+    pub single_instance: Option<SingleInstanceArg>,
+    #[command(flatten)]
+    pub apps: AppOptions,
+}
+
+/// The settings of an application in `start` that can be given to `start`,
+/// every value starts with the index of the application in `start`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Args)]
+pub struct AppOptions {
+    /// The name of an application, e.g. `0=web`.
+    #[arg(long, value_name = "INDEX=NAME", value_parser = parse_index_value::<String>)]
+    pub name: Vec<(usize, String)>,
+    /// The current working directory of an application.
+    #[arg(long, value_name = "INDEX=PATH", value_parser = parse_index_value::<PathBuf>)]
+    pub app_cwd: Vec<(usize, PathBuf)>,
+    /// An application that has to be started before an application, by its index
+    /// or name, e.g. `1=web`. Can be given more than once.
+    #[arg(long, value_name = "INDEX=APP", value_parser = parse_index_value::<String>)]
+    pub depends_on: Vec<(usize, String)>,
+    /// What an application has to do before it is ready: `tcp:HOST:PORT`,
+    /// `file:PATH`, `stdout:REGEX`, `stderr:REGEX` or an `http://` url.
+    #[arg(long, value_name = "INDEX=CHECK", value_parser = parse_index_value::<ReadyCheckArg>)]
+    pub ready: Vec<(usize, ReadyCheckArg)>,
+    /// How many milliseconds an application gets to become ready.
+    #[arg(long, value_name = "INDEX=MILLISECONDS", value_parser = parse_index_value::<u64>)]
+    pub ready_timeout: Vec<(usize, u64)>,
+    /// When an application is started again after it exited: `never`,
+    /// `on-failure` or `always`.
+    #[arg(long, value_name = "INDEX=POLICY", value_parser = parse_index_value::<RestartArg>)]
+    pub restart: Vec<(usize, RestartArg)>,
+    /// How many times an application is restarted before we give up.
+    #[arg(long, value_name = "INDEX=COUNT", value_parser = parse_index_value::<u32>)]
+    pub max_retries: Vec<(usize, u32)>,
+    /// How many milliseconds we wait before the first restart of an application.
+    #[arg(long, value_name = "INDEX=MILLISECONDS", value_parser = parse_index_value::<u64>)]
+    pub restart_backoff: Vec<(usize, u64)>,
+    /// The most milliseconds we wait before a restart of an application.
+    #[arg(long, value_name = "INDEX=MILLISECONDS", value_parser = parse_index_value::<u64>)]
+    pub restart_max_backoff: Vec<(usize, u64)>,
+    /// The most memory an application can use, e.g. `0=512MB` (needs
+    /// `--cgroups per-app`).
+    #[arg(long, value_name = "INDEX=SIZE", value_parser = parse_index_value::<String>)]
+    pub max_memory: Vec<(usize, String)>,
+    /// The niceness an application runs with, from -20 to 19.
+    #[arg(long, value_name = "INDEX=NICE", value_parser = parse_index_value::<i32>)]
+    pub nice: Vec<(usize, i32)>,
+    /// The most files an application can have open at once.
+    #[arg(long, value_name = "INDEX=COUNT", value_parser = parse_index_value::<u64>)]
+    pub open_files: Vec<(usize, u64)>,
+    /// The biggest core dump an application can write, `0` turns them off.
+    #[arg(long, value_name = "INDEX=SIZE", value_parser = parse_index_value::<String>)]
+    pub core_dump_size: Vec<(usize, String)>,
+    /// An environment variable given to an application, e.g. `0=PORT=80`. Can be
+    /// given more than once.
+    #[arg(long, value_name = "INDEX=NAME=VALUE", value_parser = parse_index_name_value)]
+    pub app_env: Vec<(usize, (String, String))>,
+    /// A dotenv file with environment variables for an application. Can be given
+    /// more than once.
+    #[arg(long, value_name = "INDEX=PATH", value_parser = parse_index_value::<PathBuf>)]
+    pub app_env_file: Vec<(usize, PathBuf)>,
+    /// Write the stdout and stderr of an application to its own log files instead
+    /// of the ones of `--log-path`, with the same replacements.
+    #[arg(long, value_name = "INDEX=PATH_TEMPLATE", value_parser = parse_index_value::<String>)]
+    pub app_log_path: Vec<(usize, String)>,
+}
+
+/// How the problems found by `validate` are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DiagnosticsFormat {
+    /// One line for every problem, with the line and column it is at.
+    #[default]
+    Human,
+    /// A JSON array with an object for every problem.
+    Json,
+}
+
+/// Which cgroups the applications are put in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CgroupsArg {
+    /// The applications stay in the cgroup of sma.
+    Off,
+    /// All the applications share one cgroup.
+    PerConfig,
+    /// Every application gets a cgroup of its own.
+    PerApp,
+}
+
+/// What happens when a config is started while sma is already running it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SingleInstanceArg {
+    /// The config is started again, next to the one that is running.
+    Allow,
+    /// The config is not started again.
+    Refuse,
+    /// The running sma is stopped first.
+    Replace,
+    /// The running sma is asked to start the applications that are not running.
+    Forward,
+}
+
+/// When an application is started again after it exited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RestartArg {
+    Never,
+    OnFailure,
+    Always,
+}
+
+impl FromStr for RestartArg {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        <RestartArg as ValueEnum>::from_str(policy, false)
+    }
+}
+
+/// What an application has to do before it is ready.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadyCheckArg {
+    Tcp(String),
+    File(PathBuf),
+    Stdout(String),
+    Stderr(String),
+    Http(String),
+}
+
+impl FromStr for ReadyCheckArg {
+    type Err = anyhow::Error;
+
+    fn from_str(check: &str) -> Result<Self, Self::Err> {
+        if check.starts_with("http://") {
+            return Ok(ReadyCheckArg::Http(check.to_string()));
+        }
+        let Some((kind, value)) = check.split_once(':') else {
+            bail!("`{check}` must be written as `tcp:HOST:PORT`, `file:PATH`, `stdout:REGEX`, `stderr:REGEX` or an `http://` url.")
+        };
+        Ok(match kind {
+            "tcp" => ReadyCheckArg::Tcp(value.to_string()),
+            "file" => ReadyCheckArg::File(value.into()),
+            "stdout" => ReadyCheckArg::Stdout(value.to_string()),
+            "stderr" => ReadyCheckArg::Stderr(value.to_string()),
+            _ => bail!("`{kind}` is not a ready check, it must be `tcp`, `file`, `stdout`, `stderr` or an `http://` url."),
+        })
+    }
+}
+
+/// The signal the applications are asked to exit with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StopSignalArg {
+    #[value(name = "SIGTERM")]
+    Term,
+    #[value(name = "SIGINT")]
+    Int,
+    #[value(name = "SIGHUP")]
+    Hup,
+    #[value(name = "SIGQUIT")]
+    Quit,
+    #[value(name = "SIGKILL")]
+    Kill,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommands {
+    /// Upgrades a config file to the current config version, the old file is
+    /// kept as a backup next to it.
+    Migrate {
+        /// The file path to the config file.
+        #[arg(
+            value_parser = cli_config_file_path_validator,
+            default_value = CONFIG_FILE_NAME
+        )]
+        file_path: PathBuf,
+    },
+}
+
+fn parse_name_value(name_value: &str) -> anyhow::Result<(String, String)> {
+    match name_value.split_once('=') {
+        Some((name, value)) if !name.is_empty() => Ok((name.to_string(), value.to_string())),
+        _ => bail!("`{name_value}` must be written as `NAME=VALUE`."),
+    }
+}
+
+/// Parses a value of an application, written as `INDEX=VALUE`.
+fn parse_index_value<T>(index_value: &str) -> anyhow::Result<(usize, T)>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let Some((index, value)) = index_value.split_once('=') else {
+        bail!("`{index_value}` must be written as `INDEX=VALUE`.")
+    };
+    let Ok(index) = index.parse() else {
+        bail!("`{index}` in `{index_value}` is not an index in `start`.")
+    };
+    match value.parse() {
+        Ok(value) => Ok((index, value)),
+        Err(e) => bail!("`{value}` in `{index_value}` is not valid: {e}"),
+    }
+}
+
+fn parse_index_name_value(index_value: &str) -> anyhow::Result<(usize, (String, String))> {
+    let (index, name_value) = parse_index_value::<String>(index_value)?;
+    Ok((index, parse_name_value(&name_value)?))
 }
 
 fn cli_config_file_path_validator(file_path: &str) -> anyhow::Result<PathBuf> {
@@ -99,13 +504,13 @@ fn check_extension<P: AsRef<Path>>(file_path: P) -> anyhow::Result<()> {
             Some(ext) => {
                 let ext = match ext.to_str() {
                     Some(ext) => ext,
-                    None => bail!("Config file does not have the correct extension. Expected json, but found a extension that contains none utf-8 symbols."),
+                    None => bail!("Config file does not have the correct extension. Expected {EXPECTED_EXTENSIONS}, but found a extension that contains none utf-8 symbols."),
                 };
-                if ext != "json" {
-                    bail!("Config file does not have the correct extension. Expected json, but found `{}`.", ext.to_string());
+                if !CONFIG_FILE_EXTENSIONS.contains(&ext) {
+                    bail!("Config file does not have the correct extension. Expected {EXPECTED_EXTENSIONS}, but found `{}`.", ext.to_string());
                 }
             }
-            None => bail!("Config file does not have the correct extension. Expected json, but found no extension."),
+            None => bail!("Config file does not have the correct extension. Expected {EXPECTED_EXTENSIONS}, but found no extension."),
         }
         Ok(())
     }
@@ -184,6 +589,130 @@ mod tests_cli {
         parse_args(args).unwrap_err();
     }
 
+    #[test]
+    fn test_get_args_config_migrate() {
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(["config".into(), "migrate".into(), "test.json".into()]);
+
+        match parse_args(args).unwrap() {
+            Commands::Config {
+                command: Some(ConfigCommands::Migrate { file_path }),
+                ..
+            } => assert_eq!(
+                std::env::current_dir().unwrap().join("test.json"),
+                file_path
+            ),
+            command => panic!("Expected `config migrate` but got {command:?}"),
+        }
+    }
+
+    #[test]
+    fn test_get_args_schema() {
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(["schema".into()]);
+        assert!(matches!(parse_args(args).unwrap(), Commands::Schema));
+    }
+
+    #[test]
+    fn test_get_args_validate() {
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(["validate".into()]);
+        match parse_args(args).unwrap() {
+            Commands::Validate { file_path, format } => {
+                assert_eq!(
+                    std::env::current_dir().unwrap().join(CONFIG_FILE_NAME),
+                    file_path
+                );
+                assert_eq!(DiagnosticsFormat::Human, format);
+            }
+            command => panic!("Expected `validate` but got {command:?}"),
+        }
+
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend([
+            "validate".into(),
+            "test.json".into(),
+            "--format".into(),
+            "json".into(),
+        ]);
+        match parse_args(args).unwrap() {
+            Commands::Validate { format, .. } => assert_eq!(DiagnosticsFormat::Json, format),
+            command => panic!("Expected `validate` but got {command:?}"),
+        }
+    }
+
+    #[test]
+    fn test_get_args_convert() {
+        let current_dir = std::env::current_dir().unwrap();
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(["convert".into(), "config.json".into(), "config.yml".into()]);
+        match parse_args(args).unwrap() {
+            Commands::Convert {
+                input,
+                output,
+                force_overide,
+            } => {
+                assert_eq!(current_dir.join("config.json"), input);
+                assert_eq!(current_dir.join("config.yml"), output);
+                assert!(!force_overide);
+            }
+            command => panic!("Expected `convert` but got {command:?}"),
+        }
+
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(["convert".into(), "config.toml".into(), "config.ini".into()]);
+        parse_args(args).unwrap_err();
+    }
+
+    #[test]
+    fn test_get_args_config_migrate_wrong_extension() {
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(["config".into(), "migrate".into(), "test.apa".into()]);
+
+        parse_args(args).unwrap_err();
+    }
+
+    #[test]
+    fn test_get_args_config_file_is_not_a_command() {
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(["config".into(), "test.json".into()]);
+
+        match parse_args(args).unwrap() {
+            Commands::Config {
+                file_path,
+                command: None,
+                ..
+            } => assert_eq!(
+                std::env::current_dir().unwrap().join("test.json"),
+                file_path
+            ),
+            command => panic!("Expected `config` but got {command:?}"),
+        }
+    }
+
+    #[test]
+    fn test_get_args_config_print_resolved() {
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend([
+            "config".into(),
+            "test.yml".into(),
+            "--print-resolved".into(),
+        ]);
+
+        match parse_args(args).unwrap() {
+            Commands::Config {
+                file_path,
+                print_resolved,
+                command: None,
+                ..
+            } => {
+                assert_eq!(std::env::current_dir().unwrap().join("test.yml"), file_path);
+                assert!(print_resolved);
+            }
+            command => panic!("Expected `config` but got {command:?}"),
+        }
+    }
+
     #[test]
     fn test_get_args_start_not_exit() {
         let mut args = std::env::args_os().collect::<Vec<_>>();
@@ -198,12 +727,301 @@ mod tests_cli {
         parse_args(args).unwrap();
     }
 
+    #[test]
+    fn test_get_args_start_with_exit_on_many() {
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend([
+            "start".into(),
+            "a".into(),
+            "b".into(),
+            "-e".into(),
+            "1".into(),
+            "--exit-on".into(),
+            "a".into(),
+            "--exit-on-any-failed".into(),
+        ]);
+        match parse_args(args).unwrap() {
+            Commands::Start {
+                start,
+                exit_on,
+                exit_on_any_failed,
+                ..
+            } => {
+                assert_eq!(vec!["a", "b"], start);
+                assert_eq!(vec!["1", "a"], exit_on);
+                assert!(exit_on_any_failed);
+            }
+            command => panic!("Expected `start` but got {command:?}"),
+        }
+    }
+
+    #[test]
+    fn test_get_args_start_with_log_path() {
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend([
+            "start".into(),
+            "a".into(),
+            "--log-path".into(),
+            "logs/{name}.log".into(),
+        ]);
+        match parse_args(args).unwrap() {
+            Commands::Start { log_path, .. } => {
+                assert_eq!(Some("logs/{name}.log".to_string()), log_path)
+            }
+            command => panic!("Expected `start` but got {command:?}"),
+        }
+    }
+
+    #[test]
+    fn test_get_args_start_with_options() {
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(
+            [
+                "start",
+                "a",
+                "b",
+                "--cwd",
+                "web",
+                "--cascade-kill",
+                "--cgroups",
+                "per-app",
+                "--stop-signal",
+                "SIGINT",
+                "--stop-timeout",
+                "100",
+                "--env",
+                "PORT=80",
+                "--env",
+                "URL=http://localhost?a=b",
+                "--env-file",
+                ".env",
+                "--clean-env",
+                "--env-allow",
+                "PATH",
+                "--var",
+                "ROOT=/srv",
+                "--single-instance",
+                "refuse",
+                "--exit-on-all",
+                "0",
+                "--exit-on-all-exited",
+            ]
+            .map(OsString::from),
+        );
+        match parse_args(args).unwrap() {
+            Commands::Start {
+                options,
+                exit_on_all,
+                exit_on_all_exited,
+                ..
+            } => {
+                assert_eq!(
+                    StartOptions {
+                        cwd: Some("web".into()),
+                        cascade_kill: true,
+                        cgroups: Some(CgroupsArg::PerApp),
+                        stop_signal: Some(StopSignalArg::Int),
+                        stop_timeout: Some(100),
+                        env: vec![
+                            ("PORT".into(), "80".into()),
+                            ("URL".into(), "http://localhost?a=b".into())
+                        ],
+                        env_file: vec![".env".into()],
+                        clean_env: true,
+                        env_allow: vec!["PATH".into()],
+                        var: vec![("ROOT".into(), "/srv".into())],
+                        single_instance: Some(SingleInstanceArg::Refuse),
+                        apps: AppOptions::default(),
+                    },
+                    *options
+                );
+                assert_eq!(vec!["0"], exit_on_all);
+                assert!(exit_on_all_exited);
+            }
+            command => panic!("Expected `start` but got {command:?}"),
+        }
+
+        for bad_args in [
+            ["start", "a", "--env", "PORT"],
+            ["start", "a", "--env-allow", "PATH"],
+            ["start", "a", "--log-keep", "3"],
+        ] {
+            let mut args = std::env::args_os().collect::<Vec<_>>();
+            args.extend(bad_args.map(OsString::from));
+            parse_args(args).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_get_args_start_with_app_options() {
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(
+            [
+                "start",
+                "api",
+                "web",
+                "--name",
+                "0=api",
+                "--app-cwd",
+                "1=web",
+                "--depends-on",
+                "1=api",
+                "--ready",
+                "0=tcp:localhost:8080",
+                "--ready",
+                "1=http://localhost/health",
+                "--ready-timeout",
+                "0=500",
+                "--restart",
+                "0=on-failure",
+                "--max-retries",
+                "0=3",
+                "--nice",
+                "1=-5",
+                "--max-memory",
+                "1=512MB",
+                "--app-env",
+                "0=PORT=8080",
+                "--app-env-file",
+                "1=web.env",
+                "--app-log-path",
+                "0=logs/api.{stream}.log",
+                "--write-config",
+                "adhoc.toml",
+            ]
+            .map(OsString::from),
+        );
+        match parse_args(args).unwrap() {
+            Commands::Start {
+                options,
+                write_config,
+                ..
+            } => {
+                assert_eq!(
+                    AppOptions {
+                        name: vec![(0, "api".into())],
+                        app_cwd: vec![(1, "web".into())],
+                        depends_on: vec![(1, "api".into())],
+                        ready: vec![
+                            (0, ReadyCheckArg::Tcp("localhost:8080".into())),
+                            (1, ReadyCheckArg::Http("http://localhost/health".into()))
+                        ],
+                        ready_timeout: vec![(0, 500)],
+                        restart: vec![(0, RestartArg::OnFailure)],
+                        max_retries: vec![(0, 3)],
+                        nice: vec![(1, -5)],
+                        max_memory: vec![(1, "512MB".into())],
+                        app_env: vec![(0, ("PORT".into(), "8080".into()))],
+                        app_env_file: vec![(1, "web.env".into())],
+                        app_log_path: vec![(0, "logs/api.{stream}.log".into())],
+                        ..Default::default()
+                    },
+                    options.apps
+                );
+                assert_eq!(
+                    Some(std::env::current_dir().unwrap().join("adhoc.toml")),
+                    write_config
+                );
+            }
+            command => panic!("Expected `start` but got {command:?}"),
+        }
+
+        for bad_args in [
+            ["start", "a", "--name", "web"],
+            ["start", "a", "--name", "first=web"],
+            ["start", "a", "--ready", "0=udp:localhost:53"],
+            ["start", "a", "--restart", "0=sometimes"],
+            ["start", "a", "--app-env", "0=PORT"],
+            ["start", "a", "--write-config", "adhoc.txt"],
+        ] {
+            let mut args = std::env::args_os().collect::<Vec<_>>();
+            args.extend(bad_args.map(OsString::from));
+            parse_args(args).unwrap_err();
+        }
+    }
+
+    #[test]
+    fn test_get_args_output_mode() {
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(["start".into(), "a".into()]);
+        match parse_args(args).unwrap() {
+            Commands::Start { output, .. } => assert_eq!(OutputArgs::default(), output),
+            command => panic!("Expected `start` but got {command:?}"),
+        }
+
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend([
+            "config".into(),
+            "test.json".into(),
+            "--output".into(),
+            "prefixed".into(),
+            "--timestamps".into(),
+        ]);
+        match parse_args(args).unwrap() {
+            Commands::Config { output, .. } => assert_eq!(
+                OutputArgs {
+                    output: OutputMode::Prefixed,
+                    timestamps: true
+                },
+                output
+            ),
+            command => panic!("Expected `config` but got {command:?}"),
+        }
+    }
+
+    #[test]
+    fn test_get_args_control_commands() {
+        let current_dir = std::env::current_dir().unwrap();
+
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(["status".into()]);
+        match parse_args(args).unwrap() {
+            Commands::Status { file_path } => {
+                assert_eq!(current_dir.join(CONFIG_FILE_NAME), file_path)
+            }
+            command => panic!("Expected `status` but got {command:?}"),
+        }
+
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(["stop".into(), "test.apa".into()]);
+        parse_args(args).unwrap_err();
+
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(["cleanup".into(), "test.json".into()]);
+        match parse_args(args).unwrap() {
+            Commands::Cleanup { file_path } => {
+                assert_eq!(current_dir.join("test.json"), file_path)
+            }
+            command => panic!("Expected `cleanup` but got {command:?}"),
+        }
+
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(["restart".into(), "api".into(), "test.json".into()]);
+        match parse_args(args).unwrap() {
+            Commands::Restart { app, file_path } => {
+                assert_eq!("api", app);
+                assert_eq!(current_dir.join("test.json"), file_path);
+            }
+            command => panic!("Expected `restart` but got {command:?}"),
+        }
+
+        let mut args = std::env::args_os().collect::<Vec<_>>();
+        args.extend(["logs".into(), "0".into(), "-n".into(), "5".into()]);
+        match parse_args(args).unwrap() {
+            Commands::Logs { app, lines, .. } => {
+                assert_eq!("0", app);
+                assert_eq!(5, lines);
+            }
+            command => panic!("Expected `logs` but got {command:?}"),
+        }
+    }
+
     #[test]
     fn test_check_extension_no_extension() {
         let file_path = PathBuf::from_str("test_file").unwrap();
         let err_str = check_extension(file_path).unwrap_err().to_string();
 
-        assert_eq!("Config file does not have the correct extension. Expected json, but found no extension.".to_string(), err_str);
+        assert_eq!("Config file does not have the correct extension. Expected json, toml, yaml or yml, but found no extension.".to_string(), err_str);
     }
 
     #[test]
@@ -212,7 +1030,7 @@ mod tests_cli {
         let err_str = check_extension(file_path).unwrap_err().to_string();
 
         assert_eq!(
-            "Config file does not have the correct extension. Expected json, but found `exe`."
+            "Config file does not have the correct extension. Expected json, toml, yaml or yml, but found `exe`."
                 .to_string(),
             err_str
         );
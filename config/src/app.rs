@@ -0,0 +1,366 @@
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{interpolation::Variables, Limits, LogConfig, ReadyProbe, RestartPolicy};
+
+/// An application in `start`.
+///
+/// In the config file an application is either written as a plain command
+/// line string, e.g. `"node server.js --port 80"`, or as an object where
+/// every setting can be given separately. The plain string is a shorthand for
+/// an object that only has `command` set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct App {
+    // This is the name that is used when we refer to this application.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    // This is the program to start. If `args` is not given this is a command
+    // line that is split the same way a shell would split it.
+    command: String,
+    // These are the arguments given to `command`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    args: Option<Vec<String>>,
+    // This is the current working directory of this application. If it is
+    // relative it is relative to the `cwd` of the config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cwd: Option<PathBuf>,
+    // These are extra environment variables given to this application, they
+    // can use variables like the `env` of the config.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+    // These are dotenv files with extra environment variables for this
+    // application, they come after the ones of the config. If they are
+    // relative they are relative to the config file.
+    #[serde(
+        default,
+        deserialize_with = "crate::env::deserialize_env_files",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    #[schemars(schema_with = "crate::env::env_files_schema")]
+    env_file: Vec<PathBuf>,
+    // This is if the application should be started at all.
+    #[serde(default = "default_enabled", skip_serializing_if = "is_enabled")]
+    enabled: bool,
+    // These are the applications that have to be started before this one.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    depends_on: Vec<AppRef>,
+    // This is how we know that this application is ready, so the
+    // applications after it in the start order can be started.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    ready: Option<ReadyProbe>,
+    // This is when and how this application is started again after it
+    // exited.
+    #[serde(default, skip_serializing_if = "RestartPolicy::is_default")]
+    restart: RestartPolicy,
+    // This is where the output of this application is written to, instead
+    // of the `logs` of the config.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    logs: Option<LogConfig>,
+    // These are the resources this application may use.
+    #[serde(default, skip_serializing_if = "Limits::is_empty")]
+    limits: Limits,
+}
+
+/// A reference to an app in `start`, either by its index or by its name.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum AppRef {
+    Index(usize),
+    Name(String),
+}
+
+impl Display for AppRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppRef::Index(index) => write!(f, "{index}"),
+            AppRef::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+impl From<usize> for AppRef {
+    fn from(index: usize) -> Self {
+        AppRef::Index(index)
+    }
+}
+
+impl From<&str> for AppRef {
+    fn from(name: &str) -> Self {
+        AppRef::Name(name.into())
+    }
+}
+
+impl FromStr for AppRef {
+    type Err = Infallible;
+
+    /// A number is an index, everything else is a name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.parse() {
+            Ok(index) => AppRef::Index(index),
+            Err(_) => AppRef::Name(s.into()),
+        })
+    }
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
+
+impl App {
+    /// Creates a new application from a command line.
+    pub fn new<S: Into<String>>(command: S) -> App {
+        App {
+            name: None,
+            command: command.into(),
+            args: None,
+            cwd: None,
+            env: Default::default(),
+            env_file: Default::default(),
+            enabled: true,
+            depends_on: Default::default(),
+            ready: None,
+            restart: Default::default(),
+            logs: None,
+            limits: Default::default(),
+        }
+    }
+
+    pub fn with_name<S: Into<String>>(mut self, name: S) -> App {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_args(mut self, args: Vec<String>) -> App {
+        self.args = Some(args);
+        self
+    }
+
+    pub fn with_cwd<P: Into<PathBuf>>(mut self, cwd: P) -> App {
+        self.cwd = Some(cwd.into());
+        self
+    }
+
+    pub fn with_env<K: Into<String>, V: Into<String>>(mut self, key: K, value: V) -> App {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn with_env_file<P: Into<PathBuf>>(mut self, path: P) -> App {
+        self.env_file.push(path.into());
+        self
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> App {
+        self.enabled = enabled;
+        self
+    }
+
+    pub fn with_depends_on(mut self, depends_on: Vec<AppRef>) -> App {
+        self.depends_on = depends_on;
+        self
+    }
+
+    pub fn with_ready(mut self, ready: ReadyProbe) -> App {
+        self.ready = Some(ready);
+        self
+    }
+
+    pub fn with_restart(mut self, restart: RestartPolicy) -> App {
+        self.restart = restart;
+        self
+    }
+
+    pub fn with_logs(mut self, logs: LogConfig) -> App {
+        self.logs = Some(logs);
+        self
+    }
+
+    pub fn with_limits(mut self, limits: Limits) -> App {
+        self.limits = limits;
+        self
+    }
+
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn get_command(&self) -> &str {
+        self.command.as_str()
+    }
+
+    pub fn get_args(&self) -> Option<&[String]> {
+        self.args.as_deref()
+    }
+
+    pub fn get_cwd(&self) -> Option<&Path> {
+        self.cwd.as_deref()
+    }
+
+    pub fn get_env(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+
+    pub fn get_env_files(&self) -> &[PathBuf] {
+        self.env_file.as_slice()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn get_depends_on(&self) -> &[AppRef] {
+        self.depends_on.as_slice()
+    }
+
+    pub fn get_ready(&self) -> Option<&ReadyProbe> {
+        self.ready.as_ref()
+    }
+
+    pub fn get_restart(&self) -> &RestartPolicy {
+        &self.restart
+    }
+
+    pub fn get_logs(&self) -> Option<&LogConfig> {
+        self.logs.as_ref()
+    }
+
+    pub fn get_limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Returns the program followed by its arguments, or `None` if `command`
+    /// is a command line that could not be split.
+    pub fn get_cmd_vec(&self) -> Option<Vec<String>> {
+        match &self.args {
+            Some(args) => Some(
+                std::iter::once(self.command.clone())
+                    .chain(args.iter().cloned())
+                    .collect(),
+            ),
+            None => shlex::split(&self.command),
+        }
+    }
+
+    /// Expands the variables in `command`, `args` and `cwd`, `index` is the
+    /// index of this application in `start`.
+    ///
+    /// A command line is split into the program and its arguments before the
+    /// variables in it are expanded, so a value with spaces or backslashes
+    /// stays a single argument.
+    pub(crate) fn interpolate(&mut self, variables: &mut Variables, index: usize) {
+        let location = |field: &str| format!("start[{index}].{field}");
+        let command_line = match self.args {
+            None if self.command.contains("${") => shlex::split(&self.command),
+            _ => None,
+        };
+        if let Some(command_line) = command_line {
+            let mut cmd_vec = command_line
+                .iter()
+                .map(|part| variables.expand(part, &location("command"), Some(index)));
+            self.command = cmd_vec.next().unwrap_or_default();
+            self.args = Some(cmd_vec.collect());
+        } else {
+            self.command = variables.expand(&self.command, &location("command"), Some(index));
+            if let Some(args) = &mut self.args {
+                for (arg_index, arg) in args.iter_mut().enumerate() {
+                    *arg = variables.expand(
+                        arg,
+                        &location(&format!("args[{arg_index}]")),
+                        Some(index),
+                    );
+                }
+            }
+        }
+        if let Some(cwd) = &mut self.cwd {
+            *cwd = variables.expand_path(cwd, &location("cwd"), Some(index));
+        }
+    }
+
+    /// Returns true if this application can be written as a plain command
+    /// line string without losing any settings.
+    fn is_shorthand(&self) -> bool {
+        self.name.is_none()
+            && self.args.is_none()
+            && self.cwd.is_none()
+            && self.env.is_empty()
+            && self.env_file.is_empty()
+            && self.enabled
+            && self.depends_on.is_empty()
+            && self.ready.is_none()
+            && self.restart.is_default()
+            && self.logs.is_none()
+            && self.limits.is_empty()
+    }
+}
+
+impl From<String> for App {
+    fn from(command: String) -> Self {
+        App::new(command)
+    }
+}
+
+impl From<&str> for App {
+    fn from(command: &str) -> Self {
+        App::new(command)
+    }
+}
+
+/// (De)serializes `start`, where every application is either a command line
+/// string or an object.
+pub(crate) mod start_serde {
+    use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::App;
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    #[serde(untagged)]
+    #[schemars(rename = "StartEntry")]
+    enum AppRepr {
+        CommandLine(String),
+        Full(Box<App>),
+    }
+
+    pub(crate) fn serialize<S: Serializer>(
+        start: &[App],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(start.iter().map(|app| {
+            if app.is_shorthand() {
+                AppRepr::CommandLine(app.command.clone())
+            } else {
+                AppRepr::Full(Box::new(app.clone()))
+            }
+        }))
+    }
+
+    /// Returns the schema of `start`.
+    pub(crate) fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        gen.subschema_for::<Vec<AppRepr>>()
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<App>, D::Error> {
+        Ok(Vec::<AppRepr>::deserialize(deserializer)?
+            .into_iter()
+            .map(|repr| match repr {
+                AppRepr::CommandLine(command) => App::new(command),
+                AppRepr::Full(app) => *app,
+            })
+            .collect())
+    }
+}
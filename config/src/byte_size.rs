@@ -0,0 +1,102 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::bail;
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const UNITS: [(&str, u64); 4] = [
+    ("GB", 1024 * 1024 * 1024),
+    ("MB", 1024 * 1024),
+    ("KB", 1024),
+    ("B", 1),
+];
+
+/// An amount of bytes.
+///
+/// In the config file it is written either as a number of bytes or as a
+/// string with a unit, e.g. `"10MB"`. The units `KB`, `MB` and `GB` are powers
+/// of 1024.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteSize(u64);
+
+impl ByteSize {
+    pub fn new(bytes: u64) -> ByteSize {
+        ByteSize(bytes)
+    }
+
+    pub fn get_bytes(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for ByteSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Uses the biggest unit that the size can be written in exactly.
+        let (unit, size) = UNITS
+            .iter()
+            .find(|(_, size)| self.0.is_multiple_of(*size) && self.0 != 0)
+            .unwrap_or(&("B", 1));
+        write!(f, "{}{unit}", self.0 / size)
+    }
+}
+
+impl FromStr for ByteSize {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let Some((unit, size)) = UNITS
+            .iter()
+            .find(|(unit, _)| s.to_ascii_uppercase().ends_with(unit))
+        else {
+            return match s.parse() {
+                Ok(bytes) => Ok(ByteSize(bytes)),
+                Err(_) => {
+                    bail!("`{s}` is not a size, it must be written as e.g. `512KB` or `10MB`.")
+                }
+            };
+        };
+        let number = s[..s.len() - unit.len()].trim();
+        match number
+            .parse::<u64>()
+            .ok()
+            .and_then(|n| n.checked_mul(*size))
+        {
+            Some(bytes) => Ok(ByteSize(bytes)),
+            None => bail!("`{s}` is not a size, it must be written as e.g. `512KB` or `10MB`."),
+        }
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// How a [`ByteSize`] is written in the config file.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+enum ByteSizeRepr {
+    Bytes(u64),
+    WithUnit(String),
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match ByteSizeRepr::deserialize(deserializer)? {
+            ByteSizeRepr::Bytes(bytes) => Ok(ByteSize(bytes)),
+            ByteSizeRepr::WithUnit(s) => s.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+impl JsonSchema for ByteSize {
+    fn schema_name() -> String {
+        "ByteSize".into()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        ByteSizeRepr::json_schema(gen)
+    }
+}
@@ -0,0 +1,411 @@
+use std::error::Error;
+
+use serde::Serialize;
+
+use toml_edit::{Item, TableLike, Value};
+
+use crate::{format, ConfigError, ConfigFormat};
+
+/// A problem with a config file, found when it is validated.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    message: String,
+    // This is the JSON pointer to the value the problem is about, like
+    // `/start/0/command`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pointer: Option<String>,
+    // This is where the problem is in the config file, the line and column
+    // start at 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    column: Option<usize>,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic that is not about a value in a config file.
+    pub fn new(message: String) -> Diagnostic {
+        Diagnostic {
+            message,
+            pointer: None,
+            line: None,
+            column: None,
+        }
+    }
+
+    /// Sets the position of the diagnostic to where its pointer is in
+    /// `source`, or to the closest value around it that is in `source`.
+    pub(crate) fn with_position_in(mut self, source: &str, format: ConfigFormat) -> Diagnostic {
+        let find_pointer = match format {
+            ConfigFormat::Json => find_json_pointer,
+            ConfigFormat::Toml => find_toml_pointer,
+            ConfigFormat::Yaml => find_yaml_pointer,
+        };
+        if let Some(offset) = self.pointer.as_deref().and_then(|pointer| {
+            let tokens: Vec<String> = pointer
+                .split('/')
+                .skip(1)
+                .map(|token| token.replace("~1", "/").replace("~0", "~"))
+                .collect();
+            find_pointer(source, &tokens)
+        }) {
+            let (line, column) = format::line_column(source, offset);
+            self.line = Some(line);
+            self.column = Some(column);
+        }
+        self
+    }
+
+    pub fn get_message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn get_pointer(&self) -> Option<&str> {
+        self.pointer.as_deref()
+    }
+
+    pub fn get_line(&self) -> Option<usize> {
+        self.line
+    }
+
+    pub fn get_column(&self) -> Option<usize> {
+        self.column
+    }
+}
+
+impl From<&ConfigError> for Diagnostic {
+    fn from(e: &ConfigError) -> Diagnostic {
+        if let ConfigError::Parse {
+            line,
+            column,
+            source,
+            ..
+        } = e
+        {
+            // The position is given on its own.
+            return Diagnostic {
+                message: format::short_message(source.as_ref()),
+                pointer: None,
+                line: *line,
+                column: *column,
+            };
+        }
+
+        let mut message = e.to_string();
+        let mut source = e.source();
+        while let Some(cause) = source {
+            message += &format!(": {cause}");
+            source = cause.source();
+        }
+        Diagnostic {
+            message,
+            pointer: e.get_pointer(),
+            line: None,
+            column: None,
+        }
+    }
+}
+
+/// Turns a location like `start[0].args[1]` into the JSON pointer
+/// `/start/0/args/1`.
+pub(crate) fn location_to_pointer(location: &str) -> String {
+    let mut pointer = String::from("/");
+    for c in location.chars() {
+        match c {
+            '.' | '[' => pointer.push('/'),
+            ']' => (),
+            c => pointer.push(c),
+        }
+    }
+    pointer
+}
+
+/// Escapes `key` so it can be used as a token of a JSON pointer.
+pub(crate) fn escape_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Returns the byte offset in the JSON `source` of the value at the pointer
+/// `tokens`. If the value is not there, the offset of the deepest value on the
+/// way to it is returned. `None` is returned if `source` is not valid JSON.
+fn find_json_pointer(source: &str, tokens: &[String]) -> Option<usize> {
+    let mut scanner = Scanner { source, pos: 0 };
+    scanner.skip_whitespace();
+    scanner.find(tokens)
+}
+
+/// Like [`find_json_pointer`], for a TOML `source`. The offset of a value in a
+/// table is the offset of its key.
+fn find_toml_pointer(source: &str, tokens: &[String]) -> Option<usize> {
+    let document = toml_edit::ImDocument::parse(source).ok()?;
+    Some(find_in_toml_table(document.as_table(), tokens, 0))
+}
+
+fn find_in_toml_table(table: &dyn TableLike, tokens: &[String], offset: usize) -> usize {
+    let Some((token, rest)) = tokens.split_first() else {
+        return offset;
+    };
+    match table.get_key_value(token) {
+        Some((key, item)) => {
+            find_in_toml_item(item, rest, key.span().map_or(offset, |span| span.start))
+        }
+        None => offset,
+    }
+}
+
+fn find_in_toml_item(item: &Item, tokens: &[String], offset: usize) -> usize {
+    let Some((token, rest)) = tokens.split_first() else {
+        return offset;
+    };
+    match item {
+        Item::Table(table) => find_in_toml_table(table, tokens, offset),
+        Item::ArrayOfTables(tables) => match token.parse().ok().and_then(|index| tables.get(index))
+        {
+            Some(table) => {
+                find_in_toml_table(table, rest, table.span().map_or(offset, |span| span.start))
+            }
+            None => offset,
+        },
+        Item::Value(value) => find_in_toml_value(value, tokens, offset),
+        Item::None => offset,
+    }
+}
+
+fn find_in_toml_value(value: &Value, tokens: &[String], offset: usize) -> usize {
+    let Some((token, rest)) = tokens.split_first() else {
+        return offset;
+    };
+    match value {
+        Value::InlineTable(table) => find_in_toml_table(table, tokens, offset),
+        Value::Array(array) => match token.parse().ok().and_then(|index| array.get(index)) {
+            Some(value) => {
+                find_in_toml_value(value, rest, value.span().map_or(offset, |span| span.start))
+            }
+            None => offset,
+        },
+        _ => offset,
+    }
+}
+
+/// Like [`find_json_pointer`], for a YAML `source` in block style. The offset
+/// of a value in a mapping is the offset of its key, and the offset of an item
+/// of a sequence is the offset of its `-`. Values in flow style, like
+/// `[a, b]`, are not looked into.
+fn find_yaml_pointer(source: &str, tokens: &[String]) -> Option<usize> {
+    let mut entries = Vec::new();
+    let mut line_start = 0;
+    for line in source.split_inclusive('\n') {
+        let content = line.trim_end();
+        let mut column = content.len() - content.trim_start_matches(' ').len();
+        loop {
+            let rest = &content[column..];
+            if rest.is_empty() || rest.starts_with('#') || rest.starts_with("---") {
+                break;
+            }
+            if rest == "-" || rest.starts_with("- ") {
+                entries.push(YamlEntry {
+                    offset: line_start + column,
+                    column,
+                    key: None,
+                });
+                column += 1;
+                column += content[column..].len() - content[column..].trim_start_matches(' ').len();
+                continue;
+            }
+            if let Some(key) = format::yaml_mapping_key(rest) {
+                entries.push(YamlEntry {
+                    offset: line_start + column,
+                    column,
+                    key: Some(key),
+                });
+            }
+            break;
+        }
+        line_start += line.len();
+    }
+    Some(find_in_yaml(&entries, tokens, 0))
+}
+
+/// A key of a mapping, or an item of a sequence when `key` is `None`, in a
+/// YAML document.
+struct YamlEntry {
+    offset: usize,
+    column: usize,
+    key: Option<String>,
+}
+
+/// Finds the value at `tokens` in the value made of `entries`, `offset` is
+/// where that value is.
+fn find_in_yaml(entries: &[YamlEntry], tokens: &[String], offset: usize) -> usize {
+    let (Some((token, rest)), Some(first)) = (tokens.split_first(), entries.first()) else {
+        return offset;
+    };
+    let column = first.column;
+    let is_mapping = first.key.is_some();
+    // These are the entries of the items of the value, a sequence can be at
+    // the same column as the key of the mapping it is in.
+    let items: Vec<usize> = (0..entries.len())
+        .filter(|&index| {
+            entries[index].column == column && entries[index].key.is_some() == is_mapping
+        })
+        .collect();
+    let found = if is_mapping {
+        items
+            .iter()
+            .position(|&index| entries[index].key.as_deref() == Some(token.as_str()))
+    } else {
+        token
+            .parse()
+            .ok()
+            .filter(|&index: &usize| index < items.len())
+    };
+    let Some(item) = found else {
+        return offset;
+    };
+    let start = items[item];
+    let end = items.get(item + 1).copied().unwrap_or(entries.len());
+    find_in_yaml(&entries[start + 1..end], rest, entries[start].offset)
+}
+
+/// Walks over a JSON document without building it.
+struct Scanner<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    /// Finds the value at `tokens` from the value at the current position.
+    fn find(&mut self, tokens: &[String]) -> Option<usize> {
+        let start = self.pos;
+        let Some((token, rest)) = tokens.split_first() else {
+            return Some(start);
+        };
+        match self.peek()? {
+            b'{' => {
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.eat(b'}') {
+                    return Some(start);
+                }
+                loop {
+                    let key_start = self.pos;
+                    self.skip_string()?;
+                    let key: String =
+                        serde_json::from_str(&self.source[key_start..self.pos]).ok()?;
+                    self.skip_whitespace();
+                    self.expect(b':')?;
+                    self.skip_whitespace();
+                    if key == *token {
+                        return self.find(rest);
+                    }
+                    self.skip_value()?;
+                    if !self.next_item(b'}')? {
+                        return Some(start);
+                    }
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.eat(b']') {
+                    return Some(start);
+                }
+                let index: Option<usize> = token.parse().ok();
+                let mut item = 0;
+                loop {
+                    if Some(item) == index {
+                        return self.find(rest);
+                    }
+                    self.skip_value()?;
+                    if !self.next_item(b']')? {
+                        return Some(start);
+                    }
+                    item += 1;
+                }
+            }
+            _ => Some(start),
+        }
+    }
+
+    /// Moves past the `,` after an item of an object or array, returns false
+    /// if the object or array ended instead.
+    fn next_item(&mut self, end: u8) -> Option<bool> {
+        self.skip_whitespace();
+        if self.eat(end) {
+            return Some(false);
+        }
+        self.expect(b',')?;
+        self.skip_whitespace();
+        Some(true)
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'"' => self.skip_string(),
+            open @ (b'{' | b'[') => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                self.pos += 1;
+                self.skip_whitespace();
+                if self.eat(close) {
+                    return Some(());
+                }
+                loop {
+                    if open == b'{' {
+                        self.skip_string()?;
+                        self.skip_whitespace();
+                        self.expect(b':')?;
+                        self.skip_whitespace();
+                    }
+                    self.skip_value()?;
+                    if !self.next_item(close)? {
+                        return Some(());
+                    }
+                }
+            }
+            // Numbers, `true`, `false` and `null`.
+            _ => {
+                let len = self.source[self.pos..]
+                    .find(|c: char| c.is_whitespace() || matches!(c, ',' | '}' | ']'))
+                    .unwrap_or(self.source.len() - self.pos);
+                (len > 0).then(|| self.pos += len)
+            }
+        }
+    }
+
+    fn skip_string(&mut self) -> Option<()> {
+        self.expect(b'"')?;
+        let bytes = self.source.as_bytes();
+        while let Some(&byte) = bytes.get(self.pos) {
+            self.pos += 1;
+            match byte {
+                b'\\' => self.pos += 1,
+                b'"' => return Some(()),
+                _ => (),
+            }
+        }
+        None
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|byte| byte.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.source.as_bytes().get(self.pos).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        self.eat(byte).then_some(())
+    }
+}
@@ -0,0 +1,34 @@
+use std::path::{Path, PathBuf};
+
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{Deserialize, Deserializer};
+
+/// Reads the variables of the dotenv file at `path`, in the order they are
+/// written in.
+pub(crate) fn load_env_file(path: &Path) -> Result<Vec<(String, String)>, dotenvy::Error> {
+    dotenvy::from_path_iter(path).and_then(|vars| vars.collect())
+}
+
+/// How `envFile` is written in the config file.
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+#[schemars(rename = "EnvFiles")]
+enum EnvFilesRepr {
+    One(PathBuf),
+    Many(Vec<PathBuf>),
+}
+
+/// Returns the schema of `envFile`.
+pub(crate) fn env_files_schema(gen: &mut SchemaGenerator) -> Schema {
+    gen.subschema_for::<EnvFilesRepr>()
+}
+
+/// Deserializes `envFile`, which is either a single path or a list of paths.
+pub(crate) fn deserialize_env_files<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<PathBuf>, D::Error> {
+    Ok(match EnvFilesRepr::deserialize(deserializer)? {
+        EnvFilesRepr::One(path) => vec![path],
+        EnvFilesRepr::Many(paths) => paths,
+    })
+}
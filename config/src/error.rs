@@ -0,0 +1,339 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{diagnostic, AppRef};
+
+/// Why a config could not be read, or why it is not valid.
+///
+/// [`ConfigError::get_pointer`] tells which value of the config the error is
+/// about.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ConfigError {
+    #[error("Config file does not exist at `{}`.", .path.display())]
+    FileNotFound { path: PathBuf },
+    #[error("Got an error while trying to see if `{}` exists.", .path.display())]
+    FileAccess {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("The given path `{}` to the config, is not a valid file.", .path.display())]
+    NotAFile { path: PathBuf },
+    #[error("Could not read `{}`.", .path.display())]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    /// The config is not valid JSON, TOML or YAML, or a value in it has the
+    /// wrong type. The line and column are only known when the config was at
+    /// the current config version.
+    #[error("Something went wrong when reading `{}`.", .path.display())]
+    Parse {
+        path: PathBuf,
+        line: Option<usize>,
+        column: Option<usize>,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+    #[error("The config `{}` could not be upgraded to the current config version.", .path.display())]
+    Migration {
+        path: PathBuf,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    /// `files` are the files in the cycle, the first file is repeated at the
+    /// end.
+    #[error("The config files `extends` or `include` each other in a cycle: {}.", files_cycle(.files))]
+    InheritanceCycle { files: Vec<PathBuf> },
+
+    #[error("The variable `{name}` in `vars` is already defined by sma.")]
+    VarRedefined { name: String },
+    /// `unresolved` has the name of every variable that is not defined,
+    /// together with where it is used, like `start[0].command`.
+    #[error("The following variables are not defined:\n    {}", unresolved_list(.unresolved))]
+    VarsNotDefined { unresolved: Vec<(String, String)> },
+
+    #[error("The given current working directory (cwd) `{}` does not exits", .cwd.display())]
+    CwdNotFound { cwd: PathBuf },
+    #[error("The given current working directory (cwd) `{}` exist but is not a directory", .cwd.display())]
+    CwdNotADirectory { cwd: PathBuf },
+
+    #[error("The name of the app at index `{index}` in `start` is empty.")]
+    AppNameEmpty { index: usize },
+    #[error("The app at index `{index}` in `start` has the name `{name}`, which is already used by the app at index `{other}`.")]
+    AppNameTaken {
+        index: usize,
+        name: String,
+        other: usize,
+    },
+    #[error("The current working directory (cwd) `{}` of the app at index `{index}` in `start` is not an existing directory", .cwd.display())]
+    AppCwdNotADirectory { index: usize, cwd: PathBuf },
+    #[error("The app at index `{index}` in `start` has an invalid ready probe: {problem}")]
+    InvalidReadyProbe {
+        index: usize,
+        problem: ReadyProbeProblem,
+    },
+    #[error("The app at index `{index}` in `start` has invalid logs: {problem}")]
+    InvalidAppLogs { index: usize, problem: LogsProblem },
+    #[error("The app at index `{index}` in `start` has invalid limits: {problem}")]
+    InvalidLimits {
+        index: usize,
+        problem: LimitsProblem,
+    },
+    #[error("The app at index `{index}` in `start` has an invalid restart policy: {problem}")]
+    InvalidRestart {
+        index: usize,
+        problem: RestartProblem,
+    },
+    /// `apps` has the index in `start` of every app whose program can not be
+    /// started, together with why.
+    #[error("The programs of the following apps in `start` can not be started:\n    {}", missing_programs_list(.apps))]
+    ProgramsNotFound { apps: Vec<(usize, String)> },
+
+    #[error("The app at index `{index}` in `start` depends on `{dependency}`, which does not exist in `start`.")]
+    DependencyNotFound { index: usize, dependency: AppRef },
+    /// `label` is how the dependency is referred to, its name or its index.
+    #[error("The app at index `{index}` in `start` depends on {label}, which is not enabled.")]
+    DependencyDisabled {
+        index: usize,
+        dependency: usize,
+        label: String,
+    },
+    /// `cycle` has the indices of the apps in the cycle, the first app is
+    /// repeated at the end. `labels` are how they are referred to.
+    #[error("The apps in `start` have a dependency cycle: {}.", .labels.join(" -> "))]
+    DependencyCycle {
+        cycle: Vec<usize>,
+        labels: Vec<String>,
+    },
+
+    #[error("exitOn should not be specified if start is empty.")]
+    ExitOnWithoutStart,
+    /// `condition` is the index of the condition in `exitOn`.
+    #[error("The `exitOn` condition `{kind}` does not refer to any app in `start`.")]
+    ExitOnWithoutApps {
+        condition: usize,
+        kind: &'static str,
+    },
+    #[error("The `exitOn` arg {} points to an app in `start` that is not enabled.", exit_on_arg(.app_ref))]
+    ExitOnAppDisabled { condition: usize, app_ref: AppRef },
+    #[error("{}", exit_on_not_found(.app_ref, *.start_len))]
+    ExitOnAppNotFound {
+        condition: usize,
+        app_ref: AppRef,
+        start_len: usize,
+    },
+
+    #[error("The `logs` of the config are invalid: {problem}")]
+    InvalidLogs { problem: LogsProblem },
+    #[error("The `path` `{path_template}` of the logs must contain `{{name}}` or `{{index}}`, so every app in `start` gets its own log files.")]
+    SharedLogPath { path_template: String },
+
+    #[error("The `envAllowlist` is only used when `cleanEnv` is set.")]
+    EnvAllowlistWithoutCleanEnv,
+    /// `index` is the index in `start` of the app the env file is given to,
+    /// it is `None` for the env files of the config.
+    #[error("Could not read the env file `{}`.", .path.display())]
+    EnvFile {
+        index: Option<usize>,
+        path: PathBuf,
+        #[source]
+        source: dotenvy::Error,
+    },
+    #[error("The env file `{}` was not read.", .path.display())]
+    EnvFileNotRead { index: Option<usize>, path: PathBuf },
+    /// `unset` are the variables `name` refers to that are not set.
+    #[error("The variable `{name}` in the `env` of {} uses {}, which {} not set.",
+        env_owner(*.index),
+        .unset.iter().map(|var| format!("`${{{var}}}`")).collect::<Vec<_>>().join(", "),
+        if .unset.len() == 1 { "is" } else { "are" }
+    )]
+    EnvVarsNotSet {
+        index: Option<usize>,
+        name: String,
+        unset: Vec<String>,
+    },
+}
+
+/// Why a [`ReadyProbe`](crate::ReadyProbe) can not be used.
+#[derive(Debug, Clone, PartialEq, Error)]
+#[non_exhaustive]
+pub enum ReadyProbeProblem {
+    #[error("The `timeoutMs` of the ready probe must be greater than 0.")]
+    ZeroTimeout,
+    #[error("The tcp address `{address}` of the ready probe must be written as `host:port`.")]
+    TcpAddress { address: String },
+    #[error("The file of the ready probe is empty.")]
+    EmptyFile,
+    #[error("The regex `{pattern}` of the ready probe is not valid: {error}")]
+    Regex {
+        pattern: String,
+        error: regex::Error,
+    },
+    #[error("The url `{url}` of the ready probe must be written as `http://host[:port][/path]`.")]
+    HttpUrl { url: String },
+}
+
+/// Why a [`LogConfig`](crate::LogConfig) can not be used.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum LogsProblem {
+    #[error("The `path` of the logs is empty.")]
+    EmptyPath,
+    #[error("The `maxSize` of the logs must be greater than 0.")]
+    ZeroMaxSize,
+}
+
+/// Why [`Limits`](crate::Limits) can not be applied.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum LimitsProblem {
+    #[error("The `maxMemory` must be greater than 0B.")]
+    ZeroMaxMemory,
+    #[error("The `maxMemory` needs every app in a cgroup of its own, `cgroups` must be `perApp`.")]
+    MaxMemoryWithoutPerAppCgroups,
+    #[error("The `nice` `{nice}` must be between -20 and 19.")]
+    NiceOutOfRange { nice: i32 },
+    #[error("The `openFiles` must be greater than 0.")]
+    ZeroOpenFiles,
+}
+
+/// Why a [`RestartPolicy`](crate::RestartPolicy) can not be used.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum RestartProblem {
+    #[error("The `backoffMs` `{backoff_ms}` of the restart policy is greater than its `maxBackoffMs` `{max_backoff_ms}`.")]
+    BackoffAboveMax {
+        backoff_ms: u64,
+        max_backoff_ms: u64,
+    },
+}
+
+impl ConfigError {
+    pub(crate) fn parse<E>(path: &Path, position: Option<(usize, usize)>, source: E) -> ConfigError
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        ConfigError::Parse {
+            path: path.to_path_buf(),
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+            source: Box::new(source),
+        }
+    }
+
+    pub(crate) fn migration(path: &Path, source: anyhow::Error) -> ConfigError {
+        ConfigError::Migration {
+            path: path.to_path_buf(),
+            source: source.into(),
+        }
+    }
+
+    /// Returns the JSON pointer to the value of the config the error is about,
+    /// like `/start/0/cwd`.
+    pub fn get_pointer(&self) -> Option<String> {
+        let app = |index: &usize, field: &str| format!("/start/{index}/{field}");
+        let owner = |index: &Option<usize>| match index {
+            Some(index) => format!("/start/{index}"),
+            None => String::new(),
+        };
+        Some(match self {
+            ConfigError::FileNotFound { .. }
+            | ConfigError::FileAccess { .. }
+            | ConfigError::NotAFile { .. }
+            | ConfigError::Read { .. }
+            | ConfigError::Parse { .. }
+            | ConfigError::InheritanceCycle { .. } => return None,
+            ConfigError::Migration { .. } => "/version".into(),
+            ConfigError::VarRedefined { name } => {
+                format!("/vars/{}", diagnostic::escape_token(name))
+            }
+            ConfigError::VarsNotDefined { unresolved } => {
+                diagnostic::location_to_pointer(&unresolved.first()?.1)
+            }
+            ConfigError::CwdNotFound { .. } | ConfigError::CwdNotADirectory { .. } => "/cwd".into(),
+            ConfigError::AppNameEmpty { index } | ConfigError::AppNameTaken { index, .. } => {
+                app(index, "name")
+            }
+            ConfigError::AppCwdNotADirectory { index, .. } => app(index, "cwd"),
+            ConfigError::InvalidReadyProbe { index, .. } => app(index, "ready"),
+            ConfigError::InvalidAppLogs { index, .. } => app(index, "logs"),
+            ConfigError::InvalidLimits { index, .. } => app(index, "limits"),
+            ConfigError::InvalidRestart { index, .. } => app(index, "restart"),
+            ConfigError::ProgramsNotFound { apps } => app(&apps.first()?.0, "command"),
+            ConfigError::DependencyNotFound { index, .. }
+            | ConfigError::DependencyDisabled { index, .. } => app(index, "dependsOn"),
+            ConfigError::DependencyCycle { cycle, .. } => app(cycle.first()?, "dependsOn"),
+            ConfigError::ExitOnWithoutStart => "/exitOn".into(),
+            ConfigError::ExitOnWithoutApps { condition, .. }
+            | ConfigError::ExitOnAppDisabled { condition, .. }
+            | ConfigError::ExitOnAppNotFound { condition, .. } => format!("/exitOn/{condition}"),
+            ConfigError::InvalidLogs { .. } => "/logs".into(),
+            ConfigError::SharedLogPath { .. } => "/logs/path".into(),
+            ConfigError::EnvAllowlistWithoutCleanEnv => "/envAllowlist".into(),
+            ConfigError::EnvFile { index, .. } | ConfigError::EnvFileNotRead { index, .. } => {
+                format!("{}/envFile", owner(index))
+            }
+            ConfigError::EnvVarsNotSet { index, name, .. } => {
+                format!("{}/env/{}", owner(index), diagnostic::escape_token(name))
+            }
+        })
+    }
+}
+
+fn files_cycle(files: &[PathBuf]) -> String {
+    files
+        .iter()
+        .map(|file| format!("`{}`", file.display()))
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
+
+fn unresolved_list(unresolved: &[(String, String)]) -> String {
+    unresolved
+        .iter()
+        .map(|(name, location)| format!("`${{{name}}}` in `{location}`"))
+        .collect::<Vec<_>>()
+        .join("\n    ")
+}
+
+fn missing_programs_list(apps: &[(usize, String)]) -> String {
+    apps.iter()
+        .map(|(index, reason)| format!("index `{index}`: {reason}"))
+        .collect::<Vec<_>>()
+        .join("\n    ")
+}
+
+fn exit_on_arg(app_ref: &AppRef) -> String {
+    match app_ref {
+        AppRef::Index(index) => format!("index `{index}`"),
+        AppRef::Name(name) => format!("name `{name}`"),
+    }
+}
+
+fn exit_on_not_found(app_ref: &AppRef, start_len: usize) -> String {
+    let mut msg = format!(
+        "The `exitOn` arg {} could not be found in `start`.",
+        exit_on_arg(app_ref)
+    );
+    if let AppRef::Index(index) = app_ref {
+        if *index == start_len {
+            msg += &format!("\n\nHelp: The `exitOn` arg is `{index}` and length of `start` is {start_len}, but `start` is zero indexed. This means that to get the last element of start we use length - 1 as index. So in this case index {} is the last index.", index - 1);
+        }
+    }
+    msg
+}
+
+fn env_owner(index: Option<usize>) -> String {
+    match index {
+        Some(index) => format!("the app at index `{index}` in `start`"),
+        None => "the config".into(),
+    }
+}
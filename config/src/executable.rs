@@ -0,0 +1,92 @@
+use std::{
+    ffi::OsStr,
+    path::{Path, PathBuf},
+};
+
+use path_clean::PathClean;
+
+/// Finds the executable that `program` refers to.
+///
+/// A program that contains a path separator is resolved relative to `cwd`,
+/// every other program is looked up in the directories of `path_var`, the
+/// same way the operating system does it when the program is spawned. On
+/// error a description of why the program could not be used is returned.
+pub(crate) fn find_executable(
+    program: &str,
+    cwd: &Path,
+    path_var: Option<&OsStr>,
+) -> Result<PathBuf, String> {
+    let has_separator = program.contains('/') || (cfg!(windows) && program.contains('\\'));
+
+    if has_separator || Path::new(program).is_absolute() {
+        let path = cwd.join(program).clean();
+        return match candidates(&path).into_iter().find(|path| path.is_file()) {
+            Some(path) if is_executable(&path) => Ok(path),
+            Some(path) => Err(format!("`{}` is not executable.", path.display())),
+            None => Err(format!("`{}` does not exist.", path.display())),
+        };
+    }
+
+    // Windows also looks in the current working directory before `PATH`.
+    let dirs = cfg!(windows)
+        .then(|| cwd.to_path_buf())
+        .into_iter()
+        .chain(path_var.map(std::env::split_paths).into_iter().flatten());
+
+    let mut not_executable = None;
+    for dir in dirs {
+        for path in candidates(&dir.join(program)) {
+            if path.is_file() {
+                if is_executable(&path) {
+                    return Ok(path);
+                }
+                not_executable.get_or_insert(path);
+            }
+        }
+    }
+
+    match not_executable {
+        Some(path) => Err(format!("`{}` is not executable.", path.display())),
+        None => Err(format!("`{program}` could not be found in `PATH`.")),
+    }
+}
+
+/// Returns the paths that are tried when `path` is spawned.
+#[cfg(windows)]
+fn candidates(path: &Path) -> Vec<PathBuf> {
+    if path.extension().is_some() {
+        return vec![path.to_path_buf()];
+    }
+    let path_ext = std::env::var("PATHEXT").unwrap_or_else(|_| ".COM;.EXE;.BAT;.CMD".into());
+    std::iter::once(path.to_path_buf())
+        .chain(
+            path_ext
+                .split(';')
+                .filter(|ext| !ext.is_empty())
+                .map(|ext| {
+                    let mut path = path.as_os_str().to_owned();
+                    path.push(ext);
+                    PathBuf::from(path)
+                }),
+        )
+        .collect()
+}
+
+/// Returns the paths that are tried when `path` is spawned.
+#[cfg(not(windows))]
+fn candidates(path: &Path) -> Vec<PathBuf> {
+    vec![path.to_path_buf()]
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    path.metadata()
+        .is_ok_and(|metadata| metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
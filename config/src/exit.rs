@@ -0,0 +1,67 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::AppRef;
+
+/// A condition that makes sma stop every application and exit.
+///
+/// In the config file the conditions that refer to apps are written as
+/// `{"any": ["api", 2]}` or `{"all": ["api", 2]}`, the others as plain
+/// strings, e.g. `"anyFailed"`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ExitCondition {
+    /// Any of the apps has exited.
+    Any(Vec<AppRef>),
+    /// All of the apps have exited.
+    All(Vec<AppRef>),
+    /// Any enabled app has exited with a failure.
+    AnyFailed,
+    /// Every enabled app has exited.
+    AllExited,
+}
+
+impl ExitCondition {
+    /// Returns the apps this condition refers to.
+    pub fn get_app_refs(&self) -> &[AppRef] {
+        match self {
+            ExitCondition::Any(apps) | ExitCondition::All(apps) => apps.as_slice(),
+            ExitCondition::AnyFailed | ExitCondition::AllExited => &[],
+        }
+    }
+
+    /// Returns the name of the condition as it is written in the config file.
+    pub fn get_kind(&self) -> &'static str {
+        match self {
+            ExitCondition::Any(_) => "any",
+            ExitCondition::All(_) => "all",
+            ExitCondition::AnyFailed => "anyFailed",
+            ExitCondition::AllExited => "allExited",
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ExitCondition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        enum ExitConditionRepr {
+            Any(Vec<AppRef>),
+            All(Vec<AppRef>),
+            AnyFailed,
+            AllExited,
+        }
+
+        // YAML only reads an enum from a tag like `!any`, so the condition is
+        // read as a value first, to read `{"any": [..]}` in every format.
+        let value = serde_json::Value::deserialize(deserializer)?;
+        Ok(
+            match serde_json::from_value(value).map_err(serde::de::Error::custom)? {
+                ExitConditionRepr::Any(apps) => ExitCondition::Any(apps),
+                ExitConditionRepr::All(apps) => ExitCondition::All(apps),
+                ExitConditionRepr::AnyFailed => ExitCondition::AnyFailed,
+                ExitConditionRepr::AllExited => ExitCondition::AllExited,
+            },
+        )
+    }
+}
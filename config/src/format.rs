@@ -0,0 +1,378 @@
+use std::{error::Error, path::Path};
+
+use serde::de::DeserializeOwned;
+use toml_edit::{Item, Table, Value};
+
+use crate::ConfigError;
+
+// This is the key of the JSON Schema in a config.
+const SCHEMA: &str = "$schema";
+
+/// The formats a config file can be written in, the format of a file is
+/// chosen by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfigFormat {
+    #[default]
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Returns the format of the config file at `file_path`. Files that are
+    /// not `.toml`, `.yaml` or `.yml` are read as JSON.
+    pub fn from_path<P: AsRef<Path>>(file_path: P) -> ConfigFormat {
+        match file_path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => ConfigFormat::Toml,
+            Some("yaml" | "yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Json,
+        }
+    }
+
+    /// Reads `source`, the text of the config file at `path`, in this format.
+    pub(crate) fn parse<T: DeserializeOwned>(
+        self,
+        path: &Path,
+        source: &str,
+    ) -> Result<T, ConfigError> {
+        match self {
+            ConfigFormat::Json => serde_json::from_str(source).map_err(|e| {
+                // Errors of values that were not read from text have no position.
+                let position = (e.line() > 0).then(|| (e.line(), e.column()));
+                ConfigError::parse(path, position, e)
+            }),
+            ConfigFormat::Toml => toml::from_str(source).map_err(|e| {
+                let position = e.span().map(|span| line_column(source, span.start));
+                ConfigError::parse(path, position, e)
+            }),
+            ConfigFormat::Yaml => serde_norway::from_str(source).map_err(|e| {
+                let position = e
+                    .location()
+                    .map(|location| (location.line(), location.column()));
+                ConfigError::parse(path, position, e)
+            }),
+        }
+    }
+
+    /// Reads `source`, the text of the config file at `path`, in this format
+    /// as a value. The schema given in a comment is read as `$schema`.
+    pub(crate) fn parse_value(
+        self,
+        path: &Path,
+        source: &str,
+    ) -> Result<serde_json::Value, ConfigError> {
+        let mut value: serde_json::Value = self.parse(path, source)?;
+        if let (Some(schema), Some(object)) =
+            (self.read_schema_comment(source), value.as_object_mut())
+        {
+            if !object.contains_key(SCHEMA) {
+                *object = std::iter::once((SCHEMA.to_string(), schema.into()))
+                    .chain(std::mem::take(object))
+                    .collect();
+            }
+        }
+        Ok(value)
+    }
+
+    /// Writes `value` in this format. If `previous` is the text of the file
+    /// that is overwritten, its comments are kept where the same values are
+    /// still written.
+    ///
+    /// TOML and YAML have no `$schema` key, the schema is written in the
+    /// comment their editors look for at the top of the file.
+    pub(crate) fn render(
+        self,
+        value: &serde_json::Value,
+        previous: Option<&str>,
+    ) -> anyhow::Result<String> {
+        let Some(prefix) = self.schema_comment_prefix() else {
+            return self.render_values(value, previous);
+        };
+        let mut value = value.clone();
+        let schema = value
+            .as_object_mut()
+            .and_then(|object| object.shift_remove(SCHEMA));
+        let rendered = self.render_values(&value, previous)?;
+
+        // The schema comment of `previous` is kept with its other comments.
+        let mut is_header = true;
+        let mut output = match schema.as_ref().and_then(|schema| schema.as_str()) {
+            Some(schema) => format!("{prefix}{schema}\n"),
+            None => String::new(),
+        };
+        for line in rendered.lines() {
+            let line_start = line.trim_start();
+            is_header &= line_start.is_empty() || line_start.starts_with('#');
+            if !(is_header && line_start.starts_with(prefix)) {
+                output += line;
+                output.push('\n');
+            }
+        }
+        Ok(output)
+    }
+
+    /// Returns the start of the comment that tells editors which JSON Schema
+    /// a file in this format uses, JSON uses the `$schema` key instead.
+    fn schema_comment_prefix(self) -> Option<&'static str> {
+        match self {
+            ConfigFormat::Json => None,
+            ConfigFormat::Toml => Some("#:schema "),
+            ConfigFormat::Yaml => Some("# yaml-language-server: $schema="),
+        }
+    }
+
+    /// Returns the schema in the comments at the top of `source`.
+    fn read_schema_comment(self, source: &str) -> Option<String> {
+        let prefix = self.schema_comment_prefix()?;
+        source
+            .lines()
+            .map(str::trim)
+            .take_while(|line| line.is_empty() || line.starts_with('#'))
+            .find_map(|line| line.strip_prefix(prefix))
+            .map(|schema| schema.trim().to_string())
+    }
+
+    fn render_values(
+        self,
+        value: &serde_json::Value,
+        previous: Option<&str>,
+    ) -> anyhow::Result<String> {
+        match self {
+            ConfigFormat::Json => Ok(serde_json::to_string_pretty(value)?),
+            ConfigFormat::Toml => {
+                // TOML has no null, a missing value means the same.
+                let mut value = value.clone();
+                remove_nulls(&mut value);
+                let rendered = toml::to_string_pretty(&value)?;
+                let Some(mut document) =
+                    previous.and_then(|previous| previous.parse::<toml_edit::DocumentMut>().ok())
+                else {
+                    return Ok(rendered);
+                };
+                let mut rendered = rendered.parse::<toml_edit::DocumentMut>()?;
+                merge_table(
+                    document.as_table_mut(),
+                    std::mem::take(rendered.as_table_mut()),
+                );
+                Ok(document.to_string())
+            }
+            ConfigFormat::Yaml => {
+                let rendered = quote_yaml_booleans(&serde_norway::to_string(value)?);
+                Ok(match previous {
+                    Some(previous) => keep_yaml_comments(previous, &rendered),
+                    None => rendered,
+                })
+            }
+        }
+    }
+}
+
+/// Returns the message of an error that was returned when a config was
+/// parsed, without the position it was found at.
+pub(crate) fn short_message(e: &(dyn Error + 'static)) -> String {
+    let message = e.to_string();
+    let position = if let Some(e) = e.downcast_ref::<serde_json::Error>() {
+        format!(" at line {} column {}", e.line(), e.column())
+    } else if let Some(e) = e.downcast_ref::<toml::de::Error>() {
+        return e.message().trim_end().to_string();
+    } else if let Some(location) = e
+        .downcast_ref::<serde_norway::Error>()
+        .and_then(|e| e.location())
+    {
+        format!(" at line {} column {}", location.line(), location.column())
+    } else {
+        return message;
+    };
+    message.replacen(&position, "", 1)
+}
+
+/// Returns the line and column of the byte `offset` in `source`, both start
+/// at 1.
+pub(crate) fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+fn remove_nulls(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            map.retain(|_, value| !value.is_null());
+            map.values_mut().for_each(remove_nulls);
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(remove_nulls),
+        _ => (),
+    }
+}
+
+/// Puts the values of `new` in `old`, the comments and formatting of the
+/// values that are in both are kept.
+fn merge_table(old: &mut Table, new: Table) {
+    old.retain(|key, _| new.contains_key(key));
+    for (key, item) in new {
+        match old.get_mut(&key) {
+            Some(old_item) => merge_item(old_item, item),
+            None => {
+                old.insert(&key, item);
+            }
+        }
+    }
+}
+
+fn merge_item(old: &mut Item, new: Item) {
+    match (old, new) {
+        (Item::Table(old), Item::Table(new)) => merge_table(old, new),
+        (Item::ArrayOfTables(old), Item::ArrayOfTables(new)) => {
+            let len = new.len();
+            for (index, table) in new.into_iter().enumerate() {
+                match old.get_mut(index) {
+                    Some(old_table) => merge_table(old_table, table),
+                    None => old.push(table),
+                }
+            }
+            while old.len() > len {
+                old.remove(len);
+            }
+        }
+        (Item::Value(old), Item::Value(new)) => merge_value(old, new),
+        (old, new) => *old = new,
+    }
+}
+
+fn merge_value(old: &mut Value, new: Value) {
+    match (old, new) {
+        (Value::Array(old), Value::Array(new)) => {
+            let len = new.len();
+            for (index, value) in new.into_iter().enumerate() {
+                match old.get_mut(index) {
+                    Some(old_value) => merge_value(old_value, value),
+                    None => old.push_formatted(value),
+                }
+            }
+            while old.len() > len {
+                old.remove(len);
+            }
+        }
+        (Value::InlineTable(old), Value::InlineTable(new)) => {
+            old.retain(|key, _| new.contains_key(key));
+            for (key, value) in new {
+                match old.get_mut(&key) {
+                    Some(old_value) => merge_value(old_value, value),
+                    None => {
+                        old.insert(&key, value);
+                    }
+                }
+            }
+        }
+        (old, new) => {
+            let decor = old.decor().clone();
+            *old = new;
+            *old.decor_mut() = decor;
+        }
+    }
+}
+
+/// Adds the comments at the top of the YAML `previous`, and the comments
+/// above its top level keys, to `rendered`.
+fn keep_yaml_comments(previous: &str, rendered: &str) -> String {
+    let mut header = Vec::new();
+    let mut key_comments = Vec::new();
+    let mut comments = Vec::new();
+    for line in previous.lines() {
+        if line.trim_start().starts_with('#') {
+            comments.push(line);
+        } else if let Some(key) = yaml_key(line) {
+            if header.is_empty() && key_comments.is_empty() {
+                header = std::mem::take(&mut comments);
+            }
+            key_comments.push((key, std::mem::take(&mut comments)));
+        } else if !line.trim().is_empty() {
+            comments.clear();
+        }
+    }
+
+    let mut output: String = header.iter().map(|line| format!("{line}\n")).collect();
+    for line in rendered.lines() {
+        if let Some(key) = yaml_key(line) {
+            if let Some((_, comments)) = key_comments.iter().find(|(other, _)| *other == key) {
+                comments
+                    .iter()
+                    .for_each(|comment| output += &format!("{comment}\n"));
+            }
+        }
+        output += line;
+        output.push('\n');
+    }
+    output
+}
+
+/// Quotes the plain values of `rendered` that YAML 1.1 reads as booleans, like
+/// `cgroups: off`, serde_norway only quotes the ones of YAML 1.2.
+fn quote_yaml_booleans(rendered: &str) -> String {
+    const BOOLEANS: [&str; 6] = ["y", "yes", "n", "no", "on", "off"];
+    let mut output = String::with_capacity(rendered.len());
+    // This is the indentation of the line that starts a block scalar, the
+    // more indented lines after it are its text.
+    let mut block_indent = None;
+    for line in rendered.lines() {
+        let indent = line.len() - line.trim_start().len();
+        match block_indent {
+            Some(block_indent) if indent > block_indent || line.trim().is_empty() => {
+                output += line;
+                output.push('\n');
+                continue;
+            }
+            _ => block_indent = None,
+        }
+        let mut value = line.trim_start();
+        while let Some(rest) = value.strip_prefix("- ") {
+            value = rest;
+        }
+        if let Some((_, rest)) = value.split_once(": ") {
+            value = rest;
+        }
+        let start = line.len() - value.len();
+        if value.starts_with(['|', '>']) {
+            block_indent = Some(indent);
+        } else if BOOLEANS.contains(&value.to_lowercase().as_str()) {
+            output += &format!("{}'{value}'\n", &line[..start]);
+            continue;
+        }
+        output += line;
+        output.push('\n');
+    }
+    output
+}
+
+/// Returns the key of a top level YAML `line` like `key: value`.
+fn yaml_key(line: &str) -> Option<String> {
+    if line.starts_with(char::is_whitespace) || line.starts_with('-') {
+        return None;
+    }
+    yaml_mapping_key(line)
+}
+
+/// Returns the key if `text` starts with a key of a YAML block mapping.
+pub(crate) fn yaml_mapping_key(text: &str) -> Option<String> {
+    let (key, rest) = match text.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = text[1..].find(quote)? + 1;
+            (text[1..end].to_string(), &text[end + 1..])
+        }
+        '#' | '{' | '[' | '&' | '*' | '!' | '|' | '>' => return None,
+        _ => {
+            let end = text
+                .match_indices(':')
+                .map(|(index, _)| index)
+                .find(|&index| {
+                    text[index + 1..].is_empty() || text[index + 1..].starts_with(' ')
+                })?;
+            (text[..end].trim_end().to_string(), &text[end..])
+        }
+    };
+    let rest = rest.trim_start();
+    (rest.starts_with(':') && (rest.len() == 1 || rest[1..].starts_with(' '))).then_some(key)
+}
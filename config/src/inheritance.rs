@@ -0,0 +1,213 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde_json::{Map, Value};
+
+use crate::{interpolation, migration, Config, ConfigError, UnVerified};
+
+// These are the keys that tell which other files a config is made of.
+const EXTENDS: &str = "extends";
+const INCLUDE: &str = "include";
+
+/// Merges the files that the config `value`, read from `file_path`, `extends`
+/// and `include` into it. Returns false if the config does not use any other
+/// files.
+///
+/// The `extends` file is the base, then every `include` fragment is merged on
+/// top of it in order, and the config itself is merged last. When a value is
+/// merged on top of another:
+/// - `env` and `vars`, also the `env` of an app, are merged by their keys.
+/// - `envFile`, also the `envFile` of an app, is the files of both.
+/// - An app in `start` with the `name` of an app that is already there is
+///   merged on top of that app, every other app is added after them.
+/// - Every other value replaces the value that was there.
+///
+/// The `extends` and `include` of the config itself are kept in `value`.
+pub(crate) fn resolve(value: &mut Value, file_path: &Path) -> Result<bool, ConfigError> {
+    let Some(object) = value.as_object() else {
+        return Ok(false);
+    };
+    let own: Vec<_> = [EXTENDS, INCLUDE]
+        .into_iter()
+        .filter_map(|key| Some((key.to_string(), object.get(key)?.clone())))
+        .collect();
+    if own.is_empty() {
+        return Ok(false);
+    }
+
+    let mut files = vec![canonical_path(file_path)];
+    resolve_in(value, file_path, &mut files)?;
+    if let Some(object) = value.as_object_mut() {
+        object.extend(own);
+    }
+    Ok(true)
+}
+
+/// Removes the `extends` and `include` of a resolved config `value`.
+pub(crate) fn remove_references(value: &mut Value) {
+    if let Some(object) = value.as_object_mut() {
+        object.remove(EXTENDS);
+        object.remove(INCLUDE);
+    }
+}
+
+/// Resolves `value` from the file at `file_path`, `files` are the files that
+/// are being resolved, to find cycles.
+fn resolve_in(
+    value: &mut Value,
+    file_path: &Path,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), ConfigError> {
+    let Some(object) = value.as_object_mut() else {
+        return Ok(());
+    };
+    let extends: Option<PathBuf> = object
+        .remove(EXTENDS)
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|e| ConfigError::parse(file_path, None, e))?;
+    let include: Vec<PathBuf> = object
+        .remove(INCLUDE)
+        .map(serde_json::from_value)
+        .transpose()
+        .map_err(|e| ConfigError::parse(file_path, None, e))?
+        .unwrap_or_default();
+    if extends.is_none() && include.is_empty() {
+        return Ok(());
+    }
+
+    let file_dir = file_path.parent().unwrap_or(Path::new(""));
+    let mut merged = match extends {
+        Some(base) => load(&file_dir.join(base), files)?,
+        None => Value::Object(Map::new()),
+    };
+    for fragment in include {
+        let fragment = load(&file_dir.join(fragment), files)?;
+        merge(&mut merged, fragment, true);
+    }
+    merge(&mut merged, std::mem::take(value), true);
+    *value = merged;
+    Ok(())
+}
+
+/// Reads and resolves the config file, or fragment of one, at `file_path`.
+fn load(file_path: &Path, files: &mut Vec<PathBuf>) -> Result<Value, ConfigError> {
+    let (mut value, _) = Config::<UnVerified>::read_config_value(file_path)?;
+    let canonical = canonical_path(file_path);
+    if files.contains(&canonical) {
+        let mut cycle = files.clone();
+        cycle.push(canonical);
+        return Err(ConfigError::InheritanceCycle { files: cycle });
+    }
+
+    // A fragment does not have to say which version it is written in.
+    if value.get("version").is_some() {
+        migration::migrate(&mut value).map_err(|e| ConfigError::migration(file_path, e))?;
+    }
+    files.push(canonical);
+    resolve_in(&mut value, file_path, files)?;
+    files.pop();
+
+    if let Some(file_dir) = file_path.parent() {
+        rebase_paths(&mut value, file_dir);
+    }
+    Ok(value)
+}
+
+fn canonical_path(file_path: &Path) -> PathBuf {
+    fs::canonicalize(file_path).unwrap_or_else(|_| file_path.to_path_buf())
+}
+
+/// Makes the paths in `value` that are relative to its file absolute, so they
+/// still point to the same files when it is merged into a config in another
+/// directory.
+fn rebase_paths(value: &mut Value, file_dir: &Path) {
+    let rebase = |path: &mut Value| {
+        if let Some(relative) = path
+            .as_str()
+            .map(Path::new)
+            .filter(|path| !interpolation::has_vars(path))
+        {
+            *path = file_dir
+                .join(relative)
+                .to_string_lossy()
+                .into_owned()
+                .into();
+        }
+    };
+    let rebase_env_files = |object: &mut Map<String, Value>| match object.get_mut("envFile") {
+        Some(Value::Array(paths)) => paths.iter_mut().for_each(rebase),
+        Some(path) => rebase(path),
+        None => (),
+    };
+
+    let Some(object) = value.as_object_mut() else {
+        return;
+    };
+    if let Some(cwd) = object.get_mut("cwd") {
+        rebase(cwd);
+    }
+    rebase_env_files(object);
+    if let Some(Value::Array(start)) = object.get_mut("start") {
+        start
+            .iter_mut()
+            .filter_map(Value::as_object_mut)
+            .for_each(rebase_env_files);
+    }
+}
+
+/// Merges `over` on top of `base`, `is_config` is false when they are apps.
+fn merge(base: &mut Value, over: Value, is_config: bool) {
+    match (base, over) {
+        (Value::Object(base), Value::Object(over)) => merge_object(base, over, is_config),
+        (base, over) => *base = over,
+    }
+}
+
+fn merge_object(base: &mut Map<String, Value>, over: Map<String, Value>, is_config: bool) {
+    for (key, value) in over {
+        match (key.as_str(), base.get_mut(&key), value) {
+            ("env" | "vars", Some(Value::Object(base_vars)), Value::Object(vars)) => {
+                base_vars.extend(vars)
+            }
+            ("envFile", Some(base_files), files) => {
+                let mut all = into_list(std::mem::take(base_files));
+                all.extend(into_list(files));
+                *base_files = Value::Array(all);
+            }
+            ("start", Some(Value::Array(base_start)), Value::Array(start)) if is_config => {
+                merge_start(base_start, start)
+            }
+            (_, _, value) => {
+                base.insert(key.clone(), value);
+            }
+        }
+    }
+}
+
+/// Merges the apps of `start` on top of the apps of `base`, by their names.
+fn merge_start(base: &mut Vec<Value>, start: Vec<Value>) {
+    for app in start {
+        let same_name = app_name(&app)
+            .and_then(|name| base.iter().position(|other| app_name(other) == Some(name)));
+        match same_name {
+            Some(index) => merge(&mut base[index], app, false),
+            None => base.push(app),
+        }
+    }
+}
+
+fn app_name(app: &Value) -> Option<&str> {
+    app.get("name")?.as_str()
+}
+
+/// Turns a single value, or a list of values, into a list.
+fn into_list(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(values) => values,
+        Value::Null => vec![],
+        value => vec![value],
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+// These variables are defined by sma, they can not be defined in `vars`.
+const BUILTIN_VARS: [&str; 3] = ["CONFIG_DIR", "HOME", "APP_INDEX"];
+
+/// Replaces every `${NAME}` in `value` with what `lookup` returns for `NAME`,
+/// `$${` is written as a plain `${`.
+///
+/// Returns the names `lookup` did not know if there are any.
+pub(crate) fn expand_vars(
+    value: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, Vec<String>> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut unresolved = Vec::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            expanded.push_str(&rest[..start - 1]);
+            expanded.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 2..start + 2 + len];
+        expanded.push_str(&rest[..start]);
+        match lookup(name) {
+            Some(var) => expanded.push_str(&var),
+            None => unresolved.push(name.to_string()),
+        }
+        rest = &rest[start + 2 + len + 1..];
+    }
+    expanded.push_str(rest);
+
+    if unresolved.is_empty() {
+        Ok(expanded)
+    } else {
+        Err(unresolved)
+    }
+}
+
+/// Returns true if `path` uses any variables.
+pub(crate) fn has_vars(path: &Path) -> bool {
+    path.to_str().is_some_and(|path| path.contains("${"))
+}
+
+/// The variables that can be used in `start` and `cwd`.
+pub(crate) struct Variables<'a> {
+    // This is the directory of the config file, if the config is a file.
+    config_dir: Option<String>,
+    home: Option<String>,
+    // These are the variables defined in `vars`.
+    vars: &'a BTreeMap<String, String>,
+    // These are the variables that were not defined, with where they are
+    // used.
+    unresolved: Vec<(String, String)>,
+}
+
+impl<'a> Variables<'a> {
+    pub(crate) fn new(
+        config_file_path: Option<&Path>,
+        vars: &'a BTreeMap<String, String>,
+    ) -> Variables<'a> {
+        let config_dir = config_file_path
+            .and_then(Path::parent)
+            .and_then(|config_dir| std::path::absolute(config_dir).ok())
+            .and_then(|config_dir| config_dir.into_os_string().into_string().ok());
+        let home = std::env::var(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).ok();
+        Variables {
+            config_dir,
+            home,
+            vars,
+            unresolved: Vec::new(),
+        }
+    }
+
+    /// Returns the names in `vars` that are already defined by sma.
+    pub(crate) fn get_redefined(&self) -> Vec<&'a str> {
+        self.vars
+            .keys()
+            .map(String::as_str)
+            .filter(|name| BUILTIN_VARS.contains(name) || name.starts_with("env:"))
+            .collect()
+    }
+
+    /// Expands the variables in `value`, `app_index` is the index of the app
+    /// in `start` that `value` belongs to. The variables that are not defined
+    /// are remembered together with `location`.
+    pub(crate) fn expand(
+        &mut self,
+        value: &str,
+        location: &str,
+        app_index: Option<usize>,
+    ) -> String {
+        let lookup = |name: &str| match name {
+            "CONFIG_DIR" => self.config_dir.clone(),
+            "HOME" => self.home.clone(),
+            "APP_INDEX" => app_index.map(|index| index.to_string()),
+            _ => match name.strip_prefix("env:") {
+                Some(name) => std::env::var(name).ok(),
+                None => self.vars.get(name).cloned(),
+            },
+        };
+        match expand_vars(value, lookup) {
+            Ok(expanded) => expanded,
+            Err(unresolved) => {
+                self.unresolved.extend(
+                    unresolved
+                        .into_iter()
+                        .map(|name| (name, location.to_string())),
+                );
+                value.to_string()
+            }
+        }
+    }
+
+    /// Expands the variables in `path`, a path that is not valid unicode is
+    /// left as it is.
+    pub(crate) fn expand_path(
+        &mut self,
+        path: &Path,
+        location: &str,
+        app_index: Option<usize>,
+    ) -> PathBuf {
+        match path.to_str() {
+            Some(value) => PathBuf::from(self.expand(value, location, app_index)),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Returns the variables that were not defined, as `(name, location)`.
+    pub(crate) fn get_unresolved(&self) -> &[(String, String)] {
+        self.unresolved.as_slice()
+    }
+}
@@ -1,13 +1,45 @@
+mod app;
+mod byte_size;
+mod diagnostic;
+mod env;
+mod error;
+mod executable;
+mod exit;
+mod format;
+mod inheritance;
+mod interpolation;
+mod limits;
+mod log;
+mod migration;
+mod ready;
+mod restart;
+
+pub use app::{App, AppRef};
+pub use byte_size::ByteSize;
+pub use diagnostic::Diagnostic;
+pub use error::{ConfigError, LimitsProblem, LogsProblem, ReadyProbeProblem, RestartProblem};
+pub use exit::ExitCondition;
+pub use format::ConfigFormat;
+pub use limits::Limits;
+pub use log::LogConfig;
+pub use ready::{HttpTarget, ReadyCheck, ReadyProbe};
+pub use restart::{Restart, RestartPolicy};
+
 use std::{
+    collections::{btree_map::Entry, BTreeMap, HashMap},
+    ffi::{OsStr, OsString},
     fs,
     io::Write,
     marker::PhantomData,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use anyhow::{anyhow, bail};
 
+use interpolation::Variables;
 use path_clean::PathClean;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 pub trait VerifiedState: private::VerifiedStatePrivate {}
@@ -26,20 +58,132 @@ mod private {
     impl VerifiedStatePrivate for super::UnVerified {}
 }
 
-const CONFIG_VERSION: u32 = 1;
+const CONFIG_VERSION: u32 = 3;
+
+// This is where the JSON Schema of the current config version is published,
+// it is the `config/schema/v3.json` of this repository. Every config version
+// gets a file of its own, so the schema a config points to never changes.
+const CONFIG_SCHEMA_URL: &str =
+    "https://raw.githubusercontent.com/GabbeHags/sma/main/config/schema/v3.json";
+
+/// The signal that is sent to an application to ask it to exit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+pub enum StopSignal {
+    #[default]
+    #[serde(rename = "SIGTERM")]
+    Term,
+    #[serde(rename = "SIGINT")]
+    Int,
+    #[serde(rename = "SIGHUP")]
+    Hup,
+    #[serde(rename = "SIGQUIT")]
+    Quit,
+    // Skips the grace period and kills the application right away.
+    #[serde(rename = "SIGKILL")]
+    Kill,
+}
+
+/// How the applications are stopped when sma shuts them down.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StopPolicy {
+    // This is the signal we first send to every application that is still
+    // running.
+    signal: StopSignal,
+    // This is how many milliseconds the applications get to exit on their
+    // own before they are force killed.
+    timeout_ms: u64,
+}
+
+impl Default for StopPolicy {
+    fn default() -> Self {
+        Self {
+            signal: StopSignal::Term,
+            timeout_ms: 5000,
+        }
+    }
+}
+
+impl StopPolicy {
+    pub fn new(signal: StopSignal, timeout: Duration) -> StopPolicy {
+        StopPolicy {
+            signal,
+            timeout_ms: timeout.as_millis() as u64,
+        }
+    }
+
+    pub fn get_signal(&self) -> StopSignal {
+        self.signal
+    }
+
+    pub fn get_timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+/// What happens when a config file is started while sma is already running it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SingleInstance {
+    /// The config is started again, next to the one that is running.
+    #[default]
+    Allow,
+    /// The config is not started again and sma exits with a message.
+    Refuse,
+    /// The running sma is stopped first and then the config is started.
+    Replace,
+    /// The running sma is asked to start the apps that are not running, and
+    /// the new sma exits.
+    Forward,
+}
+
+/// Whether the apps are put in cgroups of their own, which lets a cascade kill
+/// stop every descendant. This needs a delegated cgroup v2 on Linux, without
+/// one the descendants are found by their parent pids instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum Cgroups {
+    /// The apps stay in the cgroup of sma.
+    #[default]
+    Off,
+    /// All the apps of the config share one cgroup.
+    PerConfig,
+    /// Every app gets a cgroup of its own, inside the cgroup of the config.
+    PerApp,
+}
+
+/// What [`Config::migrate_config_file`] did with the config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationOutcome {
+    /// The config was already at the current config version.
+    UpToDate,
+    /// The config was upgraded, and the old file was kept at `backup_path`.
+    Migrated {
+        from_version: u32,
+        backup_path: PathBuf,
+    },
+}
 
 #[cfg_attr(test, derive(Clone))]
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
+#[schemars(rename = "Config", bound = "State: VerifiedState")]
 pub struct Config<State: VerifiedState> {
-    // TODO: add a option to check if we start sma with a config but it is
-    // already running, should start the same config again and start all the
-    // applications, or should we exit with out starting them, or should we
-    // start them and then send the info to the first running sma, so it
-    // handles the new pids that spawned.
-
     // TODO: add a option to start some starts with a console or not.
 
+    // This is the JSON Schema of the config file, editors use it to check
+    // and complete the config.
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    schema: Option<String>,
+    // This is the config file this config is based on, relative to this
+    // config file. It is merged into this config when the config is read,
+    // so a config that has it can not be written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    extends: Option<PathBuf>,
+    // These are the fragments of a config that are merged into this config
+    // when it is read, relative to this config file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    include: Vec<PathBuf>,
     // This is the version of this config file.
     version: u32,
     // This is where we the current working directory will be for the
@@ -49,10 +193,65 @@ pub struct Config<State: VerifiedState> {
     // the processes we spawned.
     cascade_kill: bool,
     // This is the applications that we are going to spawn.
-    start: Vec<String>,
-    // This is the index to the start list of applications that we spawned,
-    // that we waiting for to exit and then we kill everything else we spawned.
-    exit_on: Option<u8>,
+    #[serde(with = "app::start_serde")]
+    #[schemars(schema_with = "app::start_serde::json_schema")]
+    start: Vec<App>,
+    // These are the conditions that we wait for, when any of them is met we
+    // kill everything else we spawned and exit.
+    #[serde(default)]
+    exit_on: Vec<ExitCondition>,
+    // This is how the applications that are still running are stopped when
+    // we exit.
+    #[serde(default)]
+    stop: StopPolicy,
+    // This is where the output of the applications is written to, an
+    // application can override it with its own `logs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    logs: Option<LogConfig>,
+    // This is what happens when this config is started while sma is already
+    // running it.
+    #[serde(default)]
+    single_instance: SingleInstance,
+    // This is if the applications are put in cgroups of their own.
+    #[serde(default)]
+    cgroups: Cgroups,
+    // These are extra environment variables given to all the applications. A
+    // value can use `${NAME}` for the variables before it or in the
+    // environment of sma, and `${env:NAME}` for the environment of sma only,
+    // like in `start`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    env: BTreeMap<String, String>,
+    // These are dotenv files with extra environment variables for all the
+    // applications. If they are relative they are relative to the config
+    // file.
+    #[serde(
+        default,
+        deserialize_with = "env::deserialize_env_files",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    #[schemars(schema_with = "env::env_files_schema")]
+    env_file: Vec<PathBuf>,
+    // This is if the applications start from an empty environment instead
+    // of the environment of sma.
+    #[serde(default, skip_serializing_if = "is_false")]
+    clean_env: bool,
+    // These are the variables of sma that are still given to the
+    // applications when `cleanEnv` is set.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    env_allowlist: Vec<String>,
+    // These are variables that can be used as `${NAME}` in `start` and `cwd`,
+    // next to the ones sma defines. They are not used in `env`, where a
+    // `${NAME}` is an environment variable.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    vars: BTreeMap<String, String>,
+    // These are `start` and `cwd` from before their variables were expanded
+    // when the config was verified.
+    #[serde(skip)]
+    uninterpolated: Option<Box<Uninterpolated>>,
+    // These are the variables of the env files, they are read when the
+    // config is verified.
+    #[serde(skip)]
+    loaded_env_files: BTreeMap<PathBuf, Vec<(String, String)>>,
     // This is the file path to the config file if it exists.
     #[serde(skip)]
     config_file_path: Option<PathBuf>,
@@ -60,14 +259,52 @@ pub struct Config<State: VerifiedState> {
     _marker: PhantomData<State>,
 }
 
+/// The `start` and `cwd` of a config as they were written, before the
+/// variables in them were expanded.
+#[derive(Debug, Clone)]
+struct Uninterpolated {
+    cwd: Option<PathBuf>,
+    start: Vec<App>,
+}
+
+/// The parts of a config that use variables that are not defined, the checks
+/// that need their values are skipped for them.
+#[derive(Debug, Default)]
+struct Unresolved {
+    cwd: bool,
+    // These are the indices of the apps in `start`.
+    apps: Vec<usize>,
+}
+
+/// The variables of the env files of a config, by the path of the file.
+type LoadedEnvFiles = BTreeMap<PathBuf, Vec<(String, String)>>;
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
 impl Default for Config<UnVerified> {
     fn default() -> Self {
         Self {
+            schema: Some(CONFIG_SCHEMA_URL.into()),
+            extends: None,
+            include: Default::default(),
             version: CONFIG_VERSION,
             cwd: None,
             cascade_kill: false,
             start: Default::default(),
             exit_on: Default::default(),
+            stop: Default::default(),
+            logs: None,
+            single_instance: Default::default(),
+            cgroups: Default::default(),
+            env: Default::default(),
+            env_file: Default::default(),
+            clean_env: false,
+            env_allowlist: Default::default(),
+            vars: Default::default(),
+            uninterpolated: None,
+            loaded_env_files: Default::default(),
             config_file_path: None,
             _marker: Default::default(),
         }
@@ -79,6 +316,14 @@ impl<State: VerifiedState> Config<State> {
         self.version
     }
 
+    pub fn get_extends(&self) -> Option<&Path> {
+        self.extends.as_deref()
+    }
+
+    pub fn get_include(&self) -> &[PathBuf] {
+        &self.include
+    }
+
     pub fn get_cwd(&self) -> Option<&Path> {
         self.cwd.as_deref()
     }
@@ -87,21 +332,263 @@ impl<State: VerifiedState> Config<State> {
         self.cascade_kill
     }
 
-    pub fn get_start(&self) -> &[String] {
+    pub fn get_start(&self) -> &[App] {
         self.start.as_slice()
     }
 
-    pub fn get_exit_on(&self) -> Option<u8> {
-        self.exit_on
+    pub fn get_exit_on(&self) -> &[ExitCondition] {
+        self.exit_on.as_slice()
+    }
+
+    pub fn get_stop(&self) -> &StopPolicy {
+        &self.stop
+    }
+
+    pub fn get_logs(&self) -> Option<&LogConfig> {
+        self.logs.as_ref()
+    }
+
+    pub fn get_single_instance(&self) -> SingleInstance {
+        self.single_instance
+    }
+
+    pub fn get_cgroups(&self) -> Cgroups {
+        self.cgroups
+    }
+
+    pub fn get_env(&self) -> &BTreeMap<String, String> {
+        &self.env
+    }
+
+    pub fn get_env_files(&self) -> &[PathBuf] {
+        self.env_file.as_slice()
+    }
+
+    pub fn get_clean_env(&self) -> bool {
+        self.clean_env
+    }
+
+    pub fn get_env_allowlist(&self) -> &[String] {
+        self.env_allowlist.as_slice()
+    }
+
+    pub fn get_vars(&self) -> &BTreeMap<String, String> {
+        &self.vars
+    }
+
+    /// Returns the path of the env file at `path`, resolved against the
+    /// directory of the config file.
+    pub fn get_env_file_path(&self, path: &Path) -> PathBuf {
+        match self.get_config_file_path().and_then(Path::parent) {
+            Some(config_dir) => config_dir.join(path).clean(),
+            None => path.to_path_buf(),
+        }
+    }
+
+    /// Returns where the output of `app` is written to, if anywhere.
+    pub fn get_app_logs<'a>(&'a self, app: &'a App) -> Option<&'a LogConfig> {
+        app.get_logs().or(self.logs.as_ref())
     }
 
     pub fn get_config_file_path(&self) -> Option<&Path> {
         self.config_file_path.as_deref()
     }
+
+    /// Returns the directory the applications are started from, which is
+    /// `cwd` if it is given and otherwise the directory of the config file.
+    pub fn get_base_dir(&self) -> Option<&Path> {
+        self.get_cwd()
+            .or_else(|| self.get_config_file_path().and_then(Path::parent))
+    }
+
+    /// Returns the current working directory of `app`, resolved against the
+    /// base directory of this config.
+    pub fn get_app_cwd(&self, app: &App) -> Option<PathBuf> {
+        app.get_cwd().map(|cwd| match self.get_base_dir() {
+            Some(base_dir) => base_dir.join(cwd).clean(),
+            None => cwd.to_path_buf(),
+        })
+    }
+
+    /// Returns the index in `start` of the app that `app_ref` refers to.
+    pub fn resolve_app_ref(&self, app_ref: &AppRef) -> Option<usize> {
+        match app_ref {
+            AppRef::Index(index) => (*index < self.start.len()).then_some(*index),
+            AppRef::Name(name) => self
+                .start
+                .iter()
+                .position(|app| app.get_name() == Some(name.as_str())),
+        }
+    }
+
+    /// Returns the indices of the apps that the app at `index` depends on.
+    pub fn get_dependencies(&self, index: usize) -> Vec<usize> {
+        self.start[index]
+            .get_depends_on()
+            .iter()
+            .filter_map(|app_ref| self.resolve_app_ref(app_ref))
+            .collect()
+    }
+
+    /// Builds the environment of the app at `index` in `start` from the
+    /// environment of sma and the `loaded` env files.
+    ///
+    /// The environment of sma is kept as it is, also the variables whose names
+    /// or values are not UTF-8.
+    fn build_app_env(
+        &self,
+        index: usize,
+        loaded: &LoadedEnvFiles,
+    ) -> Result<BTreeMap<OsString, OsString>, ConfigError> {
+        let mut app_env: BTreeMap<OsString, OsString> = std::env::vars_os()
+            .filter(|(name, _)| {
+                !self.clean_env
+                    || self
+                        .env_allowlist
+                        .iter()
+                        .any(|allowed| *name == allowed.as_str())
+            })
+            .collect();
+        app_env.extend(self.build_configured_env(index, loaded)?);
+        Ok(app_env)
+    }
+
+    /// Builds the variables that the env files and the `env` of the config
+    /// and of the app at `index` in `start` set, from the `loaded` env files.
+    fn build_configured_env(
+        &self,
+        index: usize,
+        loaded: &LoadedEnvFiles,
+    ) -> Result<BTreeMap<OsString, OsString>, ConfigError> {
+        let sma_env: BTreeMap<OsString, OsString> = std::env::vars_os().collect();
+        let mut configured: BTreeMap<OsString, OsString> = BTreeMap::new();
+
+        let app = &self.start[index];
+        let layers = [
+            (self.env_file.as_slice(), &self.env, None),
+            (app.get_env_files(), app.get_env(), Some(index)),
+        ];
+        for (files, vars, owner) in layers {
+            for file in files {
+                let path = self.get_env_file_path(file);
+                let Some(file_vars) = loaded.get(&path) else {
+                    return Err(ConfigError::EnvFileNotRead { index: owner, path });
+                };
+                configured.extend(
+                    file_vars
+                        .iter()
+                        .map(|(name, value)| (name.into(), value.into())),
+                );
+            }
+            // The variables can refer to the layers before them, and to the
+            // environment of sma even if it is not given to the app.
+            let mut expanded = Vec::new();
+            for (name, value) in vars {
+                let lookup = |var: &str| {
+                    let value = match var.strip_prefix("env:") {
+                        Some(var) => sma_env.get(OsStr::new(var)),
+                        None => configured
+                            .get(OsStr::new(var))
+                            .or_else(|| sma_env.get(OsStr::new(var))),
+                    };
+                    value.map(|value| value.to_string_lossy().into_owned())
+                };
+                match interpolation::expand_vars(value, lookup) {
+                    Ok(value) => expanded.push((name.into(), value.into())),
+                    Err(unset) => {
+                        return Err(ConfigError::EnvVarsNotSet {
+                            index: owner,
+                            name: name.clone(),
+                            unset,
+                        })
+                    }
+                }
+            }
+            configured.extend(expanded);
+        }
+        Ok(configured)
+    }
+
+    /// Returns how the app at `index` is referred to in messages.
+    pub fn get_app_label(&self, index: usize) -> String {
+        match self.start[index].get_name() {
+            Some(name) => format!("`{name}`"),
+            None => format!("index `{index}`"),
+        }
+    }
 }
 
 impl Config<Verified> {
+    /// Returns the whole environment the app at `index` in `start` is
+    /// started with.
+    ///
+    /// It is the environment of sma, or only the variables in
+    /// `envAllowlist` of it if `cleanEnv` is set. It is followed by the
+    /// `envFile` and the `env` of the config, and then by those of the app. A
+    /// variable replaces the variable with the same name that came before.
+    pub fn get_app_env(&self, index: usize) -> Result<BTreeMap<OsString, OsString>, ConfigError> {
+        self.build_app_env(index, &self.loaded_env_files)
+    }
+
+    /// Returns only the variables that the `envFile` and the `env` of the
+    /// config and of the app at `index` in `start` set, without the
+    /// environment of sma.
+    pub fn get_app_configured_env(
+        &self,
+        index: usize,
+    ) -> Result<BTreeMap<OsString, OsString>, ConfigError> {
+        self.build_configured_env(index, &self.loaded_env_files)
+    }
+
+    /// Returns true if any of the conditions in `exitOn` is met.
+    ///
+    /// `exits` has an entry for every app in `start`, it is `None` while the
+    /// app is running or if it is disabled, and `Some(success)` once the app
+    /// has exited and is not restarted again.
+    pub fn should_exit(&self, exits: &[Option<bool>]) -> bool {
+        let has_exited = |app_ref: &AppRef| {
+            self.resolve_app_ref(app_ref)
+                .is_some_and(|index| exits[index].is_some())
+        };
+        self.exit_on.iter().any(|condition| match condition {
+            ExitCondition::Any(apps) => apps.iter().any(has_exited),
+            ExitCondition::All(apps) => apps.iter().all(has_exited),
+            ExitCondition::AnyFailed => exits.contains(&Some(false)),
+            ExitCondition::AllExited => self
+                .start
+                .iter()
+                .zip(exits)
+                .all(|(app, exit)| !app.is_enabled() || exit.is_some()),
+        })
+    }
+
+    /// Returns the indices of the enabled apps in the order they should be
+    /// started in, every app comes after the apps it depends on. Apps that
+    /// do not depend on each other keep the order they have in `start`.
+    pub fn get_start_order(&self) -> Vec<usize> {
+        let mut order = Vec::new();
+        let mut started = vec![false; self.start.len()];
+        while order.len() < self.start.iter().filter(|app| app.is_enabled()).count() {
+            let next = (0..self.start.len())
+                .find(|&index| {
+                    !started[index]
+                        && self.start[index].is_enabled()
+                        && self
+                            .get_dependencies(index)
+                            .iter()
+                            .all(|&dependency| started[dependency])
+                })
+                .expect("a verified config does not have dependency cycles");
+            started[next] = true;
+            order.push(next);
+        }
+        order
+    }
+
     /// Writes this config to a file at `file_path`, if `force_overide` is true we will overide any file with that file path.
+    ///
+    /// A config that `extends` or `include`s other files can not be written,
+    /// they are merged into it so it can not be told which values are its own.
     pub fn create_file<P: AsRef<Path>>(
         &self,
         file_path: P,
@@ -111,82 +598,248 @@ impl Config<Verified> {
     }
 
     fn priv_create_file(&self, file_path: &Path, force_overide: bool) -> anyhow::Result<()> {
-        let mut file_options = fs::OpenOptions::new();
-        if force_overide {
-            file_options.create(true).truncate(true)
-        } else {
-            match file_path.try_exists() {
-                Ok(true) => {
-                    bail!(
-                        // TODO: remove this error msg, because this is not something the config cares about, this should be delegated to the caller.
-                        // "The config file `{}` already exist.\nUse flag `-f`, `--force-overide` to force a override of the config file."
-                        "The config file you are trying to create `{}` already exist.",
-                        file_path.display()
-                    )
-                }
-                Ok(false) => file_options.create_new(true),
-                Err(e) => {
-                    bail!(anyhow!(e).context(anyhow!(
-                        "Got an error while trying to check if `{}` exists.",
-                        file_path.display()
-                    )))
-                }
-            }
+        if self.extends.is_some() || !self.include.is_empty() {
+            bail!(
+                "The config `{}` is made of the files it `extends` and `include`, so it can not be written without them being merged into it. Change the files it is made of instead.",
+                file_path.display()
+            )
+        }
+        let mut value = serde_json::to_value(self)?;
+        // The file keeps the variables, not the values they were expanded to.
+        if let Some(uninterpolated) = &self.uninterpolated {
+            value["cwd"] = serde_json::to_value(&uninterpolated.cwd)?;
+            value["start"] =
+                app::start_serde::serialize(&uninterpolated.start, serde_json::value::Serializer)?;
         }
-        .write(true);
+        write_config_file(file_path, &value, force_overide)
+    }
+}
 
-        let mut file = file_options.open(file_path).or_else(|e| {
-            bail!(anyhow!(e).context(anyhow!("Could not open `{}`.", file_path.display())))
-        })?;
-        file.write_all(&serde_json::to_vec_pretty(self)?)?;
-        Ok(())
+/// Writes the config `value` to a file at `file_path`, in the format of its
+/// extension. If `force_overide` is true we will overide any file with that
+/// file path, and keep the comments of that file where we can.
+fn write_config_file(
+    file_path: &Path,
+    value: &serde_json::Value,
+    force_overide: bool,
+) -> anyhow::Result<()> {
+    let previous = force_overide
+        .then(|| fs::read_to_string(file_path).ok())
+        .flatten();
+    let contents = ConfigFormat::from_path(file_path).render(value, previous.as_deref())?;
+
+    let mut file_options = fs::OpenOptions::new();
+    if force_overide {
+        file_options.create(true).truncate(true)
+    } else {
+        match file_path.try_exists() {
+            Ok(true) => {
+                bail!(
+                    // TODO: remove this error msg, because this is not something the config cares about, this should be delegated to the caller.
+                    // "The config file `{}` already exist.\nUse flag `-f`, `--force-overide` to force a override of the config file."
+                    "The config file you are trying to create `{}` already exist.",
+                    file_path.display()
+                )
+            }
+            Ok(false) => file_options.create_new(true),
+            Err(e) => {
+                bail!(anyhow!(e).context(anyhow!(
+                    "Got an error while trying to check if `{}` exists.",
+                    file_path.display()
+                )))
+            }
+        }
     }
+    .write(true);
+
+    let mut file = file_options.open(file_path).or_else(|e| {
+        bail!(anyhow!(e).context(anyhow!("Could not open `{}`.", file_path.display())))
+    })?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
 }
 
 impl Config<UnVerified> {
     /// Reads in a config from a file at `file_path`
     pub fn from_existing_config_file<P: AsRef<Path>>(
         file_path: P,
-    ) -> anyhow::Result<Config<UnVerified>> {
+    ) -> Result<Config<UnVerified>, ConfigError> {
         Self::priv_from_existing_config_file(file_path.as_ref())
     }
 
-    fn priv_from_existing_config_file(file_path: &Path) -> anyhow::Result<Config<UnVerified>> {
+    fn priv_from_existing_config_file(file_path: &Path) -> Result<Config<UnVerified>, ConfigError> {
+        Self::from_source(file_path, &Self::read_config_source(file_path)?)
+    }
+
+    /// Reads in the config `source` of the file at `file_path`, and brings it
+    /// to the current config version.
+    fn from_source(file_path: &Path, source: &str) -> Result<Config<UnVerified>, ConfigError> {
+        let format = ConfigFormat::from_path(file_path);
+        let mut value = format.parse_value(file_path, source)?;
+        let from_version =
+            migration::migrate(&mut value).map_err(|e| ConfigError::migration(file_path, e))?;
+        let resolved = inheritance::resolve(&mut value, file_path)?;
+
+        // Only a config that is at the current version, and is not merged with
+        // other files, is read from its source, so the errors have a position.
+        let config: Config<UnVerified> = if from_version == CONFIG_VERSION && !resolved {
+            // TOML and YAML give their schema in a comment, so it is only in
+            // `value`.
+            let schema = value.get("$schema").and_then(|schema| schema.as_str());
+            Config {
+                schema: schema.map(String::from),
+                ..format.parse(file_path, source)?
+            }
+        } else {
+            serde_json::from_value(value).map_err(|e| ConfigError::parse(file_path, None, e))?
+        };
+
+        Ok(config.with_config_file_path(file_path))
+    }
+
+    /// Sets the file the config was read from, the relative paths in the
+    /// config are relative to it.
+    fn with_config_file_path(mut self, file_path: &Path) -> Config<UnVerified> {
+        // A cwd with variables is resolved once they are expanded.
+        if let Some(cwd) = self
+            .cwd
+            .as_deref()
+            .filter(|cwd| !interpolation::has_vars(cwd))
+        {
+            if let Some(file_dir) = file_path.parent() {
+                self.cwd = Some(file_dir.join(cwd).clean());
+            }
+        }
+
+        self.config_file_path = Some(file_path.to_path_buf());
+        self
+    }
+
+    /// Reads the config file at `file_path` without interpreting it, the text
+    /// of the file is returned with it.
+    fn read_config_value(file_path: &Path) -> Result<(serde_json::Value, String), ConfigError> {
+        let source = Self::read_config_source(file_path)?;
+        let value = ConfigFormat::from_path(file_path).parse_value(file_path, &source)?;
+        Ok((value, source))
+    }
+
+    /// Reads the text of the config file at `file_path`.
+    fn read_config_source(file_path: &Path) -> Result<String, ConfigError> {
+        let path = file_path.to_path_buf();
         match file_path.try_exists() {
             Ok(true) => (),
-            Ok(false) => bail!("Config file does not exist at `{}`.", file_path.display()),
-            Err(e) => {
-                bail!(anyhow!(e).context(anyhow!(
-                    "Got an error while trying to see if `{}` exists.",
-                    file_path.display()
-                )))
-            }
+            Ok(false) => return Err(ConfigError::FileNotFound { path }),
+            Err(source) => return Err(ConfigError::FileAccess { path, source }),
         }
 
         if !file_path.is_file() {
-            bail!(
-                "The given path `{}` to the config, is not a valid file.",
-                file_path.display()
-            )
+            return Err(ConfigError::NotAFile { path });
         }
 
-        let mut config: Config<UnVerified> =
-            serde_json::from_str(fs::read_to_string(file_path)?.as_str()).or_else(|e| {
-                bail!(anyhow!(e).context(anyhow!(
-                    "Something went wrong when reading `{}`.",
-                    file_path.display()
-                )))
-            })?;
+        fs::read_to_string(file_path).map_err(|source| ConfigError::Read { path, source })
+    }
 
-        if let Some(cwd) = &config.cwd {
-            if let Some(file_dir) = file_path.parent() {
-                config.cwd = Some(file_dir.join(cwd).clean());
-            }
+    /// Upgrades the config file at `file_path` in place to the current config
+    /// version. The old file is kept as a backup next to it.
+    pub fn migrate_config_file<P: AsRef<Path>>(file_path: P) -> anyhow::Result<MigrationOutcome> {
+        Self::priv_migrate_config_file(file_path.as_ref())
+    }
+
+    fn priv_migrate_config_file(file_path: &Path) -> anyhow::Result<MigrationOutcome> {
+        let (mut value, source) = Self::read_config_value(file_path)?;
+        let from_version =
+            migration::migrate(&mut value).map_err(|e| ConfigError::migration(file_path, e))?;
+        if from_version == CONFIG_VERSION {
+            return Ok(MigrationOutcome::UpToDate);
         }
 
-        config.config_file_path = Some(file_path.to_path_buf());
+        // Make sure that the migrated config can actually be used before we
+        // touch the file.
+        serde_json::from_value::<Config<UnVerified>>(value.clone()).or_else(|e| {
+            bail!(anyhow!(e).context(anyhow!(
+                "The migrated config `{}` is not valid.",
+                file_path.display()
+            )))
+        })?;
+
+        let mut backup_path = file_path.as_os_str().to_owned();
+        backup_path.push(format!(".v{from_version}.bak"));
+        let backup_path = PathBuf::from(backup_path);
+        fs::copy(file_path, &backup_path).or_else(|e| {
+            bail!(anyhow!(e).context(anyhow!(
+                "Could not create the backup `{}`.",
+                backup_path.display()
+            )))
+        })?;
+
+        let contents = ConfigFormat::from_path(file_path).render(&value, Some(&source))?;
+        fs::write(file_path, contents).or_else(|e| {
+            bail!(anyhow!(e).context(anyhow!("Could not write `{}`.", file_path.display())))
+        })?;
+
+        Ok(MigrationOutcome::Migrated {
+            from_version,
+            backup_path,
+        })
+    }
+
+    /// Writes the config file at `input` to `output`, in the format of the
+    /// extension of `output`. The config is brought to the current config
+    /// version, but is otherwise written as it is.
+    pub fn convert_config_file<P: AsRef<Path>, Q: AsRef<Path>>(
+        input: P,
+        output: Q,
+        force_overide: bool,
+    ) -> anyhow::Result<()> {
+        Self::priv_convert_config_file(input.as_ref(), output.as_ref(), force_overide)
+    }
+
+    fn priv_convert_config_file(
+        input: &Path,
+        output: &Path,
+        force_overide: bool,
+    ) -> anyhow::Result<()> {
+        let (mut value, _) = Self::read_config_value(input)?;
+        migration::migrate(&mut value).map_err(|e| ConfigError::migration(input, e))?;
+        serde_json::from_value::<Config<UnVerified>>(value.clone())
+            .map_err(|e| ConfigError::parse(input, None, e))?;
+
+        write_config_file(output, &value, force_overide)
+    }
+
+    /// Returns the config file at `file_path` with the files it `extends` and
+    /// `include` merged into it, written in the format of the file.
+    pub fn resolve_config_file<P: AsRef<Path>>(file_path: P) -> anyhow::Result<String> {
+        Self::priv_resolve_config_file(file_path.as_ref())
+    }
+
+    fn priv_resolve_config_file(file_path: &Path) -> anyhow::Result<String> {
+        let (mut value, _) = Self::read_config_value(file_path)?;
+        migration::migrate(&mut value).map_err(|e| ConfigError::migration(file_path, e))?;
+        inheritance::resolve(&mut value, file_path)?;
+        inheritance::remove_references(&mut value);
+        serde_json::from_value::<Config<UnVerified>>(value.clone())
+            .map_err(|e| ConfigError::parse(file_path, None, e))?;
+        ConfigFormat::from_path(file_path).render(&value, None)
+    }
 
-        Ok(config)
+    /// Returns the JSON Schema of the config file at the current config
+    /// version.
+    pub fn json_schema() -> String {
+        let mut schema = serde_json::to_value(schemars::schema_for!(Config<UnVerified>))
+            .expect("a schema can always be written as JSON");
+        // A config that `extends` another config gets the settings it leaves
+        // out from it, so it only has to have them when it extends nothing.
+        let inherited = ["cascadeKill", "start"];
+        if let Some(required) = schema["required"].as_array_mut() {
+            required.retain(|key| !inherited.iter().any(|inherited| key == inherited));
+        }
+        // Only a config of the current version can be checked with this
+        // schema, older ones have to be migrated first.
+        schema["properties"]["version"]["const"] = CONFIG_VERSION.into();
+        schema["if"] = serde_json::json!({ "required": ["extends"] });
+        schema["else"] = serde_json::json!({ "required": inherited });
+        serde_json::to_string_pretty(&schema).expect("a schema can always be written as JSON")
     }
 
     /// Writes a new config at `file_path` with the default settings.
@@ -203,123 +856,593 @@ impl Config<UnVerified> {
     pub fn new(
         cwd: Option<PathBuf>,
         cascade_kill: bool,
-        start: Vec<String>,
-        exit_on: Option<u8>,
+        start: Vec<App>,
+        exit_on: Vec<ExitCondition>,
     ) -> Config<UnVerified> {
         Config {
+            schema: None,
+            extends: None,
+            include: Default::default(),
             version: CONFIG_VERSION,
             cwd,
             cascade_kill,
             start,
             exit_on,
+            stop: Default::default(),
+            logs: None,
+            single_instance: Default::default(),
+            cgroups: Default::default(),
+            env: Default::default(),
+            env_file: Default::default(),
+            clean_env: false,
+            env_allowlist: Default::default(),
+            vars: Default::default(),
+            uninterpolated: None,
+            loaded_env_files: Default::default(),
             config_file_path: None,
             _marker: Default::default(),
         }
     }
 
+    /// Sets how the applications are stopped when sma exits.
+    pub fn with_stop(mut self, stop: StopPolicy) -> Config<UnVerified> {
+        self.stop = stop;
+        self
+    }
+
+    /// Sets where the output of the applications is written to.
+    pub fn with_logs(mut self, logs: LogConfig) -> Config<UnVerified> {
+        self.logs = Some(logs);
+        self
+    }
+
+    /// Sets what happens when this config is started while sma is already
+    /// running it.
+    pub fn with_single_instance(mut self, single_instance: SingleInstance) -> Config<UnVerified> {
+        self.single_instance = single_instance;
+        self
+    }
+
+    /// Sets if the applications are put in cgroups of their own.
+    pub fn with_cgroups(mut self, cgroups: Cgroups) -> Config<UnVerified> {
+        self.cgroups = cgroups;
+        self
+    }
+
+    /// Adds an environment variable that is given to all the applications.
+    pub fn with_env<K: Into<String>, V: Into<String>>(
+        mut self,
+        key: K,
+        value: V,
+    ) -> Config<UnVerified> {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Adds a dotenv file with environment variables for all the
+    /// applications.
+    pub fn with_env_file<P: Into<PathBuf>>(mut self, path: P) -> Config<UnVerified> {
+        self.env_file.push(path.into());
+        self
+    }
+
+    /// Adds a variable that can be used as `${NAME}` in `start` and `cwd`.
+    pub fn with_var<K: Into<String>, V: Into<String>>(
+        mut self,
+        name: K,
+        value: V,
+    ) -> Config<UnVerified> {
+        self.vars.insert(name.into(), value.into());
+        self
+    }
+
+    /// Makes the applications start from an empty environment, only the
+    /// variables of sma in `allowlist` are given to them.
+    pub fn with_clean_env(mut self, allowlist: Vec<String>) -> Config<UnVerified> {
+        self.clean_env = true;
+        self.env_allowlist = allowlist;
+        self
+    }
+
     /// Verifies the Config so its valid to use.
-    pub fn verify(self) -> anyhow::Result<Config<Verified>> {
-        self.validate_start()?;
-        self.validate_exit_on()?;
-        self.validate_cwd()?;
+    pub fn verify(mut self) -> Result<Config<Verified>, ConfigError> {
+        let mut errors = Vec::new();
+        let (uninterpolated, loaded_env_files) = self.check(&mut errors);
+        if let Some(error) = errors.into_iter().next() {
+            return Err(error);
+        }
         Ok(Config {
+            schema: self.schema,
+            extends: self.extends,
+            include: self.include,
             version: self.version,
             cwd: self.cwd,
             cascade_kill: self.cascade_kill,
             start: self.start,
             exit_on: self.exit_on,
+            stop: self.stop,
+            logs: self.logs,
+            single_instance: self.single_instance,
+            cgroups: self.cgroups,
+            env: self.env,
+            env_file: self.env_file,
+            clean_env: self.clean_env,
+            env_allowlist: self.env_allowlist,
+            vars: self.vars,
+            uninterpolated,
+            loaded_env_files,
             config_file_path: self.config_file_path,
             _marker: Default::default(),
         })
     }
 
-    fn validate_cwd(&self) -> anyhow::Result<()> {
-        // checks so the given cwd is an existing directory
-        if let Some(cwd) = &self.cwd {
-            if !cwd.exists() {
-                bail!(
-                    "The given current working directory (cwd) `{}` does not exits",
-                    cwd.display()
-                )
-            }
-            if !cwd.is_dir() {
-                bail!(
-                    "The given current working directory (cwd) `{}` exist but is not a directory",
-                    cwd.display()
-                )
-            }
-        }
-        Ok(())
+    /// Runs every check on the config file at `file_path`, and returns all
+    /// the problems that were found. The config is valid if there are none.
+    pub fn validate_file<P: AsRef<Path>>(file_path: P) -> Vec<Diagnostic> {
+        Self::priv_validate_file(file_path.as_ref())
     }
 
-    fn validate_start(&self) -> anyhow::Result<()> {
-        // TODO: Check if the given command actually exist
-        Ok(())
+    fn priv_validate_file(file_path: &Path) -> Vec<Diagnostic> {
+        let source = match Self::read_config_source(file_path) {
+            Ok(source) => source,
+            Err(e) => return vec![Diagnostic::from(&e)],
+        };
+        let format = ConfigFormat::from_path(file_path);
+        let mut config = match Self::from_source(file_path, &source) {
+            Ok(config) => config,
+            Err(e) => return vec![Diagnostic::from(&e).with_position_in(&source, format)],
+        };
+
+        let mut errors = Vec::new();
+        config.check(&mut errors);
+        // The values of a config that is merged with other files are not all
+        // in its own file, so they have no position.
+        let is_merged = config.extends.is_some() || !config.include.is_empty();
+        let mut diagnostics: Vec<Diagnostic> = errors
+            .iter()
+            .map(|e| {
+                if is_merged {
+                    Diagnostic::from(e)
+                } else {
+                    Diagnostic::from(e).with_position_in(&source, format)
+                }
+            })
+            .collect();
+        // The problems are shown in the order they are in the file.
+        diagnostics.sort_by_key(|diagnostic| (diagnostic.get_line(), diagnostic.get_column()));
+        diagnostics
     }
 
-    fn validate_exit_on(&self) -> anyhow::Result<()> {
-        // checks if exit_on is given then, its index must exist
-        if let Some(index) = self.exit_on {
-            if self.start.is_empty() {
-                bail!("exitOn should not be specified if start is empty.")
-            }
-            if self.start.get(index as usize).is_none() {
-                bail!({
-                    let mut msg =
-                        format!("The `exitOn` arg index `{index}` could not be found in `start`.");
-                    let len = self.start.len();
-                    if index as usize - len == 0 {
-                        let help_msg = &format!("\n\nHelp: The `exitOn` arg is `{index}` and length of `start` is {len}, but `start` is zero indexed. This means that to get the last element of start we use length - 1 as index. So in this case index {} is the last index.", index - 1);
-                        msg += help_msg;
-                    }
-                    msg
-                })
-            }
+    /// Runs every check on the config, the errors that are found are added
+    /// to `errors`.
+    ///
+    /// Returns what [`Config::interpolate`] and [`Config::validate_env`] did.
+    fn check(
+        &mut self,
+        errors: &mut Vec<ConfigError>,
+    ) -> (Option<Box<Uninterpolated>>, LoadedEnvFiles) {
+        let (uninterpolated, unresolved) = self.interpolate(errors);
+        // The programs are looked up in the `PATH` of the env of their apps,
+        // but the errors of the env are reported last.
+        let mut env_errors = Vec::new();
+        let loaded_env_files = self.validate_env(&mut env_errors);
+        self.validate_start(errors, &unresolved, &loaded_env_files);
+        self.validate_dependencies(errors);
+        self.validate_exit_on(errors);
+        self.validate_logs(errors);
+        if !unresolved.cwd {
+            self.validate_cwd(errors);
         }
-        Ok(())
+        errors.extend(env_errors);
+        (uninterpolated, loaded_env_files)
     }
-}
 
-#[cfg(test)]
-mod tests_config_version_1 {
-    use std::{fs::File, str::FromStr};
+    /// Expands the variables in `start` and `cwd`. If anything was expanded
+    /// the values from before are returned, with the parts that use
+    /// variables that are not defined.
+    ///
+    /// The variables are `${CONFIG_DIR}`, `${HOME}`, `${env:NAME}` for the
+    /// environment variables of sma, `${APP_INDEX}` inside an app, and the
+    /// variables in `vars`.
+    fn interpolate(
+        &mut self,
+        errors: &mut Vec<ConfigError>,
+    ) -> (Option<Box<Uninterpolated>>, Unresolved) {
+        let mut variables = Variables::new(self.config_file_path.as_deref(), &self.vars);
+        let redefined = variables.get_redefined();
+        for name in &redefined {
+            errors.push(ConfigError::VarRedefined {
+                name: name.to_string(),
+            });
+        }
 
-    use super::*;
-    use tempdir::TempDir;
+        let cwd = self.cwd.as_deref().map(|cwd| {
+            let expanded = variables.expand_path(cwd, "cwd", None);
+            match self.config_file_path.as_deref().and_then(Path::parent) {
+                Some(file_dir) if interpolation::has_vars(cwd) => file_dir.join(expanded).clean(),
+                _ => expanded,
+            }
+        });
+        let mut unresolved = Unresolved {
+            cwd: !variables.get_unresolved().is_empty(),
+            apps: Vec::new(),
+        };
+        let mut start = Vec::with_capacity(self.start.len());
+        for (index, app) in self.start.iter().enumerate() {
+            let unresolved_before = variables.get_unresolved().len();
+            let mut app = app.clone();
+            app.interpolate(&mut variables, index);
+            if variables.get_unresolved().len() > unresolved_before {
+                unresolved.apps.push(index);
+            }
+            start.push(app);
+        }
+        if !variables.get_unresolved().is_empty() {
+            errors.push(ConfigError::VarsNotDefined {
+                unresolved: variables.get_unresolved().to_vec(),
+            });
+        }
 
-    impl<MyState: VerifiedState, OtherState: VerifiedState> PartialEq<Config<OtherState>>
-        for Config<MyState>
-    {
-        fn eq(&self, other: &Config<OtherState>) -> bool {
-            self.version == other.version
-                && self.cwd == other.cwd
-                && self.cascade_kill == other.cascade_kill
-                && self.start == other.start
-                && self.exit_on == other.exit_on
+        if cwd == self.cwd && start == self.start {
+            return (None, unresolved);
         }
+        let cwd = std::mem::replace(&mut self.cwd, cwd);
+        let start = std::mem::replace(&mut self.start, start);
+        (Some(Box::new(Uninterpolated { cwd, start })), unresolved)
     }
 
-    #[test]
-    fn test_create_file_overwrite() {
-        let file_name = "already_exist";
-        let temp_dir = TempDir::new("test_create_file_overwrite").unwrap();
-        let path = temp_dir.path().join(file_name);
-        std::fs::write(path.as_path(), "").unwrap();
-        let config = Config::new(None, false, vec![], None).verify().unwrap();
-        config.create_file(path.as_path(), true).unwrap();
+    /// Reads the env files and checks that the environment of every enabled
+    /// app can be built, the variables of the env files are returned.
+    fn validate_env(&self, errors: &mut Vec<ConfigError>) -> LoadedEnvFiles {
+        if !self.clean_env && !self.env_allowlist.is_empty() {
+            errors.push(ConfigError::EnvAllowlistWithoutCleanEnv);
+        }
 
-        // cleanup
-        drop(temp_dir)
-    }
+        let mut loaded = BTreeMap::new();
+        let config_env_files = self.env_file.iter().map(|file| (None, file));
+        let app_env_files = self
+            .start
+            .iter()
+            .enumerate()
+            .filter(|(_, app)| app.is_enabled())
+            .flat_map(|(index, app)| {
+                app.get_env_files()
+                    .iter()
+                    .map(move |file| (Some(index), file))
+            });
+        let mut all_loaded = true;
+        for (index, file) in config_env_files.chain(app_env_files) {
+            let path = self.get_env_file_path(file);
+            if let Entry::Vacant(entry) = loaded.entry(path) {
+                match env::load_env_file(entry.key()) {
+                    Ok(vars) => {
+                        entry.insert(vars);
+                    }
+                    Err(source) => {
+                        errors.push(ConfigError::EnvFile {
+                            index,
+                            path: entry.into_key(),
+                            source,
+                        });
+                        all_loaded = false;
+                    }
+                }
+            }
+        }
+        if !all_loaded {
+            return loaded;
+        }
 
-    #[test]
+        // The env of the config is shared by every app, so only the first
+        // app that can not build its env is reported.
+        for (index, app) in self.start.iter().enumerate() {
+            if app.is_enabled() {
+                if let Err(e) = self.build_app_env(index, &loaded) {
+                    errors.push(e);
+                    break;
+                }
+            }
+        }
+        loaded
+    }
+
+    fn validate_cwd(&self, errors: &mut Vec<ConfigError>) {
+        // checks so the given cwd is an existing directory
+        if let Some(cwd) = &self.cwd {
+            if !cwd.exists() {
+                errors.push(ConfigError::CwdNotFound { cwd: cwd.clone() })
+            } else if !cwd.is_dir() {
+                errors.push(ConfigError::CwdNotADirectory { cwd: cwd.clone() })
+            }
+        }
+    }
+
+    fn validate_start(
+        &self,
+        errors: &mut Vec<ConfigError>,
+        unresolved: &Unresolved,
+        loaded: &LoadedEnvFiles,
+    ) {
+        let mut names = HashMap::new();
+        // All the apps whose program can not be started, so they can be
+        // reported at the same time.
+        let mut missing_programs = Vec::new();
+        for (index, app) in self.start.iter().enumerate() {
+            if let Some(name) = app.get_name() {
+                if name.trim().is_empty() {
+                    errors.push(ConfigError::AppNameEmpty { index });
+                } else if let Some(other) = names.insert(name, index) {
+                    errors.push(ConfigError::AppNameTaken {
+                        index,
+                        name: name.to_string(),
+                        other,
+                    });
+                }
+            }
+            if !app.is_enabled() {
+                continue;
+            }
+            // The program and cwd of an app can not be checked while they
+            // use variables that are not defined, which is already reported.
+            let is_resolved = !unresolved.cwd && !unresolved.apps.contains(&index);
+            if let Some(cwd) = self.get_app_cwd(app).filter(|_| is_resolved) {
+                if !cwd.is_dir() {
+                    errors.push(ConfigError::AppCwdNotADirectory { index, cwd });
+                }
+            }
+            if let Some(ready) = app.get_ready() {
+                if let Err(problem) = ready.validate() {
+                    errors.push(ConfigError::InvalidReadyProbe { index, problem });
+                }
+            }
+            if let Some(logs) = app.get_logs() {
+                if let Err(problem) = logs.validate() {
+                    errors.push(ConfigError::InvalidAppLogs { index, problem });
+                }
+            }
+            if let Err(problem) = app.get_limits().validate(self.cgroups) {
+                errors.push(ConfigError::InvalidLimits { index, problem });
+            }
+            if let Err(problem) = app.get_restart().validate() {
+                errors.push(ConfigError::InvalidRestart { index, problem });
+            }
+            if !is_resolved {
+                continue;
+            }
+            if let Err(reason) = self.find_program(index, loaded) {
+                missing_programs.push((index, reason));
+            }
+        }
+        if !missing_programs.is_empty() {
+            errors.push(ConfigError::ProgramsNotFound {
+                apps: missing_programs,
+            });
+        }
+    }
+
+    /// Finds the executable of the app at `index` in `start`, or returns why
+    /// it can not be started. The `loaded` env files give the `PATH` of the
+    /// app.
+    fn find_program(&self, index: usize, loaded: &LoadedEnvFiles) -> Result<PathBuf, String> {
+        let app = &self.start[index];
+        let Some(cmd_vec) = app.get_cmd_vec() else {
+            return Err(format!(
+                "The command `{}` could not be split into a program and its arguments.",
+                app.get_command()
+            ));
+        };
+        let Some(program) = cmd_vec.first().filter(|program| !program.is_empty()) else {
+            return Err("The program is empty.".into());
+        };
+
+        let current_dir = std::env::current_dir().unwrap_or_default();
+        let cwd = match self.get_app_cwd(app) {
+            Some(cwd) => current_dir.join(cwd),
+            None => current_dir.join(self.get_base_dir().unwrap_or(Path::new(""))),
+        };
+        let path_var = match self.build_app_env(index, loaded) {
+            Ok(mut app_env) => app_env.remove(OsStr::new("PATH")),
+            // An env that can not be built is reported by `validate_env`.
+            Err(_) => std::env::var_os("PATH"),
+        };
+
+        executable::find_executable(program, &cwd, path_var.as_deref()).map_err(|mut reason| {
+            if app.get_args().is_none() && app.get_command().contains('\\') {
+                reason += " This might be because \"\\\\\" was used instead of \"/\".";
+            }
+            reason
+        })
+    }
+
+    fn validate_dependencies(&self, errors: &mut Vec<ConfigError>) {
+        // checks that every dependency exists and can be started
+        for (index, app) in self.start.iter().enumerate() {
+            for app_ref in app.get_depends_on() {
+                let Some(dependency) = self.resolve_app_ref(app_ref) else {
+                    errors.push(ConfigError::DependencyNotFound {
+                        index,
+                        dependency: app_ref.clone(),
+                    });
+                    continue;
+                };
+                if app.is_enabled() && !self.start[dependency].is_enabled() {
+                    errors.push(ConfigError::DependencyDisabled {
+                        index,
+                        dependency,
+                        label: self.get_app_label(dependency),
+                    });
+                }
+            }
+        }
+
+        // checks that there are no dependency cycles, with a depth first
+        // search from every app.
+        #[derive(Clone, Copy, PartialEq)]
+        enum Visit {
+            NotVisited,
+            InProgress,
+            Done,
+        }
+
+        fn find_cycle(
+            config: &Config<UnVerified>,
+            index: usize,
+            visits: &mut [Visit],
+            path: &mut Vec<usize>,
+        ) -> Option<Vec<usize>> {
+            match visits[index] {
+                Visit::Done => return None,
+                Visit::InProgress => {
+                    let start = path.iter().position(|&i| i == index).unwrap();
+                    let mut cycle = path[start..].to_vec();
+                    cycle.push(index);
+                    return Some(cycle);
+                }
+                Visit::NotVisited => (),
+            }
+            visits[index] = Visit::InProgress;
+            path.push(index);
+            for dependency in config.get_dependencies(index) {
+                if let Some(cycle) = find_cycle(config, dependency, visits, path) {
+                    return Some(cycle);
+                }
+            }
+            path.pop();
+            visits[index] = Visit::Done;
+            None
+        }
+
+        let mut visits = vec![Visit::NotVisited; self.start.len()];
+        for index in 0..self.start.len() {
+            if let Some(cycle) = find_cycle(self, index, &mut visits, &mut vec![]) {
+                let labels = cycle
+                    .iter()
+                    .map(|&index| self.get_app_label(index))
+                    .collect();
+                errors.push(ConfigError::DependencyCycle { cycle, labels });
+                return;
+            }
+        }
+    }
+
+    fn validate_logs(&self, errors: &mut Vec<ConfigError>) {
+        let Some(logs) = &self.logs else {
+            return;
+        };
+        if let Err(problem) = logs.validate() {
+            errors.push(ConfigError::InvalidLogs { problem });
+        }
+        let apps_using_logs = self
+            .start
+            .iter()
+            .filter(|app| app.is_enabled() && app.get_logs().is_none())
+            .count();
+        if apps_using_logs > 1 && !logs.is_per_app() {
+            errors.push(ConfigError::SharedLogPath {
+                path_template: logs.get_path_template().to_string(),
+            });
+        }
+    }
+
+    fn validate_exit_on(&self, errors: &mut Vec<ConfigError>) {
+        // checks if exit_on is given then, every app it refers to must exist
+        if self.exit_on.is_empty() {
+            return;
+        }
+        if self.start.is_empty() {
+            errors.push(ConfigError::ExitOnWithoutStart);
+            return;
+        }
+        for (condition_index, condition) in self.exit_on.iter().enumerate() {
+            if condition.get_app_refs().is_empty()
+                && matches!(condition, ExitCondition::Any(_) | ExitCondition::All(_))
+            {
+                errors.push(ConfigError::ExitOnWithoutApps {
+                    condition: condition_index,
+                    kind: condition.get_kind(),
+                });
+            }
+            for app_ref in condition.get_app_refs() {
+                match self.resolve_app_ref(app_ref) {
+                    Some(index) if !self.start[index].is_enabled() => {
+                        errors.push(ConfigError::ExitOnAppDisabled {
+                            condition: condition_index,
+                            app_ref: app_ref.clone(),
+                        });
+                    }
+                    Some(_) => (),
+                    None => errors.push(ConfigError::ExitOnAppNotFound {
+                        condition: condition_index,
+                        app_ref: app_ref.clone(),
+                        start_len: self.start.len(),
+                    }),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl Config<UnVerified> {
+    /// Runs `validate` and returns the first error it found, like `verify`
+    /// would.
+    fn first_error(&self, validate: fn(&Self, &mut Vec<ConfigError>)) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+        validate(self, &mut errors);
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Runs `validate_start` as if every variable was defined.
+    fn validate_resolved_start(&self, errors: &mut Vec<ConfigError>) {
+        self.validate_start(errors, &Unresolved::default(), &LoadedEnvFiles::new());
+    }
+}
+
+#[cfg(test)]
+mod tests_config_version_1 {
+    use std::{fs::File, str::FromStr};
+
+    use super::*;
+    use tempdir::TempDir;
+
+    impl<MyState: VerifiedState, OtherState: VerifiedState> PartialEq<Config<OtherState>>
+        for Config<MyState>
+    {
+        fn eq(&self, other: &Config<OtherState>) -> bool {
+            self.version == other.version
+                && self.cwd == other.cwd
+                && self.cascade_kill == other.cascade_kill
+                && self.start == other.start
+                && self.exit_on == other.exit_on
+                && self.stop == other.stop
+                && self.logs == other.logs
+        }
+    }
+
+    #[test]
+    fn test_create_file_overwrite() {
+        let file_name = "already_exist";
+        let temp_dir = TempDir::new("test_create_file_overwrite").unwrap();
+        let path = temp_dir.path().join(file_name);
+        std::fs::write(path.as_path(), "").unwrap();
+        let config = Config::new(None, false, vec![], vec![]).verify().unwrap();
+        config.create_file(path.as_path(), true).unwrap();
+
+        // cleanup
+        drop(temp_dir)
+    }
+
+    #[test]
     fn test_create_file() {
         let file_name = "already_exist";
         let temp_dir = TempDir::new("test_create_file").unwrap();
         let path = temp_dir.path().join(file_name);
 
         std::fs::write(path.as_path(), "").unwrap();
-        let config = Config::new(None, false, vec![], None).verify().unwrap();
+        let config = Config::new(None, false, vec![], vec![]).verify().unwrap();
         let err_msg = config
             .create_file(path.as_path(), false)
             .unwrap_err()
@@ -339,7 +1462,7 @@ mod tests_config_version_1 {
 
     #[test]
     fn test_new_eq_default() {
-        let config = Config::new(None, false, vec![], None);
+        let config = Config::new(None, false, vec![], vec![]);
         let other = Config::default();
         assert_eq!(config, other);
     }
@@ -372,26 +1495,19 @@ mod tests_config_version_1 {
     #[test]
     fn test_from_existing_config_file_err_file_does_not_exist() {
         let name = "does_not_exist.json";
-        assert_eq!(
-            format!("Config file does not exist at `{}`.", name),
-            Config::from_existing_config_file(PathBuf::from_str(name).unwrap())
-                .unwrap_err()
-                .to_string()
-        )
+        assert!(matches!(
+            Config::from_existing_config_file(PathBuf::from_str(name).unwrap()),
+            Err(ConfigError::FileNotFound { path }) if path == Path::new(name)
+        ))
     }
 
     #[test]
     fn test_from_existing_config_file_err_not_json_file() {
         let temp_dir = TempDir::new("sma_config_test").unwrap();
-        assert_eq!(
-            format!(
-                "The given path `{}` to the config, is not a valid file.",
-                temp_dir.path().display()
-            ),
-            Config::from_existing_config_file(temp_dir.path())
-                .unwrap_err()
-                .to_string()
-        )
+        assert!(matches!(
+            Config::from_existing_config_file(temp_dir.path()),
+            Err(ConfigError::NotAFile { path }) if path == temp_dir.path()
+        ))
     }
 
     #[test]
@@ -399,15 +1515,10 @@ mod tests_config_version_1 {
         let temp_dir = TempDir::new("sma_config_test").unwrap();
         let config_name = temp_dir.path().join("test_config");
         fs::File::create(&config_name).unwrap();
-        assert_eq!(
-            format!(
-                "Something went wrong when reading `{}`.",
-                config_name.display()
-            ),
-            Config::from_existing_config_file(&config_name)
-                .unwrap_err()
-                .to_string()
-        )
+        assert!(matches!(
+            Config::from_existing_config_file(&config_name),
+            Err(ConfigError::Parse { path, .. }) if path == config_name
+        ))
     }
 
     #[test]
@@ -473,24 +1584,77 @@ mod tests_config_version_1 {
     fn test_default_config() {
         let config_default = Config::default();
         let config: Config<UnVerified> = Config {
-            version: 1,
+            schema: Some(CONFIG_SCHEMA_URL.into()),
+            extends: None,
+            include: vec![],
+            version: 3,
             cwd: None,
             cascade_kill: false,
             start: vec![],
-            exit_on: None,
+            exit_on: vec![],
+            stop: StopPolicy {
+                signal: StopSignal::Term,
+                timeout_ms: 5000,
+            },
+            logs: None,
+            single_instance: SingleInstance::Allow,
+            cgroups: Cgroups::Off,
+            env: BTreeMap::new(),
+            env_file: vec![],
+            clean_env: false,
+            env_allowlist: vec![],
+            vars: BTreeMap::new(),
+            uninterpolated: None,
+            loaded_env_files: BTreeMap::new(),
             config_file_path: None,
             _marker: Default::default(),
         };
         assert_eq!(config, config_default)
     }
 
+    #[test]
+    fn test_from_existing_config_file_without_stop() {
+        let temp_dir = TempDir::new("sma_config_test").unwrap();
+        let config_name = temp_dir.path().join("test_config.json");
+        fs::write(
+            &config_name,
+            r#"{"version": 1, "cwd": null, "cascadeKill": false, "start": [], "exitOn": null}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            StopPolicy::default(),
+            *Config::from_existing_config_file(&config_name)
+                .unwrap()
+                .get_stop()
+        )
+    }
+
+    #[test]
+    fn test_from_existing_config_file_with_stop() {
+        let temp_dir = TempDir::new("sma_config_test").unwrap();
+        let config_name = temp_dir.path().join("test_config.json");
+        fs::write(
+            &config_name,
+            r#"{"version": 1, "cascadeKill": false, "start": [], "stop": {"signal": "SIGINT", "timeoutMs": 100}}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            StopPolicy::new(StopSignal::Int, Duration::from_millis(100)),
+            *Config::from_existing_config_file(&config_name)
+                .unwrap()
+                .get_stop()
+        )
+    }
+
     #[test]
     fn test_validate_cwd_ok() {
         let config = Config {
             cwd: Some(".".into()),
             ..Default::default()
         };
-        config.validate_cwd().unwrap()
+        config.first_error(Config::validate_cwd).unwrap()
     }
 
     #[test]
@@ -503,13 +1667,10 @@ mod tests_config_version_1 {
             ..Default::default()
         };
 
-        assert_eq!(
-            format!(
-                "The given current working directory (cwd) `{}` exist but is not a directory",
-                file.display()
-            ),
-            config.validate_cwd().unwrap_err().to_string()
-        );
+        assert!(matches!(
+            config.first_error(Config::validate_cwd),
+            Err(ConfigError::CwdNotADirectory { cwd }) if cwd == file
+        ));
 
         // cleanup
         drop(temp_dir)
@@ -523,24 +1684,23 @@ mod tests_config_version_1 {
             ..Default::default()
         };
 
-        assert_eq!(
-            format!(
-                "The given current working directory (cwd) `{}` does not exits",
-                none_existing_dir
-            ),
-            config.validate_cwd().unwrap_err().to_string()
-        )
+        assert!(matches!(
+            config.first_error(Config::validate_cwd),
+            Err(ConfigError::CwdNotFound { cwd }) if cwd == Path::new(none_existing_dir)
+        ))
     }
 
     #[test]
     fn test_validate_exit_on_ok() {
-        assert!(Config::default().validate_exit_on().is_ok());
+        assert!(Config::default()
+            .first_error(Config::validate_exit_on)
+            .is_ok());
         assert!(Config {
-            exit_on: Some(0),
+            exit_on: vec![ExitCondition::Any(vec![0.into()])],
             start: vec!["this_exists".into()],
             ..Default::default()
         }
-        .validate_exit_on()
+        .first_error(Config::validate_exit_on)
         .is_ok());
     }
 
@@ -549,28 +1709,32 @@ mod tests_config_version_1 {
         let exit_on = 100;
         let config = Config {
             start: vec!["this_is_a_program".into()],
-            exit_on: Some(exit_on),
+            exit_on: vec![ExitCondition::Any(vec![exit_on.into()])],
             ..Default::default()
         };
 
-        assert_eq!(
-            format!("The `exitOn` arg index `{exit_on}` could not be found in `start`."),
-            config.validate_exit_on().unwrap_err().to_string()
-        )
+        assert!(matches!(
+            config.first_error(Config::validate_exit_on),
+            Err(ConfigError::ExitOnAppNotFound {
+                condition: 0,
+                app_ref: AppRef::Index(index),
+                start_len: 1,
+            }) if index == exit_on
+        ))
     }
 
     #[test]
     fn test_validate_exit_on_err_empty_start() {
         let exit_on = 100;
         let config = Config {
-            exit_on: Some(exit_on),
+            exit_on: vec![ExitCondition::Any(vec![exit_on.into()])],
             ..Default::default()
         };
 
-        assert_eq!(
-            format!("exitOn should not be specified if start is empty."),
-            config.validate_exit_on().unwrap_err().to_string()
-        )
+        assert!(matches!(
+            config.first_error(Config::validate_exit_on),
+            Err(ConfigError::ExitOnWithoutStart)
+        ))
     }
 
     #[test]
@@ -578,13 +1742,1754 @@ mod tests_config_version_1 {
         let exit_on = 1;
         let config = Config {
             start: vec!["this_is_a_program".into()],
-            exit_on: Some(exit_on),
+            exit_on: vec![ExitCondition::Any(vec![exit_on.into()])],
             ..Default::default()
         };
 
         assert_eq!(
             format!("The `exitOn` arg index `{exit_on}` could not be found in `start`.\n\nHelp: The `exitOn` arg is `{exit_on}` and length of `start` is {exit_on}, but `start` is zero indexed. This means that to get the last element of start we use length - 1 as index. So in this case index {} is the last index.", exit_on-1),
-            config.validate_exit_on().unwrap_err().to_string()
+            config.first_error(Config::validate_exit_on).unwrap_err().to_string()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests_config_version_2 {
+    use std::{collections::BTreeMap, fs::File};
+
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_from_existing_config_file_mixed_start() {
+        let temp_dir = TempDir::new("sma_config_test").unwrap();
+        let config_name = temp_dir.path().join("test_config.json");
+        fs::write(
+            &config_name,
+            r#"{
+                "version": 2,
+                "cascadeKill": false,
+                "start": [
+                    "node server.js --port 80",
+                    {
+                        "name": "db",
+                        "command": "postgres",
+                        "args": ["-D", "data dir"],
+                        "cwd": "db",
+                        "env": {"PGPORT": "5433"},
+                        "enabled": false
+                    }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let config = Config::from_existing_config_file(&config_name).unwrap();
+        assert_eq!(
+            [
+                App::new("node server.js --port 80"),
+                App::new("postgres")
+                    .with_name("db")
+                    .with_args(vec!["-D".into(), "data dir".into()])
+                    .with_cwd("db")
+                    .with_env("PGPORT", "5433")
+                    .with_enabled(false)
+            ],
+            config.get_start()
+        );
+    }
+
+    #[test]
+    fn test_start_round_trip_keeps_shorthand() {
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                "node server.js".into(),
+                App::new("postgres").with_name("db"),
+            ],
+            vec![],
+        );
+        let json = serde_json::to_value(&config).unwrap();
+        assert_eq!(
+            serde_json::json!(["node server.js", {"name": "db", "command": "postgres"}]),
+            json["start"]
+        );
+
+        let other: Config<UnVerified> = serde_json::from_value(json).unwrap();
+        assert_eq!(config, other);
+    }
+
+    #[test]
+    fn test_app_get_cmd_vec() {
+        assert_eq!(
+            Some(vec!["node".to_string(), "my server.js".to_string()]),
+            App::new("node 'my server.js'").get_cmd_vec()
+        );
+        assert_eq!(
+            Some(vec!["my node".to_string(), "a b".to_string()]),
+            App::new("my node")
+                .with_args(vec!["a b".into()])
+                .get_cmd_vec()
+        );
+        assert_eq!(None, App::new("node 'server.js").get_cmd_vec());
+    }
+
+    #[test]
+    fn test_validate_start_duplicate_name() {
+        let config = Config::new(
+            None,
+            false,
+            vec![App::new("a").with_name("x"), App::new("b").with_name("x")],
+            vec![],
+        );
+        assert!(matches!(
+            config.first_error(Config::validate_resolved_start),
+            Err(ConfigError::AppNameTaken { index: 1, name, other: 0 }) if name == "x"
+        ));
+    }
+
+    #[test]
+    fn test_validate_start_app_cwd() {
+        let temp_dir = TempDir::new("test_validate_start_app_cwd").unwrap();
+        fs::create_dir(temp_dir.path().join("app")).unwrap();
+        let config = Config::new(
+            Some(temp_dir.path().to_path_buf()),
+            false,
+            vec![App::new(std::env::current_exe().unwrap().to_str().unwrap())
+                .with_args(vec![])
+                .with_cwd("app")],
+            vec![],
+        );
+        config.first_error(Config::validate_resolved_start).unwrap();
+        assert_eq!(
+            Some(temp_dir.path().join("app")),
+            config.get_app_cwd(&config.get_start()[0])
+        );
+
+        let config = Config::new(
+            Some(temp_dir.path().to_path_buf()),
+            false,
+            vec![App::new("a").with_cwd("does_not_exist")],
+            vec![],
+        );
+        assert!(matches!(
+            config.first_error(Config::validate_resolved_start),
+            Err(ConfigError::AppCwdNotADirectory { index: 0, cwd })
+                if cwd == temp_dir.path().join("does_not_exist")
+        ));
+    }
+
+    #[test]
+    fn test_validate_exit_on_err_disabled() {
+        let config = Config::new(
+            None,
+            false,
+            vec![App::new("a").with_enabled(false)],
+            vec![ExitCondition::Any(vec![0.into()])],
+        );
+        assert!(matches!(
+            config.first_error(Config::validate_exit_on),
+            Err(ConfigError::ExitOnAppDisabled {
+                condition: 0,
+                app_ref: AppRef::Index(0),
+            })
+        ));
+    }
+
+    #[test]
+    fn test_app_env_is_sorted() {
+        let app = App::new("a").with_env("B", "2").with_env("A", "1");
+        assert_eq!(
+            BTreeMap::from([
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "2".to_string())
+            ]),
+            *app.get_env()
+        );
+    }
+
+    #[test]
+    fn test_from_existing_config_file_err_future_version() {
+        let temp_dir = TempDir::new("sma_config_test").unwrap();
+        let config_name = temp_dir.path().join("test_config.json");
+        fs::write(
+            &config_name,
+            r#"{"version": 7, "cascadeKill": false, "start": []}"#,
+        )
+        .unwrap();
+
+        let err = Config::from_existing_config_file(&config_name).unwrap_err();
+        assert!(matches!(&err, ConfigError::Migration { path, .. } if *path == config_name));
+        assert_eq!(
+            format!("The config has version `7`, but this version of sma only supports configs up to version `{CONFIG_VERSION}`. Update sma to use this config."),
+            std::error::Error::source(&err).unwrap().to_string()
+        );
+        assert_eq!(Some("/version".into()), err.get_pointer());
+    }
+
+    #[test]
+    fn test_migrate_config_file() {
+        let temp_dir = TempDir::new("sma_config_test").unwrap();
+        let config_name = temp_dir.path().join("test_config.json");
+        let old_content = r#"{"version": 1, "cascadeKill": true, "start": ["a"], "exitOn": 0}"#;
+        fs::write(&config_name, old_content).unwrap();
+
+        let backup_path = temp_dir.path().join("test_config.json.v1.bak");
+        assert_eq!(
+            MigrationOutcome::Migrated {
+                from_version: 1,
+                backup_path: backup_path.clone()
+            },
+            Config::migrate_config_file(&config_name).unwrap()
+        );
+        assert_eq!(old_content, fs::read_to_string(backup_path).unwrap());
+
+        let config = Config::from_existing_config_file(&config_name).unwrap();
+        assert_eq!(CONFIG_VERSION, config.get_version());
+        assert!(config.get_cascade_kill());
+        assert_eq!([App::new("a")], config.get_start());
+
+        assert_eq!(
+            MigrationOutcome::UpToDate,
+            Config::migrate_config_file(&config_name).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_validate_start_programs_ok() {
+        let current_exe = std::env::current_exe().unwrap();
+        let exe_dir = current_exe.parent().unwrap();
+        let exe_name = current_exe.file_name().unwrap().to_str().unwrap();
+        let config = Config::new(
+            Some(exe_dir.to_path_buf()),
+            false,
+            vec![
+                // absolute path
+                App::new(current_exe.to_str().unwrap()).with_args(vec![]),
+                // relative to the cwd of the config
+                App::new(format!("./{exe_name}")).with_args(vec![]),
+                // found with the `PATH` of the app
+                App::new(exe_name)
+                    .with_args(vec![])
+                    .with_env("PATH", exe_dir.to_str().unwrap()),
+            ],
+            vec![],
+        );
+        config.first_error(Config::validate_resolved_start).unwrap();
+    }
+
+    #[test]
+    fn test_validate_start_programs_err_reports_all() {
+        let temp_dir = TempDir::new("test_validate_start_programs_err").unwrap();
+        let config = Config::new(
+            Some(temp_dir.path().to_path_buf()),
+            false,
+            vec![
+                App::new("sma_does_not_exist --flag"),
+                App::new(std::env::current_exe().unwrap().to_str().unwrap()).with_args(vec![]),
+                App::new("./does_not_exist"),
+                App::new("does_not_exist").with_enabled(false),
+                App::new(""),
+                App::new("'unclosed"),
+            ],
+            vec![],
+        );
+        let Err(ConfigError::ProgramsNotFound { apps }) =
+            config.first_error(Config::validate_resolved_start)
+        else {
+            panic!("the programs were found");
+        };
+        assert_eq!(
+            vec![
+                (
+                    0,
+                    "`sma_does_not_exist` could not be found in `PATH`.".to_string()
+                ),
+                (
+                    2,
+                    format!(
+                        "`{}` does not exist.",
+                        temp_dir.path().join("does_not_exist").display()
+                    )
+                ),
+                (4, "The program is empty.".to_string()),
+                (
+                    5,
+                    "The command `'unclosed` could not be split into a program and its arguments."
+                        .to_string()
+                ),
+            ],
+            apps
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validate_start_programs_err_not_executable() {
+        let temp_dir = TempDir::new("test_validate_start_programs_err").unwrap();
+        File::create(temp_dir.path().join("not_executable")).unwrap();
+        let config = Config::new(
+            Some(temp_dir.path().to_path_buf()),
+            false,
+            vec![App::new("./not_executable")],
+            vec![],
+        );
+        assert!(matches!(
+            config.first_error(Config::validate_resolved_start),
+            Err(ConfigError::ProgramsNotFound { apps }) if apps == [(
+                0,
+                format!(
+                    "`{}` is not executable.",
+                    temp_dir.path().join("not_executable").display()
+                )
+            )]
+        ));
+    }
+
+    #[test]
+    fn test_validate_start_programs_err_backslash_help() {
+        let config = Config::new(None, false, vec![App::new(r"does\not\exist.exe")], vec![]);
+        assert!(matches!(
+            config.first_error(Config::validate_resolved_start),
+            Err(ConfigError::ProgramsNotFound { apps }) if apps[0].0 == 0
+                && apps[0].1.ends_with("This might be because \"\\\\\" was used instead of \"/\".")
+        ));
+    }
+
+    #[test]
+    fn test_depends_on_from_existing_config_file() {
+        let temp_dir = TempDir::new("sma_config_test").unwrap();
+        let config_name = temp_dir.path().join("test_config.json");
+        fs::write(
+            &config_name,
+            r#"{
+                "version": 2,
+                "cascadeKill": false,
+                "start": [
+                    {"name": "api", "command": "api", "dependsOn": ["db"]},
+                    {"name": "db", "command": "db"},
+                    {"command": "frontend", "dependsOn": [0, "db"]}
+                ]
+            }"#,
         )
+        .unwrap();
+
+        let config = Config::from_existing_config_file(&config_name).unwrap();
+        assert_eq!(
+            [AppRef::Name("db".into())],
+            config.get_start()[0].get_depends_on()
+        );
+        assert_eq!(vec![0, 1], config.get_dependencies(2));
+    }
+
+    #[test]
+    fn test_get_start_order() {
+        let exe = std::env::current_exe().unwrap();
+        let app = || App::new(exe.to_str().unwrap()).with_args(vec![]);
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                app().with_depends_on(vec!["api".into()]),
+                app().with_name("api").with_depends_on(vec!["db".into()]),
+                app(),
+                app().with_name("db"),
+                app().with_enabled(false),
+            ],
+            vec![],
+        )
+        .verify()
+        .unwrap();
+        assert_eq!(vec![2, 3, 1, 0], config.get_start_order());
+    }
+
+    #[test]
+    fn test_validate_dependencies_err_unknown() {
+        let config = Config::new(
+            None,
+            false,
+            vec![App::new("a").with_depends_on(vec!["b".into()])],
+            vec![],
+        );
+        assert!(matches!(
+            config.first_error(Config::validate_dependencies),
+            Err(ConfigError::DependencyNotFound { index: 0, dependency: AppRef::Name(name) }) if name == "b"
+        ));
+
+        let config = Config::new(
+            None,
+            false,
+            vec![App::new("a").with_depends_on(vec![1.into()])],
+            vec![],
+        );
+        assert!(matches!(
+            config.first_error(Config::validate_dependencies),
+            Err(ConfigError::DependencyNotFound {
+                index: 0,
+                dependency: AppRef::Index(1)
+            })
+        ));
+    }
+
+    #[test]
+    fn test_validate_dependencies_err_disabled() {
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                App::new("a").with_depends_on(vec![1.into()]),
+                App::new("b").with_name("b").with_enabled(false),
+            ],
+            vec![],
+        );
+        assert!(matches!(
+            config.first_error(Config::validate_dependencies),
+            Err(ConfigError::DependencyDisabled { index: 0, dependency: 1, label }) if label == "`b`"
+        ));
+    }
+
+    #[test]
+    fn test_validate_dependencies_err_cycle() {
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                App::new("a"),
+                App::new("b").with_name("b").with_depends_on(vec![2.into()]),
+                App::new("c").with_depends_on(vec!["d".into()]),
+                App::new("d")
+                    .with_name("d")
+                    .with_depends_on(vec!["b".into()]),
+            ],
+            vec![],
+        );
+        assert!(matches!(
+            config.first_error(Config::validate_dependencies),
+            Err(ConfigError::DependencyCycle { cycle, labels })
+                if cycle == [1, 2, 3, 1] && labels == ["`b`", "index `2`", "`d`", "`b`"]
+        ));
+
+        let config = Config::new(
+            None,
+            false,
+            vec![App::new("a").with_depends_on(vec![0.into()])],
+            vec![],
+        );
+        assert!(matches!(
+            config.first_error(Config::validate_dependencies),
+            Err(ConfigError::DependencyCycle { cycle, .. }) if cycle == [0, 0]
+        ));
+    }
+
+    #[test]
+    fn test_ready_probe_from_json() {
+        let start: Vec<App> = serde_json::from_value::<Config<UnVerified>>(serde_json::json!({
+            "version": 2,
+            "cascadeKill": false,
+            "start": [
+                {"command": "db", "ready": {"tcp": "localhost:5432"}},
+                {"command": "api", "ready": {"http": "http://localhost:8080/health", "timeoutMs": 1000}},
+                {"command": "worker", "ready": {"stdout": "^ready$"}},
+                {"command": "socket", "ready": {"file": "run/socket"}}
+            ]
+        }))
+        .unwrap()
+        .start;
+
+        assert_eq!(
+            [
+                Some(ReadyProbe::new(
+                    ReadyCheck::Tcp("localhost:5432".into()),
+                    Duration::from_secs(30)
+                )),
+                Some(ReadyProbe::new(
+                    ReadyCheck::Http("http://localhost:8080/health".into()),
+                    Duration::from_secs(1)
+                )),
+                Some(ReadyProbe::new(
+                    ReadyCheck::Stdout("^ready$".into()),
+                    Duration::from_secs(30)
+                )),
+                Some(ReadyProbe::new(
+                    ReadyCheck::File("run/socket".into()),
+                    Duration::from_secs(30)
+                )),
+            ],
+            start
+                .iter()
+                .map(|app| app.get_ready().cloned())
+                .collect::<Vec<_>>()
+                .as_slice()
+        );
+    }
+
+    #[test]
+    fn test_validate_start_err_ready_probe() {
+        let exe = std::env::current_exe().unwrap();
+        let app = |check| {
+            App::new(exe.to_str().unwrap())
+                .with_args(vec![])
+                .with_ready(ReadyProbe::new(check, Duration::from_secs(1)))
+        };
+        let problem = |check| {
+            let config = Config::new(None, false, vec![app(check)], vec![]);
+            match config.first_error(Config::validate_resolved_start) {
+                Err(ConfigError::InvalidReadyProbe { index: 0, problem }) => problem,
+                other => panic!("{other:?}"),
+            }
+        };
+        assert_eq!(
+            ReadyProbeProblem::TcpAddress {
+                address: "5432".into()
+            },
+            problem(ReadyCheck::Tcp("5432".into()))
+        );
+        assert!(matches!(
+            problem(ReadyCheck::Stderr("(".into())),
+            ReadyProbeProblem::Regex { pattern, .. } if pattern == "("
+        ));
+        assert_eq!(
+            ReadyProbeProblem::HttpUrl {
+                url: "https://localhost".into()
+            },
+            problem(ReadyCheck::Http("https://localhost".into()))
+        );
+    }
+
+    #[test]
+    fn test_http_target_parse() {
+        assert_eq!(
+            Some(HttpTarget {
+                host: "localhost".into(),
+                port: 8080,
+                path: "/health?full=1".into()
+            }),
+            HttpTarget::parse("http://localhost:8080/health?full=1")
+        );
+        assert_eq!(
+            Some(HttpTarget {
+                host: "127.0.0.1".into(),
+                port: 80,
+                path: "/".into()
+            }),
+            HttpTarget::parse("http://127.0.0.1")
+        );
+        assert_eq!(None, HttpTarget::parse("http://:80/"));
+        assert_eq!(None, HttpTarget::parse("localhost:80"));
+    }
+
+    #[test]
+    fn test_restart_policy_from_json() {
+        let start: Vec<App> = serde_json::from_value::<Config<UnVerified>>(serde_json::json!({
+            "version": 2,
+            "cascadeKill": false,
+            "start": [
+                "db",
+                {"command": "api", "restart": {"policy": "on-failure", "maxRetries": 3}},
+                {"command": "worker", "restart": {"policy": "always", "backoffMs": 10, "maxBackoffMs": 100}}
+            ]
+        }))
+        .unwrap()
+        .start;
+
+        assert_eq!(
+            [
+                RestartPolicy::default(),
+                RestartPolicy::new(Restart::OnFailure).with_max_retries(3),
+                RestartPolicy::new(Restart::Always)
+                    .with_backoff(Duration::from_millis(10), Duration::from_millis(100)),
+            ],
+            start
+                .iter()
+                .map(|app| app.get_restart().clone())
+                .collect::<Vec<_>>()
+                .as_slice()
+        );
+        assert_eq!(Restart::Never, start[0].get_restart().get_policy());
+    }
+
+    #[test]
+    fn test_restart_policy_should_restart() {
+        let never = RestartPolicy::default();
+        assert!(!never.should_restart(false, 0));
+
+        let on_failure = RestartPolicy::new(Restart::OnFailure).with_max_retries(2);
+        assert!(on_failure.should_restart(false, 0));
+        assert!(on_failure.should_restart(false, 1));
+        assert!(!on_failure.should_restart(false, 2));
+        assert!(!on_failure.should_restart(true, 0));
+
+        let always = RestartPolicy::new(Restart::Always);
+        assert!(always.should_restart(true, 1000));
+        assert!(always.should_restart(false, 1000));
+    }
+
+    #[test]
+    fn test_restart_policy_backoff() {
+        let policy = RestartPolicy::new(Restart::Always)
+            .with_backoff(Duration::from_millis(100), Duration::from_millis(1000));
+        assert_eq!(
+            [100, 200, 400, 800, 1000, 1000],
+            [0, 1, 2, 3, 4, 100].map(|restarts| policy.get_backoff(restarts).as_millis())
+        );
+    }
+
+    #[test]
+    fn test_validate_start_err_restart_policy() {
+        let exe = std::env::current_exe().unwrap();
+        let config = Config::new(
+            None,
+            false,
+            vec![App::new(exe.to_str().unwrap())
+                .with_args(vec![])
+                .with_restart(
+                    RestartPolicy::new(Restart::Always)
+                        .with_backoff(Duration::from_secs(2), Duration::from_secs(1)),
+                )],
+            vec![],
+        );
+        assert!(matches!(
+            config.first_error(Config::validate_resolved_start),
+            Err(ConfigError::InvalidRestart {
+                index: 0,
+                problem: RestartProblem::BackoffAboveMax {
+                    backoff_ms: 2000,
+                    max_backoff_ms: 1000,
+                },
+            })
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests_config_version_3 {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn exe_app(name: &str) -> App {
+        let exe = std::env::current_exe().unwrap();
+        App::new(exe.to_str().unwrap())
+            .with_args(vec![])
+            .with_name(name)
+    }
+
+    #[test]
+    fn test_exit_on_from_json() {
+        let config = serde_json::from_value::<Config<UnVerified>>(serde_json::json!({
+            "version": 3,
+            "cascadeKill": false,
+            "start": [{"command": "a", "name": "api"}, "b"],
+            "exitOn": [{"any": ["api", 1]}, {"all": [0]}, "anyFailed", "allExited"]
+        }))
+        .unwrap();
+
+        assert_eq!(
+            [
+                ExitCondition::Any(vec!["api".into(), 1.into()]),
+                ExitCondition::All(vec![0.into()]),
+                ExitCondition::AnyFailed,
+                ExitCondition::AllExited,
+            ],
+            config.get_exit_on()
+        );
+    }
+
+    #[test]
+    fn test_exit_on_defaults_to_empty() {
+        let config = serde_json::from_value::<Config<UnVerified>>(serde_json::json!({
+            "version": 3,
+            "cascadeKill": false,
+            "start": []
+        }))
+        .unwrap();
+        assert!(config.get_exit_on().is_empty());
+    }
+
+    #[test]
+    fn test_validate_exit_on_err_names() {
+        let start = vec![exe_app("api"), exe_app("worker").with_enabled(false)];
+        let first_error = |condition| {
+            Config::new(None, false, start.clone(), vec![condition])
+                .first_error(Config::validate_exit_on)
+        };
+        assert!(matches!(
+            first_error(ExitCondition::Any(vec!["db".into()])),
+            Err(ConfigError::ExitOnAppNotFound {
+                condition: 0,
+                app_ref: AppRef::Name(name),
+                start_len: 2,
+            }) if name == "db"
+        ));
+        assert!(matches!(
+            first_error(ExitCondition::All(vec!["api".into(), "worker".into()])),
+            Err(ConfigError::ExitOnAppDisabled {
+                condition: 0,
+                app_ref: AppRef::Name(name),
+            }) if name == "worker"
+        ));
+        assert!(matches!(
+            first_error(ExitCondition::All(vec![])),
+            Err(ConfigError::ExitOnWithoutApps {
+                condition: 0,
+                kind: "all",
+            })
+        ));
+    }
+
+    #[test]
+    fn test_should_exit() {
+        let config = |exit_on| {
+            Config::new(
+                None,
+                false,
+                vec![exe_app("a"), exe_app("b"), exe_app("c").with_enabled(false)],
+                exit_on,
+            )
+            .verify()
+            .unwrap()
+        };
+
+        let none = config(vec![]);
+        assert!(!none.should_exit(&[Some(false), Some(true), None]));
+
+        let any = config(vec![ExitCondition::Any(vec!["a".into(), "b".into()])]);
+        assert!(!any.should_exit(&[None, None, None]));
+        assert!(any.should_exit(&[None, Some(true), None]));
+
+        let all = config(vec![ExitCondition::All(vec!["a".into(), 1.into()])]);
+        assert!(!all.should_exit(&[None, Some(true), None]));
+        assert!(all.should_exit(&[Some(true), Some(false), None]));
+
+        let any_failed = config(vec![ExitCondition::AnyFailed]);
+        assert!(!any_failed.should_exit(&[Some(true), None, None]));
+        assert!(any_failed.should_exit(&[Some(true), Some(false), None]));
+
+        // The disabled app never exits, but it is not waited on.
+        let all_exited = config(vec![ExitCondition::AllExited]);
+        assert!(!all_exited.should_exit(&[Some(true), None, None]));
+        assert!(all_exited.should_exit(&[Some(true), Some(false), None]));
+
+        // The conditions are combined, any of them can be met.
+        let combined = config(vec![
+            ExitCondition::Any(vec!["a".into()]),
+            ExitCondition::AnyFailed,
+        ]);
+        assert!(combined.should_exit(&[None, Some(false), None]));
+        assert!(combined.should_exit(&[Some(true), None, None]));
+        assert!(!combined.should_exit(&[None, Some(true), None]));
+    }
+
+    #[test]
+    fn test_logs_from_json() {
+        let config = serde_json::from_value::<Config<UnVerified>>(serde_json::json!({
+            "version": 3,
+            "cascadeKill": false,
+            "start": ["a", {"command": "b", "logs": {"path": "b.log", "maxSize": 1024, "keep": 2}}],
+            "logs": {"path": "logs/{name}.{stream}.log", "maxSize": "1MB"}
+        }))
+        .unwrap();
+
+        let logs =
+            LogConfig::new("logs/{name}.{stream}.log").with_rotation(ByteSize::new(1024 * 1024), 5);
+        assert_eq!(Some(&logs), config.get_logs());
+        assert_eq!(Some(&logs), config.get_app_logs(&config.get_start()[0]));
+        assert_eq!(
+            Some(&LogConfig::new("b.log").with_rotation(ByteSize::new(1024), 2)),
+            config.get_app_logs(&config.get_start()[1])
+        );
+        assert_eq!(
+            PathBuf::from("logs/0.stderr.log"),
+            logs.get_path(None, 0, "stderr")
+        );
+        assert_eq!(
+            PathBuf::from("logs/api.stdout.log"),
+            logs.get_path(Some("api"), 3, "stdout")
+        );
+    }
+
+    #[test]
+    fn test_single_instance_from_json() {
+        let config = serde_json::from_value::<Config<UnVerified>>(serde_json::json!({
+            "version": 3,
+            "cascadeKill": false,
+            "start": [],
+            "singleInstance": "replace"
+        }))
+        .unwrap();
+        assert_eq!(SingleInstance::Replace, config.get_single_instance());
+
+        for (json, single_instance) in [
+            ("\"allow\"", SingleInstance::Allow),
+            ("\"refuse\"", SingleInstance::Refuse),
+            ("\"forward\"", SingleInstance::Forward),
+        ] {
+            assert_eq!(single_instance, serde_json::from_str(json).unwrap());
+        }
+        serde_json::from_str::<SingleInstance>("\"Allow\"").unwrap_err();
+    }
+
+    #[test]
+    fn test_limits_from_json() {
+        let config = serde_json::from_value::<Config<UnVerified>>(serde_json::json!({
+            "version": 3,
+            "cascadeKill": false,
+            "start": [{
+                "command": "server",
+                "limits": { "maxMemory": "512MB", "nice": 10, "openFiles": 1024, "coreDumpSize": 0 }
+            }]
+        }))
+        .unwrap();
+        assert_eq!(
+            &Limits::new()
+                .with_max_memory(ByteSize::new(512 * 1024 * 1024))
+                .with_nice(10)
+                .with_open_files(1024)
+                .with_core_dump_size(ByteSize::new(0)),
+            config.get_start()[0].get_limits()
+        );
+    }
+
+    #[test]
+    fn test_validate_start_err_limits() {
+        let exe = std::env::current_exe().unwrap();
+        for (limits, expected) in [
+            (
+                Limits::new().with_nice(20),
+                LimitsProblem::NiceOutOfRange { nice: 20 },
+            ),
+            (
+                Limits::new().with_max_memory(ByteSize::new(0)),
+                LimitsProblem::ZeroMaxMemory,
+            ),
+            (
+                Limits::new().with_open_files(0),
+                LimitsProblem::ZeroOpenFiles,
+            ),
+            (
+                Limits::new().with_max_memory(ByteSize::new(1024)),
+                LimitsProblem::MaxMemoryWithoutPerAppCgroups,
+            ),
+        ] {
+            let config = Config::new(
+                None,
+                false,
+                vec![App::new(exe.to_str().unwrap())
+                    .with_args(vec![])
+                    .with_limits(limits)],
+                vec![],
+            );
+            assert!(matches!(
+                config.verify(),
+                Err(ConfigError::InvalidLimits { index: 0, problem }) if problem == expected
+            ));
+        }
+    }
+
+    #[test]
+    fn test_app_env_layers() {
+        let temp_dir = TempDir::new("test_app_env_layers").unwrap();
+        fs::write(
+            temp_dir.path().join(".env"),
+            "SHARED=file\nFROM_FILE=config\n",
+        )
+        .unwrap();
+        fs::write(temp_dir.path().join("api.env"), "SHARED=\"app file\"\n").unwrap();
+        let config_file_path = temp_dir.path().join("config.json");
+        fs::write(
+            &config_file_path,
+            serde_json::json!({
+                "version": 3,
+                "cascadeKill": false,
+                "start": [{
+                    "command": std::env::current_exe().unwrap(),
+                    "args": [],
+                    "envFile": "api.env",
+                    "env": { "GREETING": "${SHARED} and ${FROM_FILE}", "ESCAPED": "$${SHARED}" }
+                }],
+                "envFile": [".env"],
+                "env": { "FROM_CONFIG": "config", "SMA_PATH": "${env:PATH}" }
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let config = Config::from_existing_config_file(&config_file_path)
+            .unwrap()
+            .verify()
+            .unwrap();
+        let env = config.get_app_env(0).unwrap();
+        assert_eq!("app file and config", env[OsStr::new("GREETING")]);
+        assert_eq!("${SHARED}", env[OsStr::new("ESCAPED")]);
+        assert_eq!("config", env[OsStr::new("FROM_CONFIG")]);
+        assert_eq!("app file", env[OsStr::new("SHARED")]);
+        // The environment of sma is given to the app as well.
+        assert_eq!(std::env::var_os("PATH").unwrap(), env[OsStr::new("PATH")]);
+        assert_eq!(env[OsStr::new("PATH")], env[OsStr::new("SMA_PATH")]);
+
+        let configured = config.get_app_configured_env(0).unwrap();
+        assert!(!configured.contains_key(OsStr::new("PATH")));
+        assert_eq!(
+            env.iter()
+                .filter(|(name, _)| configured.contains_key(*name))
+                .collect::<BTreeMap<_, _>>(),
+            configured.iter().collect()
+        );
+        assert_eq!(6, configured.len());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_app_env_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let value = OsStr::from_bytes(b"caf\xe9");
+        std::env::set_var("SMA_TEST_NOT_UTF8", value);
+        let exe = std::env::current_exe().unwrap();
+        let config = Config::new(
+            None,
+            false,
+            vec![App::new(exe.to_str().unwrap()).with_args(vec![])],
+            vec![],
+        );
+        let env = config.verify().unwrap().get_app_env(0).unwrap();
+        assert_eq!(value, env[OsStr::new("SMA_TEST_NOT_UTF8")]);
+    }
+
+    #[test]
+    fn test_app_env_clean() {
+        let exe = std::env::current_exe().unwrap();
+        let config = Config::new(
+            None,
+            false,
+            vec![App::new(exe.to_str().unwrap())
+                .with_args(vec![])
+                .with_env("MY_PATH", "${PATH}:/opt/bin")],
+            vec![],
+        )
+        .with_clean_env(vec!["HOME".into()]);
+        let config = config.verify().unwrap();
+        let env = config.get_app_env(0).unwrap();
+        assert_eq!(
+            format!("{}:/opt/bin", std::env::var("PATH").unwrap()).as_str(),
+            env[OsStr::new("MY_PATH")]
+        );
+        assert!(!env.contains_key(OsStr::new("PATH")));
+        assert_eq!(
+            std::env::var_os("HOME").as_ref(),
+            env.get(OsStr::new("HOME"))
+        );
+    }
+
+    #[test]
+    fn test_validate_programs_path_of_config_env() {
+        let current_exe = std::env::current_exe().unwrap();
+        let exe_dir = current_exe.parent().unwrap();
+        let exe_name = current_exe.file_name().unwrap().to_str().unwrap();
+        let app = App::new(exe_name).with_args(vec![]);
+
+        let path_var = std::env::join_paths([exe_dir.as_os_str(), OsStr::new("${PATH}")]).unwrap();
+        let config = Config::new(None, false, vec![app.clone()], vec![])
+            .with_env("PATH", path_var.to_str().unwrap());
+        config.verify().unwrap();
+
+        // The app does not get the `PATH` of sma.
+        let config = Config::new(None, false, vec![app], vec![]).with_clean_env(vec![]);
+        assert!(matches!(
+            config.verify(),
+            Err(ConfigError::ProgramsNotFound { apps }) if apps[0].0 == 0
+        ));
+    }
+
+    #[test]
+    fn test_validate_programs_path_of_env_file() {
+        let current_exe = std::env::current_exe().unwrap();
+        let exe_dir = current_exe.parent().unwrap();
+        let exe_name = current_exe.file_name().unwrap().to_str().unwrap();
+
+        let temp_dir = TempDir::new("test_validate_programs_path_of_env_file").unwrap();
+        let env_file = temp_dir.path().join("path.env");
+        fs::write(&env_file, format!("PATH={}\n", exe_dir.display())).unwrap();
+        let config = Config::new(
+            None,
+            false,
+            vec![App::new(exe_name).with_args(vec![])],
+            vec![],
+        )
+        .with_env_file(&env_file);
+        config.verify().unwrap();
+    }
+
+    #[test]
+    fn test_validate_env_err() {
+        let exe = std::env::current_exe().unwrap();
+        let app = App::new(exe.to_str().unwrap()).with_args(vec![]);
+
+        let config = Config::new(
+            None,
+            false,
+            vec![app
+                .clone()
+                .with_env("URL", "${SMA_TEST_HOST}:${SMA_TEST_PORT}")],
+            vec![],
+        );
+        assert!(matches!(
+            config.verify(),
+            Err(ConfigError::EnvVarsNotSet { index: Some(0), name, unset })
+                if name == "URL" && unset == ["SMA_TEST_HOST", "SMA_TEST_PORT"]
+        ));
+
+        let config =
+            Config::new(None, false, vec![app.clone()], vec![]).with_env_file("does_not_exist.env");
+        assert!(matches!(
+            config.verify(),
+            Err(ConfigError::EnvFile { index: None, path, .. })
+                if path == Path::new("does_not_exist.env")
+        ));
+
+        let mut config = Config::new(None, false, vec![app], vec![]);
+        config.env_allowlist = vec!["PATH".into()];
+        assert!(matches!(
+            config.verify(),
+            Err(ConfigError::EnvAllowlistWithoutCleanEnv)
+        ));
+    }
+
+    #[test]
+    fn test_interpolate() {
+        let temp_dir = TempDir::new("test_interpolate").unwrap();
+        let exe = std::env::current_exe().unwrap();
+        let config_file_path = temp_dir.path().join("config.json");
+        fs::write(
+            &config_file_path,
+            serde_json::json!({
+                "version": 3,
+                "cascadeKill": false,
+                "cwd": "${CONFIG_DIR}",
+                "vars": { "PORT": "8080" },
+                "start": [
+                    format!("{} --port ${{PORT}} $${{NOT_A_VAR}}", exe.display()),
+                    {
+                        "command": exe,
+                        "args": ["${APP_INDEX}", "${HOME}", "${env:PATH}"],
+                        "cwd": "${CONFIG_DIR}"
+                    }
+                ]
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let config = Config::from_existing_config_file(&config_file_path)
+            .unwrap()
+            .verify()
+            .unwrap();
+        let config_dir = std::path::absolute(temp_dir.path()).unwrap();
+        assert_eq!(Some(config_dir.as_path()), config.get_cwd());
+        assert_eq!(
+            Some(vec![
+                exe.display().to_string(),
+                "--port".into(),
+                "8080".into(),
+                "${NOT_A_VAR}".into()
+            ]),
+            config.get_start()[0].get_cmd_vec()
+        );
+        assert_eq!(
+            Some(
+                [
+                    "1".to_string(),
+                    std::env::var("HOME").unwrap(),
+                    std::env::var("PATH").unwrap()
+                ]
+                .as_slice()
+            ),
+            config.get_start()[1].get_args()
+        );
+        assert_eq!(Some(config_dir.as_path()), config.get_start()[1].get_cwd());
+
+        // The saved config keeps its variables.
+        config.create_file(&config_file_path, true).unwrap();
+        let saved = fs::read_to_string(&config_file_path).unwrap();
+        assert!(saved.contains("\"cwd\": \"${CONFIG_DIR}\""));
+        assert!(saved.contains("--port ${PORT} $${NOT_A_VAR}"));
+        assert!(saved.contains("\"${APP_INDEX}\""));
+    }
+
+    // A variable in a command line stays a single argument, even if its value
+    // has spaces.
+    #[cfg(unix)]
+    #[test]
+    fn test_interpolate_command_line() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = TempDir::new("test interpolate command line").unwrap();
+        let program = temp_dir.path().join("app.sh");
+        fs::write(&program, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+        let config_file_path = temp_dir.path().join("config.json");
+        fs::write(
+            &config_file_path,
+            serde_json::json!({
+                "version": 3,
+                "cascadeKill": false,
+                "start": ["${CONFIG_DIR}/app.sh --dir ${CONFIG_DIR} '${CONFIG_DIR}/a b'"]
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let config = Config::from_existing_config_file(&config_file_path)
+            .unwrap()
+            .verify()
+            .unwrap();
+        let config_dir = std::path::absolute(temp_dir.path()).unwrap();
+        let config_dir = config_dir.to_str().unwrap();
+        assert_eq!(
+            Some(vec![
+                format!("{config_dir}/app.sh"),
+                "--dir".into(),
+                config_dir.into(),
+                format!("{config_dir}/a b")
+            ]),
+            config.get_start()[0].get_cmd_vec()
+        );
+    }
+
+    // An app that uses a variable that is not defined only reports the
+    // variable, not that its program can not be found.
+    #[test]
+    fn test_validate_file_unresolved_vars() {
+        let temp_dir = TempDir::new("test_validate_file_unresolved_vars").unwrap();
+        let file_path = temp_dir.path().join("config.json");
+        let exe = std::env::current_exe().unwrap();
+        fs::write(
+            &file_path,
+            serde_json::json!({
+                "version": 3,
+                "cascadeKill": false,
+                "vars": { "EXE": exe },
+                "start": ["${EXE}", "${SMA_TEST_NOT_DEFINED}/server"]
+            })
+            .to_string(),
+        )
+        .unwrap();
+
+        let diagnostics = Config::validate_file(&file_path);
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(
+            "The following variables are not defined:\n    `${SMA_TEST_NOT_DEFINED}` in `start[1].command`",
+            diagnostics[0].get_message()
+        );
+    }
+
+    #[test]
+    fn test_interpolate_err() {
+        let config = Config::new(
+            Some("${SMA_TEST_ROOT}".into()),
+            false,
+            vec![
+                App::new("server ${PORT}"),
+                App::new("client").with_args(vec!["${CONFIG_DIR}".into()]),
+            ],
+            vec![],
+        );
+        let Err(ConfigError::VarsNotDefined { unresolved }) = config.verify() else {
+            panic!("every variable is defined");
+        };
+        assert_eq!(
+            [
+                ("SMA_TEST_ROOT", "cwd"),
+                ("PORT", "start[0].command"),
+                ("CONFIG_DIR", "start[1].args[0]"),
+            ]
+            .map(|(name, location)| (name.to_string(), location.to_string())),
+            *unresolved
+        );
+
+        let config = Config::default().with_var("HOME", "/home/sma");
+        assert!(matches!(
+            config.verify(),
+            Err(ConfigError::VarRedefined { name }) if name == "HOME"
+        ));
+    }
+
+    #[test]
+    fn test_cgroups_from_json() {
+        let config = serde_json::from_value::<Config<UnVerified>>(serde_json::json!({
+            "version": 3,
+            "cascadeKill": true,
+            "start": [],
+            "cgroups": "perApp"
+        }))
+        .unwrap();
+        assert_eq!(Cgroups::PerApp, config.get_cgroups());
+
+        let config = serde_json::from_value::<Config<UnVerified>>(serde_json::json!({
+            "version": 3,
+            "cascadeKill": true,
+            "start": []
+        }))
+        .unwrap();
+        assert_eq!(Cgroups::Off, config.get_cgroups());
+        assert_eq!(
+            Cgroups::PerConfig,
+            serde_json::from_str("\"perConfig\"").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_byte_size() {
+        for (s, bytes) in [
+            ("100", 100),
+            ("100B", 100),
+            ("512KB", 512 * 1024),
+            ("10 mb", 10 * 1024 * 1024),
+            ("1GB", 1024 * 1024 * 1024),
+        ] {
+            assert_eq!(ByteSize::new(bytes), s.parse::<ByteSize>().unwrap(), "{s}");
+        }
+        assert_eq!(
+            "`ten` is not a size, it must be written as e.g. `512KB` or `10MB`.",
+            "ten".parse::<ByteSize>().unwrap_err().to_string()
+        );
+        assert_eq!("10MB", ByteSize::new(10 * 1024 * 1024).to_string());
+        assert_eq!("1025B", ByteSize::new(1025).to_string());
+        assert_eq!(
+            serde_json::json!("2KB"),
+            serde_json::to_value(ByteSize::new(2048)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_validate_logs_err_shared_path() {
+        let config = Config::new(None, false, vec!["a".into(), "b".into()], vec![])
+            .with_logs(LogConfig::new("sma.log"));
+        assert!(matches!(
+            config.first_error(Config::validate_logs),
+            Err(ConfigError::SharedLogPath { path_template }) if path_template == "sma.log"
+        ));
+
+        let config = Config::new(None, false, vec!["a".into()], vec![])
+            .with_logs(LogConfig::new("sma.log").with_rotation(ByteSize::new(0), 1));
+        assert!(matches!(
+            config.first_error(Config::validate_logs),
+            Err(ConfigError::InvalidLogs {
+                problem: LogsProblem::ZeroMaxSize
+            })
+        ));
+    }
+
+    #[test]
+    fn test_validate_file() {
+        let temp_dir = TempDir::new("test_validate_file").unwrap();
+        let file_path = temp_dir.path().join("config.json");
+        let exe =
+            serde_json::to_string(std::env::current_exe().unwrap().to_str().unwrap()).unwrap();
+        fs::write(
+            &file_path,
+            format!(
+                r#"{{
+  "version": 3,
+  "cwd": "does_not_exist",
+  "cascadeKill": false,
+  "start": [
+    {exe},
+    "sma_test_missing_program"
+  ],
+  "exitOn": [{{ "any": [2] }}]
+}}"#
+            ),
+        )
+        .unwrap();
+        let diagnostics = Config::validate_file(&file_path);
+        let found: Vec<_> = diagnostics
+            .iter()
+            .map(|diagnostic| {
+                (
+                    diagnostic.get_pointer(),
+                    diagnostic.get_line(),
+                    diagnostic.get_column(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                (Some("/cwd"), Some(3), Some(10)),
+                (Some("/start/1/command"), Some(7), Some(5)),
+                (Some("/exitOn/0"), Some(9), Some(14)),
+            ],
+            found
+        );
+        assert!(diagnostics[2]
+            .get_message()
+            .starts_with("The `exitOn` arg index `2` could not be found in `start`."));
+
+        // A syntax error is all that can be reported.
+        fs::write(&file_path, "{\n  \"version\": 3,\n  \"start\": [,]\n}").unwrap();
+        let diagnostics = Config::validate_file(&file_path);
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(
+            (Some(3), Some(13)),
+            (diagnostics[0].get_line(), diagnostics[0].get_column())
+        );
+        assert_eq!("expected value", diagnostics[0].get_message());
+
+        fs::write(&file_path, "{\n  \"version\": 99,\n  \"start\": []\n}").unwrap();
+        let diagnostics = Config::validate_file(&file_path);
+        assert_eq!(Some("/version"), diagnostics[0].get_pointer());
+        assert_eq!(
+            (Some(2), Some(14)),
+            (diagnostics[0].get_line(), diagnostics[0].get_column())
+        );
+
+        Config::default()
+            .verify()
+            .unwrap()
+            .create_file(&file_path, true)
+            .unwrap();
+        assert!(Config::validate_file(&file_path).is_empty());
+        assert_eq!(
+            1,
+            Config::validate_file(temp_dir.path().join("missing.json")).len()
+        );
+    }
+
+    #[test]
+    fn test_config_error() {
+        let err = Config::new(
+            None,
+            false,
+            vec![exe_app("a")],
+            vec![ExitCondition::Any(vec![AppRef::Index(1)])],
+        )
+        .verify()
+        .unwrap_err();
+        assert!(matches!(
+            &err,
+            ConfigError::ExitOnAppNotFound {
+                condition: 0,
+                app_ref: AppRef::Index(1),
+                start_len: 1
+            }
+        ));
+        assert_eq!(Some("/exitOn/0".into()), err.get_pointer());
+
+        let err = Config::new(None, false, vec![exe_app("a")], vec![])
+            .with_env("URL", "${SMA_TEST_UNSET}")
+            .verify()
+            .unwrap_err();
+        assert!(matches!(
+            &err,
+            ConfigError::EnvVarsNotSet { index: None, .. }
+        ));
+        assert_eq!(Some("/env/URL".into()), err.get_pointer());
+
+        let temp_dir = TempDir::new("test_config_error").unwrap();
+        let file_path = temp_dir.path().join("config.json");
+        fs::write(&file_path, "{\n  \"version\": 3,\n  \"start\": 1\n}").unwrap();
+        match Config::from_existing_config_file(&file_path).unwrap_err() {
+            ConfigError::Parse {
+                path, line, column, ..
+            } => {
+                assert_eq!(file_path, path);
+                assert_eq!((Some(3), Some(12)), (line, column));
+            }
+            err => panic!("Expected a parse error but got {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_config_formats() {
+        let temp_dir = TempDir::new("test_config_formats").unwrap();
+        let exe =
+            serde_json::to_string(std::env::current_exe().unwrap().to_str().unwrap()).unwrap();
+        let toml = format!(
+            r#"# The services of the project.
+version = 3
+cascadeKill = true # Stop everything together.
+exitOn = [{{ any = ["web"] }}]
+
+[[start]]
+# The web server.
+name = "web"
+command = {exe}
+"#
+        );
+        let yaml = format!(
+            r#"# The services of the project.
+version: 3
+cascadeKill: true
+# Stop everything together.
+exitOn:
+  - any: [web]
+start:
+  - name: web
+    command: {exe}
+"#
+        );
+
+        for (file_name, source, comments) in [
+            (
+                "config.toml",
+                toml,
+                &[
+                    "# The services of the project.\n",
+                    "cascadeKill = true # Stop everything together.\n",
+                    "# The web server.\n",
+                ][..],
+            ),
+            (
+                "config.yaml",
+                yaml,
+                &[
+                    "# The services of the project.\n",
+                    "# Stop everything together.\nexitOn:",
+                ][..],
+            ),
+        ] {
+            let file_path = temp_dir.path().join(file_name);
+            fs::write(&file_path, source).unwrap();
+            let config = Config::from_existing_config_file(&file_path)
+                .unwrap()
+                .verify()
+                .unwrap();
+            assert!(config.get_cascade_kill());
+            assert_eq!(
+                &[ExitCondition::Any(vec![AppRef::Name("web".into())])],
+                config.get_exit_on()
+            );
+            assert_eq!(Some("web"), config.get_start()[0].get_name());
+
+            // Saving the config again keeps the comments of the file.
+            config.create_file(&file_path, true).unwrap();
+            let saved = fs::read_to_string(&file_path).unwrap();
+            for comment in comments {
+                assert!(saved.contains(comment), "`{comment}` is not in:\n{saved}");
+            }
+            let other = Config::from_existing_config_file(&file_path)
+                .unwrap()
+                .verify()
+                .unwrap();
+            assert_eq!(config.get_start(), other.get_start());
+            assert_eq!(config.get_exit_on(), other.get_exit_on());
+        }
+
+        let file_path = temp_dir.path().join("config.yml");
+        fs::write(&file_path, "version: 3\nstart: 1\n").unwrap();
+        match Config::from_existing_config_file(&file_path).unwrap_err() {
+            ConfigError::Parse { line, column, .. } => {
+                assert_eq!((Some(2), Some(8)), (line, column))
+            }
+            err => panic!("Expected a parse error but got {err:?}"),
+        }
+        let file_path = temp_dir.path().join("other.toml");
+        fs::write(&file_path, "version = 3\nstart = 1\n").unwrap();
+        match Config::from_existing_config_file(&file_path).unwrap_err() {
+            ConfigError::Parse { line, column, .. } => {
+                assert_eq!((Some(2), Some(9)), (line, column))
+            }
+            err => panic!("Expected a parse error but got {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_convert_config_file() {
+        let temp_dir = TempDir::new("test_convert_config_file").unwrap();
+        let json_path = temp_dir.path().join("config.json");
+        let toml_path = temp_dir.path().join("config.toml");
+        let yaml_path = temp_dir.path().join("config.yml");
+        let config = Config::new(
+            None,
+            true,
+            vec![exe_app("a"), exe_app("b")],
+            vec![
+                ExitCondition::AnyFailed,
+                ExitCondition::All(vec![AppRef::Index(1)]),
+            ],
+        )
+        .verify()
+        .unwrap();
+        config.create_file(&json_path, false).unwrap();
+
+        Config::convert_config_file(&json_path, &toml_path, false).unwrap();
+        Config::convert_config_file(&toml_path, &yaml_path, false).unwrap();
+        Config::convert_config_file(&yaml_path, &json_path, false).unwrap_err();
+        Config::convert_config_file(&yaml_path, &json_path, true).unwrap();
+        for file_path in [&json_path, &toml_path, &yaml_path] {
+            let other = Config::from_existing_config_file(file_path)
+                .unwrap()
+                .verify()
+                .unwrap();
+            assert_eq!(config.get_start(), other.get_start());
+            assert_eq!(config.get_exit_on(), other.get_exit_on());
+            assert!(other.get_cascade_kill());
+        }
+        assert!(fs::read_to_string(&toml_path)
+            .unwrap()
+            .contains("[[start]]"));
+
+        // YAML 1.1 reads a plain `off` as false, so the values that look like
+        // booleans are quoted, but not in the text of a block scalar.
+        let config = Config::new(None, false, vec![exe_app("a")], vec![])
+            .with_cgroups(Cgroups::Off)
+            .with_env("ANSWER", "no")
+            .with_env("TEXT", "first\noff\n")
+            .verify()
+            .unwrap();
+        config.create_file(&yaml_path, true).unwrap();
+        let saved = fs::read_to_string(&yaml_path).unwrap();
+        assert!(saved.contains("cgroups: 'off'\n"), "{saved}");
+        assert!(saved.contains("ANSWER: 'no'\n"), "{saved}");
+        assert!(saved.contains("\n    off\n"), "{saved}");
+        let other = Config::from_existing_config_file(&yaml_path)
+            .unwrap()
+            .verify()
+            .unwrap();
+        assert_eq!(Cgroups::Off, other.get_cgroups());
+        assert_eq!(config.get_env(), other.get_env());
+    }
+
+    #[test]
+    fn test_json_schema() {
+        assert_eq!(
+            include_str!("../schema/v3.json").trim_end(),
+            Config::json_schema(),
+            "The config changed, write `config/schema/v3.json` again with `sma schema > config/schema/v3.json`."
+        );
+        assert!(CONFIG_SCHEMA_URL.ends_with(&format!("/config/schema/v{CONFIG_VERSION}.json")));
+
+        let schema = serde_json::from_str(&Config::json_schema()).unwrap();
+        let validator = jsonschema::validator_for(&schema).unwrap();
+        for config in [
+            serde_json::json!({ "version": 3, "cascadeKill": false, "start": [] }),
+            serde_json::json!({
+                "version": 3,
+                "extends": "base.json",
+                "include": ["worker.yaml"],
+                "cascadeKill": false,
+                "start": ["server", { "command": "worker", "args": [] }]
+            }),
+            serde_json::to_value(Config::default()).unwrap(),
+        ] {
+            let errors: Vec<_> = validator
+                .iter_errors(&config)
+                .map(|e| e.to_string())
+                .collect();
+            assert!(errors.is_empty(), "{config}: {errors:?}");
+        }
+        assert!(!validator.is_valid(&serde_json::json!({ "version": 3, "start": [] })));
+        assert!(!validator
+            .is_valid(&serde_json::json!({ "version": 2, "cascadeKill": false, "start": [] })));
+        // A config that extends another one does not have to repeat its
+        // settings.
+        let child = serde_json::json!({
+            "version": 3,
+            "extends": "base.json",
+            "env": { "PORT": "8080" }
+        });
+        let errors: Vec<_> = validator
+            .iter_errors(&child)
+            .map(|e| e.to_string())
+            .collect();
+        assert!(errors.is_empty(), "{child}: {errors:?}");
+        assert!(!validator.is_valid(&serde_json::json!({ "version": 3, "include": ["a.json"] })));
+
+        let temp_dir = TempDir::new("test_json_schema").unwrap();
+        let file_path = temp_dir.path().join("config.json");
+        Config::new_config_to_file(&file_path, false).unwrap();
+        let value: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&file_path).unwrap()).unwrap();
+        assert_eq!(CONFIG_SCHEMA_URL, value["$schema"]);
+        let config = Config::from_existing_config_file(&file_path).unwrap();
+        assert_eq!(Some(CONFIG_SCHEMA_URL), config.schema.as_deref());
+
+        for (file_name, comment) in [
+            ("config.toml", format!("#:schema {CONFIG_SCHEMA_URL}\n")),
+            (
+                "config.yaml",
+                format!("# yaml-language-server: $schema={CONFIG_SCHEMA_URL}\n"),
+            ),
+        ] {
+            let file_path = temp_dir.path().join(file_name);
+            Config::new_config_to_file(&file_path, false).unwrap();
+            let saved = fs::read_to_string(&file_path).unwrap();
+            assert!(saved.starts_with(&comment), "{saved}");
+            assert!(!saved[comment.len()..].contains("$schema"), "{saved}");
+
+            let config = Config::from_existing_config_file(&file_path).unwrap();
+            assert_eq!(Some(CONFIG_SCHEMA_URL), config.schema.as_deref());
+            config
+                .verify()
+                .unwrap()
+                .create_file(&file_path, true)
+                .unwrap();
+            let saved = fs::read_to_string(&file_path).unwrap();
+            assert_eq!(1, saved.matches(&comment).count(), "{saved}");
+        }
+    }
+
+    #[test]
+    fn test_extends_and_include() {
+        let temp_dir = TempDir::new("test_extends_and_include").unwrap();
+        let base_dir = temp_dir.path().join("base");
+        fs::create_dir(&base_dir).unwrap();
+        let exe =
+            serde_json::to_string(std::env::current_exe().unwrap().to_str().unwrap()).unwrap();
+        fs::write(
+            base_dir.join("base.json"),
+            format!(
+                r#"{{
+  "version": 3,
+  "cwd": ".",
+  "cascadeKill": true,
+  "env": {{ "A": "1", "B": "1" }},
+  "envFile": "base.env",
+  "stop": {{ "signal": "SIGINT", "timeoutMs": 1000 }},
+  "start": [
+    {{ "name": "web", "command": {exe}, "env": {{ "X": "1" }} }},
+    {{ "name": "db", "command": {exe} }}
+  ]
+}}"#
+            ),
+        )
+        .unwrap();
+        fs::write(base_dir.join("base.env"), "E=1\n").unwrap();
+        fs::write(
+            temp_dir.path().join("worker.yml"),
+            format!("start:\n  - name: worker\n    command: {exe}\n"),
+        )
+        .unwrap();
+        let file_path = temp_dir.path().join("config.toml");
+        fs::write(
+            &file_path,
+            r#"extends = "base/base.json"
+include = ["worker.yml"]
+version = 3
+cascadeKill = false
+env = { B = "2" }
+
+[[start]]
+name = "web"
+env = { Y = "2" }
+"#,
+        )
+        .unwrap();
+
+        let config = Config::from_existing_config_file(&file_path)
+            .unwrap()
+            .verify()
+            .unwrap();
+        assert_eq!(Some(Path::new("base/base.json")), config.get_extends());
+        assert_eq!(&[PathBuf::from("worker.yml")], config.get_include());
+        assert!(!config.get_cascade_kill());
+        assert_eq!(Some(base_dir.as_path()), config.get_cwd());
+        assert_eq!(
+            &StopPolicy::new(StopSignal::Int, Duration::from_millis(1000)),
+            config.get_stop()
+        );
+        assert_eq!(
+            BTreeMap::from([("A".into(), "1".into()), ("B".into(), "2".into())]),
+            *config.get_env()
+        );
+        assert_eq!(&[base_dir.join("base.env")], config.get_env_files());
+        let names: Vec<_> = config.get_start().iter().map(App::get_name).collect();
+        assert_eq!(vec![Some("web"), Some("db"), Some("worker")], names);
+        assert_eq!(
+            BTreeMap::from([("X".into(), "1".into()), ("Y".into(), "2".into())]),
+            *config.get_start()[0].get_env()
+        );
+
+        // The resolved config is a config of its own.
+        let resolved = Config::resolve_config_file(&file_path).unwrap();
+        assert!(!resolved.contains("extends ="));
+        let resolved_path = temp_dir.path().join("resolved.toml");
+        fs::write(&resolved_path, resolved).unwrap();
+        let other = Config::from_existing_config_file(&resolved_path)
+            .unwrap()
+            .verify()
+            .unwrap();
+        assert_eq!(config.get_start(), other.get_start());
+        assert_eq!(config.get_env(), other.get_env());
+
+        // Writing the config would merge the other files into it.
+        let source = fs::read_to_string(&file_path).unwrap();
+        assert!(config.create_file(&file_path, true).is_err());
+        assert_eq!(source, fs::read_to_string(&file_path).unwrap());
+
+        fs::write(
+            temp_dir.path().join("a.json"),
+            r#"{ "version": 3, "extends": "b.json", "cascadeKill": false, "start": [] }"#,
+        )
+        .unwrap();
+        fs::write(
+            temp_dir.path().join("b.json"),
+            r#"{ "include": ["a.json"] }"#,
+        )
+        .unwrap();
+        match Config::from_existing_config_file(temp_dir.path().join("a.json")).unwrap_err() {
+            ConfigError::InheritanceCycle { files } => assert_eq!(3, files.len()),
+            err => panic!("Expected a cycle but got {err:?}"),
+        }
+
+        fs::write(&file_path, "version = 3\ninclude = [\"missing.toml\"]\n").unwrap();
+        match Config::from_existing_config_file(&file_path).unwrap_err() {
+            ConfigError::FileNotFound { path } => {
+                assert_eq!(temp_dir.path().join("missing.toml"), path)
+            }
+            err => panic!("Expected a missing file but got {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_validate_file_formats() {
+        let temp_dir = TempDir::new("test_validate_file_formats").unwrap();
+        let exe =
+            serde_json::to_string(std::env::current_exe().unwrap().to_str().unwrap()).unwrap();
+        let toml = format!(
+            r#"version = 3
+cascadeKill = false
+exitOn = [{{ any = [2] }}]
+
+[[start]]
+command = {exe}
+
+[[start]]
+command = "sma_test_missing_program"
+"#
+        );
+        let yaml = format!(
+            r#"version: 3
+cascadeKill: false
+start:
+  - command: {exe}
+  - name: db
+    command: sma_test_missing_program
+exitOn:
+- any: [2]
+"#
+        );
+        for (file_name, source, positions) in [
+            ("config.toml", toml, [(9, 1), (3, 11)]),
+            ("config.yaml", yaml, [(6, 5), (8, 1)]),
+        ] {
+            let file_path = temp_dir.path().join(file_name);
+            fs::write(&file_path, source).unwrap();
+            let found: Vec<_> = Config::validate_file(&file_path)
+                .iter()
+                .map(|diagnostic| {
+                    (
+                        diagnostic.get_pointer().map(str::to_string),
+                        diagnostic.get_line().zip(diagnostic.get_column()),
+                    )
+                })
+                .collect();
+            let mut expected = vec![
+                (Some("/start/1/command".into()), Some(positions[0])),
+                (Some("/exitOn/0".into()), Some(positions[1])),
+            ];
+            expected.sort_by_key(|(_, position)| *position);
+            assert_eq!(expected, found, "{file_name}");
+        }
     }
 }
@@ -0,0 +1,99 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::LimitsProblem, ByteSize, Cgroups};
+
+/// The resources an application may use, they are applied when the
+/// application is spawned and are inherited by everything it starts.
+///
+/// Only Unix can limit a process this way, the limits are ignored elsewhere.
+/// The memory is limited through the cgroup of the application, so only on
+/// Linux.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Limits {
+    // This is the most memory the application and everything it starts can
+    // use together, it is the `memory.max` of the cgroup of the application.
+    // The application is not started if it can not be put in a cgroup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_memory: Option<ByteSize>,
+    // This is the niceness the application runs with, from -20 (the highest
+    // priority) to 19 (the lowest priority).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nice: Option<i32>,
+    // This is the most files the application can have open at once.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    open_files: Option<u64>,
+    // This is the biggest core dump the application can write, 0 turns off
+    // core dumps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    core_dump_size: Option<ByteSize>,
+}
+
+impl Limits {
+    pub fn new() -> Limits {
+        Default::default()
+    }
+
+    pub fn with_max_memory(mut self, max_memory: ByteSize) -> Limits {
+        self.max_memory = Some(max_memory);
+        self
+    }
+
+    pub fn with_nice(mut self, nice: i32) -> Limits {
+        self.nice = Some(nice);
+        self
+    }
+
+    pub fn with_open_files(mut self, open_files: u64) -> Limits {
+        self.open_files = Some(open_files);
+        self
+    }
+
+    pub fn with_core_dump_size(mut self, core_dump_size: ByteSize) -> Limits {
+        self.core_dump_size = Some(core_dump_size);
+        self
+    }
+
+    pub fn get_max_memory(&self) -> Option<ByteSize> {
+        self.max_memory
+    }
+
+    pub fn get_nice(&self) -> Option<i32> {
+        self.nice
+    }
+
+    pub fn get_open_files(&self) -> Option<u64> {
+        self.open_files
+    }
+
+    pub fn get_core_dump_size(&self) -> Option<ByteSize> {
+        self.core_dump_size
+    }
+
+    /// Checks that the limits can be applied to an application that is put
+    /// in `cgroups`.
+    pub(crate) fn validate(&self, cgroups: Cgroups) -> Result<(), LimitsProblem> {
+        if let Some(max_memory) = self.max_memory {
+            if max_memory.get_bytes() == 0 {
+                return Err(LimitsProblem::ZeroMaxMemory);
+            }
+            if cgroups != Cgroups::PerApp {
+                return Err(LimitsProblem::MaxMemoryWithoutPerAppCgroups);
+            }
+        }
+        if let Some(nice) = self.nice {
+            if !(-20..=19).contains(&nice) {
+                return Err(LimitsProblem::NiceOutOfRange { nice });
+            }
+        }
+        if self.open_files == Some(0) {
+            return Err(LimitsProblem::ZeroOpenFiles);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == Limits::default()
+    }
+}
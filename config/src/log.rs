@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::LogsProblem, ByteSize};
+
+/// Where the output of an application is written to.
+///
+/// The path is a template where `{name}` is replaced with the name of the
+/// application (or its index if it has no name), `{index}` with its index in
+/// `start` and `{stream}` with `stdout` or `stderr`. When both streams end up
+/// in the same file they share it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LogConfig {
+    // This is the path template of the log files. If it is relative it is
+    // relative to the `cwd` of the config.
+    path: String,
+    // This is how big a log file can get before it is rotated.
+    #[serde(default = "default_max_size")]
+    max_size: ByteSize,
+    // This is how many rotated log files are kept, the oldest is removed.
+    #[serde(default = "default_keep")]
+    keep: u32,
+}
+
+fn default_max_size() -> ByteSize {
+    ByteSize::new(10 * 1024 * 1024)
+}
+
+fn default_keep() -> u32 {
+    5
+}
+
+impl LogConfig {
+    pub fn new<S: Into<String>>(path: S) -> LogConfig {
+        LogConfig {
+            path: path.into(),
+            max_size: default_max_size(),
+            keep: default_keep(),
+        }
+    }
+
+    pub fn with_rotation(mut self, max_size: ByteSize, keep: u32) -> LogConfig {
+        self.max_size = max_size;
+        self.keep = keep;
+        self
+    }
+
+    pub fn get_path_template(&self) -> &str {
+        self.path.as_str()
+    }
+
+    pub fn get_max_size(&self) -> ByteSize {
+        self.max_size
+    }
+
+    pub fn get_keep(&self) -> u32 {
+        self.keep
+    }
+
+    /// Returns the log file for `stream` of the app at `index` in `start`.
+    pub fn get_path(&self, name: Option<&str>, index: usize, stream: &str) -> PathBuf {
+        let index = index.to_string();
+        self.path
+            .replace("{name}", name.unwrap_or(&index))
+            .replace("{index}", &index)
+            .replace("{stream}", stream)
+            .into()
+    }
+
+    /// Returns true if every app gets its own log files.
+    pub(crate) fn is_per_app(&self) -> bool {
+        self.path.contains("{name}") || self.path.contains("{index}")
+    }
+
+    /// Checks that the log config can be used.
+    pub(crate) fn validate(&self) -> Result<(), LogsProblem> {
+        if self.path.trim().is_empty() {
+            return Err(LogsProblem::EmptyPath);
+        }
+        if self.max_size.get_bytes() == 0 {
+            return Err(LogsProblem::ZeroMaxSize);
+        }
+        Ok(())
+    }
+}
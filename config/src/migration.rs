@@ -0,0 +1,149 @@
+use anyhow::{anyhow, bail};
+use serde_json::{json, Map, Value};
+
+use crate::CONFIG_VERSION;
+
+/// Upgrades a config from one version to the next one.
+type Migration = fn(&mut Map<String, Value>) -> anyhow::Result<()>;
+
+/// All the registered migrations, the migration at index `i` upgrades a
+/// config from version `i + 1` to version `i + 2`.
+const MIGRATIONS: &[Migration] = &[migrate_v1_to_v2, migrate_v2_to_v3];
+
+// There has to be exactly one migration to every version after the first one.
+const _: () = assert!(MIGRATIONS.len() as u32 + 1 == CONFIG_VERSION);
+
+/// Returns the version of the config in `value`.
+pub(crate) fn get_version(value: &Value) -> anyhow::Result<u32> {
+    let Some(version) = value.get("version") else {
+        bail!("The config does not have a `version`.")
+    };
+    let Some(version) = version.as_u64().and_then(|v| u32::try_from(v).ok()) else {
+        bail!("The `version` of the config must be a positive integer, but found `{version}`.")
+    };
+    if version == 0 {
+        bail!("The `version` of the config must be a positive integer, but found `0`.")
+    }
+    if version > CONFIG_VERSION {
+        bail!("The config has version `{version}`, but this version of sma only supports configs up to version `{CONFIG_VERSION}`. Update sma to use this config.")
+    }
+    Ok(version)
+}
+
+/// Runs every migration needed to bring `value` up to [`CONFIG_VERSION`] and
+/// returns the version the config had before.
+pub(crate) fn migrate(value: &mut Value) -> anyhow::Result<u32> {
+    let version = get_version(value)?;
+    let Some(config) = value.as_object_mut() else {
+        bail!("The config must be an object.")
+    };
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version as usize - 1) {
+        let from = index as u32 + 1;
+        migration(config).map_err(|e| {
+            e.context(anyhow!(
+                "Could not migrate the config from version `{from}` to version `{}`.",
+                from + 1
+            ))
+        })?;
+        config.insert("version".into(), (from + 1).into());
+    }
+
+    Ok(version)
+}
+
+/// Version 2 added the object form of the apps in `start`, every config that
+/// was valid in version 1 is still valid.
+fn migrate_v1_to_v2(_config: &mut Map<String, Value>) -> anyhow::Result<()> {
+    Ok(())
+}
+
+/// Version 3 replaced the single `exitOn` index with a list of exit
+/// conditions, an index `i` becomes the condition `{"any": [i]}`.
+fn migrate_v2_to_v3(config: &mut Map<String, Value>) -> anyhow::Result<()> {
+    let exit_on = config.entry("exitOn").or_insert(Value::Null);
+    *exit_on = match exit_on {
+        Value::Null => json!([]),
+        Value::Number(index) if index.is_u64() => json!([{ "any": [index] }]),
+        _ => bail!("The `exitOn` of the config must be an index in `start` or null, but found `{exit_on}`."),
+    };
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests_migration {
+    use super::*;
+
+    #[test]
+    fn test_migrate_v1() {
+        let mut value = json!({"version": 1, "cascadeKill": false, "start": ["a"], "exitOn": null});
+        assert_eq!(1, migrate(&mut value).unwrap());
+        assert_eq!(
+            json!({"version": CONFIG_VERSION, "cascadeKill": false, "start": ["a"], "exitOn": []}),
+            value
+        );
+    }
+
+    #[test]
+    fn test_migrate_v2_exit_on() {
+        let mut value = json!({"version": 2, "start": ["a", "b"], "exitOn": 1});
+        assert_eq!(2, migrate(&mut value).unwrap());
+        assert_eq!(
+            json!({"version": 3, "start": ["a", "b"], "exitOn": [{"any": [1]}]}),
+            value
+        );
+
+        let mut value = json!({"version": 2, "start": []});
+        migrate(&mut value).unwrap();
+        assert_eq!(json!({"version": 3, "start": [], "exitOn": []}), value);
+    }
+
+    #[test]
+    fn test_migrate_v2_err_bad_exit_on() {
+        let mut value = json!({"version": 2, "start": ["a"], "exitOn": "a"});
+        let err = migrate(&mut value).unwrap_err();
+        assert_eq!(
+            "Could not migrate the config from version `2` to version `3`.",
+            err.to_string()
+        );
+        assert_eq!(
+            "The `exitOn` of the config must be an index in `start` or null, but found `\"a\"`.",
+            err.root_cause().to_string()
+        );
+    }
+
+    #[test]
+    fn test_migrate_current_version() {
+        let mut value = json!({"version": CONFIG_VERSION, "start": []});
+        let expected = value.clone();
+        assert_eq!(CONFIG_VERSION, migrate(&mut value).unwrap());
+        assert_eq!(expected, value);
+    }
+
+    #[test]
+    fn test_migrate_err_future_version() {
+        let mut value = json!({"version": 7});
+        assert_eq!(
+            format!("The config has version `7`, but this version of sma only supports configs up to version `{CONFIG_VERSION}`. Update sma to use this config."),
+            migrate(&mut value).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_migrate_err_bad_version() {
+        assert_eq!(
+            "The config does not have a `version`.",
+            migrate(&mut json!({})).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "The `version` of the config must be a positive integer, but found `0`.",
+            migrate(&mut json!({"version": 0})).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "The `version` of the config must be a positive integer, but found `\"1\"`.",
+            migrate(&mut json!({"version": "1"}))
+                .unwrap_err()
+                .to_string()
+        );
+    }
+}
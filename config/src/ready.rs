@@ -0,0 +1,138 @@
+use std::{path::PathBuf, time::Duration};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ReadyProbeProblem;
+
+/// A check that tells when an application is ready, so the applications that
+/// depend on it can be started.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadyProbe {
+    #[serde(flatten)]
+    check: ReadyCheck,
+    // This is how many milliseconds the application gets to become ready
+    // before we give up.
+    #[serde(default = "default_timeout_ms")]
+    timeout_ms: u64,
+}
+
+/// What a [`ReadyProbe`] waits for.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum ReadyCheck {
+    /// A `host:port` that accepts tcp connections.
+    Tcp(String),
+    /// A file that exists, relative to the cwd of the application.
+    File(PathBuf),
+    /// A regex that matches a line the application writes to stdout.
+    Stdout(String),
+    /// A regex that matches a line the application writes to stderr.
+    Stderr(String),
+    /// A local `http://` url that responds with a 2xx status code.
+    Http(String),
+}
+
+fn default_timeout_ms() -> u64 {
+    30_000
+}
+
+impl From<ReadyCheck> for ReadyProbe {
+    /// Returns a probe with `check` and the default timeout.
+    fn from(check: ReadyCheck) -> Self {
+        ReadyProbe {
+            check,
+            timeout_ms: default_timeout_ms(),
+        }
+    }
+}
+
+impl ReadyProbe {
+    pub fn new(check: ReadyCheck, timeout: Duration) -> ReadyProbe {
+        ReadyProbe {
+            check,
+            timeout_ms: timeout.as_millis() as u64,
+        }
+    }
+
+    pub fn get_check(&self) -> &ReadyCheck {
+        &self.check
+    }
+
+    pub fn get_timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+
+    /// Checks that the probe can be used.
+    pub(crate) fn validate(&self) -> Result<(), ReadyProbeProblem> {
+        if self.timeout_ms == 0 {
+            return Err(ReadyProbeProblem::ZeroTimeout);
+        }
+        match &self.check {
+            ReadyCheck::Tcp(address) => {
+                if split_host_port(address).is_none() {
+                    return Err(ReadyProbeProblem::TcpAddress {
+                        address: address.clone(),
+                    });
+                }
+            }
+            ReadyCheck::File(path) => {
+                if path.as_os_str().is_empty() {
+                    return Err(ReadyProbeProblem::EmptyFile);
+                }
+            }
+            ReadyCheck::Stdout(pattern) | ReadyCheck::Stderr(pattern) => {
+                if let Err(error) = regex::Regex::new(pattern) {
+                    return Err(ReadyProbeProblem::Regex {
+                        pattern: pattern.clone(),
+                        error,
+                    });
+                }
+            }
+            ReadyCheck::Http(url) => {
+                if HttpTarget::parse(url).is_none() {
+                    return Err(ReadyProbeProblem::HttpUrl { url: url.clone() });
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The parts of an `http://` url that a [`ReadyCheck::Http`] connects to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpTarget {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl HttpTarget {
+    pub fn parse(url: &str) -> Option<HttpTarget> {
+        let rest = url.strip_prefix("http://")?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        let (host, port) = match split_host_port(authority) {
+            Some((host, port)) => (host, port),
+            None if !authority.is_empty() && !authority.contains(':') => (authority, 80),
+            None => return None,
+        };
+        Some(HttpTarget {
+            host: host.into(),
+            port,
+            path: path.into(),
+        })
+    }
+}
+
+/// Splits `host:port` into its parts.
+fn split_host_port(address: &str) -> Option<(&str, u16)> {
+    let (host, port) = address.rsplit_once(':')?;
+    if host.is_empty() {
+        return None;
+    }
+    Some((host, port.parse().ok()?))
+}
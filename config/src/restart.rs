@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::RestartProblem;
+
+/// When an application is started again after it exited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Restart {
+    #[default]
+    Never,
+    OnFailure,
+    Always,
+}
+
+/// How an application that exited is restarted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartPolicy {
+    // This is when the application is restarted.
+    #[serde(default)]
+    policy: Restart,
+    // This is how many times the application is restarted before we give up.
+    // If it is not given there is no limit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_retries: Option<u32>,
+    // This is how many milliseconds we wait before the first restart, the
+    // wait is doubled for every restart after it.
+    #[serde(default = "default_backoff_ms")]
+    backoff_ms: u64,
+    // This is the most milliseconds we wait before a restart.
+    #[serde(default = "default_max_backoff_ms")]
+    max_backoff_ms: u64,
+}
+
+fn default_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_max_backoff_ms() -> u64 {
+    60_000
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy::new(Restart::Never)
+    }
+}
+
+impl RestartPolicy {
+    pub fn new(policy: Restart) -> RestartPolicy {
+        RestartPolicy {
+            policy,
+            max_retries: None,
+            backoff_ms: default_backoff_ms(),
+            max_backoff_ms: default_max_backoff_ms(),
+        }
+    }
+
+    pub fn with_max_retries(mut self, max_retries: u32) -> RestartPolicy {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    pub fn with_backoff(mut self, backoff: Duration, max_backoff: Duration) -> RestartPolicy {
+        self.backoff_ms = backoff.as_millis() as u64;
+        self.max_backoff_ms = max_backoff.as_millis() as u64;
+        self
+    }
+
+    pub fn get_policy(&self) -> Restart {
+        self.policy
+    }
+
+    pub fn get_max_retries(&self) -> Option<u32> {
+        self.max_retries
+    }
+
+    /// Returns true if an application that exited, after already being
+    /// restarted `restarts` times, should be restarted again.
+    pub fn should_restart(&self, success: bool, restarts: u32) -> bool {
+        let wants_restart = match self.policy {
+            Restart::Never => false,
+            Restart::OnFailure => !success,
+            Restart::Always => true,
+        };
+        wants_restart && self.max_retries.is_none_or(|max| restarts < max)
+    }
+
+    /// Returns how long to wait before the restart that comes after
+    /// `restarts` restarts.
+    pub fn get_backoff(&self, restarts: u32) -> Duration {
+        let backoff_ms = self
+            .backoff_ms
+            .saturating_mul(2_u64.saturating_pow(restarts));
+        Duration::from_millis(backoff_ms.min(self.max_backoff_ms))
+    }
+
+    pub fn get_max_backoff(&self) -> Duration {
+        Duration::from_millis(self.max_backoff_ms)
+    }
+
+    /// Checks that the policy can be used.
+    pub(crate) fn validate(&self) -> Result<(), RestartProblem> {
+        if self.backoff_ms > self.max_backoff_ms {
+            return Err(RestartProblem::BackoffAboveMax {
+                backoff_ms: self.backoff_ms,
+                max_backoff_ms: self.max_backoff_ms,
+            });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn is_default(&self) -> bool {
+        *self == RestartPolicy::default()
+    }
+}
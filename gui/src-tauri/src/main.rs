@@ -5,7 +5,7 @@
 
 use std::path::PathBuf;
 
-use config::{Config, UnVerified};
+use config::{Config, Diagnostic, UnVerified};
 
 use tauri::Manager;
 
@@ -32,18 +32,28 @@ fn gui() -> anyhow::Result<()> {
     Ok(())
 }
 
+// The errors of the config are given as diagnostics, so the field they are
+// about can be highlighted.
 #[tauri::command]
-fn load_config(config_path: PathBuf) -> Result<config::Config<UnVerified>, String> {
-    config::Config::from_existing_config_file(config_path).map_err(|err| err.to_string())
+fn load_config(config_path: PathBuf) -> Result<config::Config<UnVerified>, Diagnostic> {
+    config::Config::from_existing_config_file(config_path).map_err(|err| Diagnostic::from(&err))
 }
 
 #[tauri::command]
-fn save_config(config: Config<UnVerified>, config_path: PathBuf) -> Result<(), String> {
+fn save_config(config: Config<UnVerified>, config_path: PathBuf) -> Result<(), Diagnostic> {
+    // This is checked before the config is verified, so the user is told why
+    // the config can not be saved instead of what the merged files left out.
+    if config.get_extends().is_some() || !config.get_include().is_empty() {
+        return Err(Diagnostic::new(
+            "This config `extends` or `include`s other config files, so it can not be saved from here. Change the files it is made of instead."
+                .into(),
+        ));
+    }
     config
         .verify()
-        .map_err(|err| err.to_string())?
+        .map_err(|err| Diagnostic::from(&err))?
         .create_file(config_path, true)
-        .map_err(|err| err.to_string())
+        .map_err(|err| Diagnostic::new(err.to_string()))
 }
 
 #[tauri::command]
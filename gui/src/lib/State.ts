@@ -1,4 +1,14 @@
-import type { IRustConfig, IToRustConfig } from '$lib/rust-bindings';
+import type {
+  IRustApp,
+  IRustCgroups,
+  IRustConfig,
+  IRustExitCondition,
+  IRustLogConfig,
+  IRustSingleInstance,
+  IRustStartEntry,
+  IRustStopPolicy,
+  IToRustConfig
+} from '$lib/rust-bindings';
 
 export class State {
   configPath: string;
@@ -11,30 +21,87 @@ export class State {
 }
 
 export class Config implements IToRustConfig {
+  $schema?: string;
+  // The GUI can not edit the files the config is made of, they are kept as they are.
+  extends?: string;
+  include?: string[];
   version: number;
   cwd: string;
   cascadeKill: boolean;
-  start: string[];
+  start: StartEntry[];
   exitOn: ExitOn;
+  stop: IRustStopPolicy;
+  // The GUI can not edit the logs yet, they are kept as they are.
+  logs?: IRustLogConfig;
+  singleInstance: IRustSingleInstance;
+  cgroups: IRustCgroups;
+  // The GUI can not edit the environment yet, it is kept as it is.
+  env?: Record<string, string>;
+  envFile?: string | string[];
+  cleanEnv?: boolean;
+  envAllowlist?: string[];
+  vars?: Record<string, string>;
 
   constructor(config: Config = {} as Config) {
     const {
-      version = 1,
+      $schema,
+      extends: extendsPath,
+      include,
+      version = 3,
       cwd = '.',
       cascadeKill = false,
-      start = [''],
-      exitOn = new ExitOn()
+      start = [new StartEntry()],
+      exitOn = new ExitOn(),
+      stop = defaultStopPolicy(),
+      logs,
+      singleInstance = 'allow',
+      cgroups = 'off',
+      env,
+      envFile,
+      cleanEnv,
+      envAllowlist,
+      vars
     } = config;
 
+    this.$schema = $schema;
+    this.extends = extendsPath;
+    this.include = include;
     this.version = version;
     this.cwd = cwd;
     this.cascadeKill = cascadeKill;
     this.start = start;
     this.exitOn = new ExitOn(exitOn);
+    this.stop = { ...stop };
+    this.logs = logs;
+    this.singleInstance = singleInstance;
+    this.cgroups = cgroups;
+    this.env = env;
+    this.envFile = envFile;
+    this.cleanEnv = cleanEnv;
+    this.envAllowlist = envAllowlist;
+    this.vars = vars;
   }
 
   static fromRustConfig(rustConfig: IRustConfig = {} as IRustConfig): Config {
-    const { version = 1, cwd = null, cascadeKill = false, start = [], exitOn = null } = rustConfig;
+    const {
+      $schema,
+      extends: extendsPath,
+      include,
+      version = 3,
+      cwd = null,
+      cascadeKill = false,
+      start = [],
+      exitOn = [],
+      stop = defaultStopPolicy(),
+      logs,
+      singleInstance = 'allow',
+      cgroups = 'off',
+      env,
+      envFile,
+      cleanEnv,
+      envAllowlist,
+      vars
+    } = rustConfig;
     const config = new Config();
 
     let tempCwd = '.';
@@ -42,33 +109,56 @@ export class Config implements IToRustConfig {
       tempCwd = cwd;
     }
 
+    // The GUI can only edit a single index, every other condition is kept as it is.
     const tempExitOn = new ExitOn();
-    if (exitOn !== null) {
-      tempExitOn.active = true;
-      tempExitOn.num = exitOn;
+    for (const condition of exitOn) {
+      if (
+        !tempExitOn.active &&
+        typeof condition === 'object' &&
+        'any' in condition &&
+        condition.any.length === 1 &&
+        typeof condition.any[0] === 'number'
+      ) {
+        tempExitOn.active = true;
+        tempExitOn.num = condition.any[0];
+      } else {
+        tempExitOn.others.push(condition);
+      }
     }
 
-    let tempStart = [''];
+    let tempStart = [new StartEntry()];
     if (start.length > 0) {
-      tempStart = start;
+      tempStart = start.map((entry) => new StartEntry(entry));
     }
 
+    config.$schema = $schema;
+    config.extends = extendsPath;
+    config.include = include;
     config.version = version;
     config.cwd = tempCwd;
     config.cascadeKill = cascadeKill;
     config.start = tempStart;
     config.exitOn = tempExitOn;
+    config.stop = { ...stop };
+    config.logs = logs;
+    config.singleInstance = singleInstance;
+    config.cgroups = cgroups;
+    config.env = env;
+    config.envFile = envFile;
+    config.cleanEnv = cleanEnv;
+    config.envAllowlist = envAllowlist;
+    config.vars = vars;
     return config;
   }
 
   cleanUpStart(): Config {
-    const isEmpty = (s: string): boolean => s.replaceAll(' ', '') == '';
+    const isEmpty = (entry: StartEntry): boolean => entry.command.replaceAll(' ', '') == '';
     const config: Config = new Config(this);
     config.exitOn.active = config.exitOn.active && !isEmpty(config.start[config.exitOn.num]);
     console.log(config);
     if (config.exitOn.active) {
-      let leftHalf: string[] = [];
-      let rightHalf: string[] = [];
+      let leftHalf: StartEntry[] = [];
+      let rightHalf: StartEntry[] = [];
 
       // cleanup all the items right of `num`
       if (config.start.length - 1 != config.exitOn.num) {
@@ -90,27 +180,71 @@ export class Config implements IToRustConfig {
 
   toRustConfig(): IRustConfig {
     const config = this.cleanUpStart();
-    const exitOn: number | null = config.exitOn.active ? config.exitOn.num : null;
+    const exitOn: IRustExitCondition[] = config.exitOn.active
+      ? [{ any: [config.exitOn.num] }, ...config.exitOn.others]
+      : [...config.exitOn.others];
 
     return {
+      $schema: config.$schema,
+      extends: config.extends,
+      include: config.include,
       version: config.version,
       cwd: config.cwd,
       cascadeKill: config.cascadeKill,
-      start: config.start,
-      exitOn
+      start: config.start.map((entry) => entry.toRustStartEntry()),
+      exitOn,
+      stop: config.stop,
+      logs: config.logs,
+      singleInstance: config.singleInstance,
+      cgroups: config.cgroups,
+      env: config.env,
+      envFile: config.envFile,
+      cleanEnv: config.cleanEnv,
+      envAllowlist: config.envAllowlist,
+      vars: config.vars
     };
   }
 }
 
+export class StartEntry {
+  command: string;
+  // The rest of the settings of an app that was written as an object.
+  app: IRustApp | null;
+
+  constructor(entry: IRustStartEntry = '') {
+    if (typeof entry === 'string') {
+      this.command = entry;
+      this.app = null;
+    } else {
+      this.command = entry.command;
+      this.app = { ...entry };
+    }
+  }
+
+  toRustStartEntry(): IRustStartEntry {
+    if (this.app === null) {
+      return this.command;
+    }
+    return { ...this.app, command: this.command };
+  }
+}
+
+function defaultStopPolicy(): IRustStopPolicy {
+  return { signal: 'SIGTERM', timeoutMs: 5000 };
+}
+
 class ExitOn {
   private _num: number;
   active: boolean;
   displayNum: number;
+  // The conditions that can not be edited in the GUI.
+  others: IRustExitCondition[];
 
   constructor(exitOn: ExitOn = {} as ExitOn) {
-    const { active = false, displayNum = 1 } = exitOn;
+    const { active = false, displayNum = 1, others = [] } = exitOn;
     this.active = active;
     this.displayNum = displayNum;
+    this.others = [...others];
     this._num = exitOn.num ? exitOn.num : displayNum - 1;
   }
 
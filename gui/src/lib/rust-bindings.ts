@@ -1,11 +1,109 @@
 import { invoke } from '@tauri-apps/api/tauri';
 
 export interface IRustConfig {
+  // The JSON Schema editors use to check the config.
+  $schema?: string;
+  // The files this config is made of, relative to the config file. A config
+  // that has them can not be saved.
+  extends?: string;
+  include?: string[];
   version: number;
   cwd: string | null;
   cascadeKill: boolean;
-  start: string[];
-  exitOn: number | null;
+  start: IRustStartEntry[];
+  exitOn: IRustExitCondition[];
+  stop: IRustStopPolicy;
+  logs?: IRustLogConfig;
+  singleInstance: IRustSingleInstance;
+  cgroups: IRustCgroups;
+  env?: Record<string, string>;
+  // A single path or a list of paths, relative to the config file.
+  envFile?: string | string[];
+  cleanEnv?: boolean;
+  envAllowlist?: string[];
+  // Used as `${NAME}` in `start` and `cwd`.
+  vars?: Record<string, string>;
+}
+
+export interface IRustApp {
+  name?: string;
+  command: string;
+  args?: string[];
+  cwd?: string;
+  env?: Record<string, string>;
+  envFile?: string | string[];
+  enabled?: boolean;
+  // The index or the name of the apps that have to be started first.
+  dependsOn?: (number | string)[];
+  ready?: IRustReadyProbe;
+  restart?: IRustRestartPolicy;
+  logs?: IRustLogConfig;
+  limits?: IRustLimits;
+}
+
+// The limits are only applied on Unix.
+export interface IRustLimits {
+  // A number of bytes or a size like "512MB", it needs `cgroups` to be
+  // "perApp".
+  maxMemory?: number | string;
+  nice?: number;
+  openFiles?: number;
+  coreDumpSize?: number | string;
+}
+
+// Exactly one of the checks is set.
+export interface IRustReadyProbe {
+  tcp?: string;
+  file?: string;
+  stdout?: string;
+  stderr?: string;
+  http?: string;
+  timeoutMs?: number;
+}
+
+// An app is either a command line or an object with all of its settings.
+export type IRustStartEntry = string | IRustApp;
+
+// Any of the conditions has to be met for sma to exit.
+export type IRustExitCondition =
+  | { any: (number | string)[] }
+  | { all: (number | string)[] }
+  | 'anyFailed'
+  | 'allExited';
+
+export interface IRustRestartPolicy {
+  policy?: 'never' | 'on-failure' | 'always';
+  maxRetries?: number;
+  backoffMs?: number;
+  maxBackoffMs?: number;
+}
+
+// `{name}`, `{index}` and `{stream}` in the path are replaced for every app.
+export interface IRustLogConfig {
+  path: string;
+  // A number of bytes or a size like "10MB".
+  maxSize?: number | string;
+  keep?: number;
+}
+
+export interface IRustStopPolicy {
+  signal: 'SIGTERM' | 'SIGINT' | 'SIGHUP' | 'SIGQUIT' | 'SIGKILL';
+  timeoutMs: number;
+}
+
+// What happens when the config is started while sma is already running it.
+export type IRustSingleInstance = 'allow' | 'refuse' | 'replace' | 'forward';
+
+// Whether the apps are put in cgroups of their own, this only works on Linux.
+export type IRustCgroups = 'off' | 'perConfig' | 'perApp';
+
+// Why a config could not be loaded or saved, `pointer` is the JSON pointer to
+// the field it is about, like `/start/0/cwd`.
+export interface IRustDiagnostic {
+  message: string;
+  pointer?: string;
+  line?: number;
+  column?: number;
 }
 
 export interface IToRustConfig {
@@ -0,0 +1,429 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, Sender},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context};
+use config::AppRef;
+use interprocess::local_socket::{prelude::*, GenericNamespaced, ListenerOptions, Name, Stream};
+use serde::{Deserialize, Serialize};
+
+/// A request to a running sma, sent as one line of JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "camelCase")]
+pub(crate) enum Request {
+    Status,
+    Stop,
+    // Starts every app that is not running.
+    Start,
+    Restart { app: AppRef },
+    Logs { app: AppRef, lines: usize },
+}
+
+/// The answer of a running sma to a [`Request`], sent as one line of JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum Response {
+    Status(Vec<AppStatus>),
+    Stopped,
+    // These are the indices in `start` of the apps that were started.
+    Started(Vec<usize>),
+    Restarted { pid: u32 },
+    Logs(Vec<LogTail>),
+    Error(String),
+}
+
+/// The status of an app in `start`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AppStatus {
+    pub(crate) index: usize,
+    pub(crate) name: Option<String>,
+    // This is only set while the app is running.
+    pub(crate) pid: Option<u32>,
+    pub(crate) state: AppState,
+    // This is how long the app has been running, it is only set while the
+    // app is running.
+    pub(crate) uptime_ms: Option<u64>,
+    // This is `None` if the app has not exited yet, or if it was ended by a
+    // signal.
+    pub(crate) last_exit_code: Option<i32>,
+    pub(crate) restarts: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum AppState {
+    Running,
+    // The app exited and is restarted by its restart policy.
+    Restarting,
+    Exited,
+    Disabled,
+}
+
+/// The last lines of a log file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct LogTail {
+    pub(crate) path: PathBuf,
+    pub(crate) lines: Vec<String>,
+}
+
+/// A request that was received by the control endpoint, the response is sent
+/// back to the client through the sender.
+pub(crate) type ControlRequest = (Request, Sender<Response>);
+
+/// Starts the control endpoint of the config at `config_file_path` and
+/// returns the requests that are sent to it.
+///
+/// Fails if another sma is already running the same config.
+pub(crate) fn listen(config_file_path: &Path) -> anyhow::Result<Receiver<ControlRequest>> {
+    let name = endpoint_name(config_file_path)?;
+    if Stream::connect(name.borrow()).is_ok() {
+        bail!(
+            "Another sma is already running the config `{}`.",
+            config_file_path.display()
+        )
+    }
+
+    // Nothing is listening on the name, so a socket file that is left behind
+    // by an sma that did not exit cleanly can be replaced.
+    let listener = ListenerOptions::new()
+        .name(name)
+        .try_overwrite(true)
+        .create_sync()?;
+
+    let (requests_tx, requests_rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for connection in listener.incoming().filter_map(Result::ok) {
+            let requests_tx = requests_tx.clone();
+            // A client that never sends its request must not block the others.
+            std::thread::spawn(move || {
+                let _ = serve(connection, &requests_tx);
+            });
+        }
+    });
+
+    Ok(requests_rx)
+}
+
+/// Answers the one request of a client.
+fn serve(connection: Stream, requests: &Sender<ControlRequest>) -> anyhow::Result<()> {
+    let mut connection = BufReader::new(connection);
+    let mut line = String::new();
+    connection.read_line(&mut line)?;
+
+    let response = match serde_json::from_str(&line) {
+        Ok(request) => {
+            let (response_tx, response_rx) = mpsc::channel();
+            // No one receives the requests anymore when sma is exiting.
+            requests
+                .send((request, response_tx))
+                .ok()
+                .and_then(|_| response_rx.recv().ok())
+                .unwrap_or_else(|| Response::Error("sma is exiting.".into()))
+        }
+        Err(e) => Response::Error(format!("The request `{}` is invalid: {e}", line.trim())),
+    };
+
+    let mut response = serde_json::to_string(&response)?;
+    response.push('\n');
+    connection.get_mut().write_all(response.as_bytes())?;
+    Ok(())
+}
+
+/// Sends `request` to the sma that is running the config at
+/// `config_file_path` and returns its response.
+pub(crate) fn send_request(config_file_path: &Path, request: &Request) -> anyhow::Result<Response> {
+    let connection = Stream::connect(endpoint_name(config_file_path)?).with_context(|| {
+        anyhow!(
+            "Could not connect to sma for the config `{}`, it might not be running.",
+            config_file_path.display()
+        )
+    })?;
+    let mut connection = BufReader::new(connection);
+
+    let mut request = serde_json::to_string(request)?;
+    request.push('\n');
+    connection.get_mut().write_all(request.as_bytes())?;
+
+    let mut line = String::new();
+    connection.read_line(&mut line)?;
+    if line.is_empty() {
+        bail!("sma closed the connection without answering.")
+    }
+    match serde_json::from_str(&line)? {
+        Response::Error(e) => bail!("{e}"),
+        response => Ok(response),
+    }
+}
+
+/// Returns the name of the control endpoint of the config at
+/// `config_file_path`.
+fn endpoint_name(config_file_path: &Path) -> io::Result<Name<'static>> {
+    format!("{}.sock", instance_id(config_file_path)).to_ns_name::<GenericNamespaced>()
+}
+
+/// Returns an id for the config at `config_file_path` that every sma running
+/// that config agrees on, e.g. `sma-0123456789abcdef`.
+pub(crate) fn instance_id(config_file_path: &Path) -> String {
+    let config_file_path =
+        std::fs::canonicalize(config_file_path).unwrap_or_else(|_| config_file_path.into());
+    format!(
+        "sma-{:016x}",
+        fnv1a(config_file_path.as_os_str().as_encoded_bytes())
+    )
+}
+
+/// The 64 bit FNV-1a hash, which unlike the hasher of std is the same in
+/// every build of sma.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Returns the last `lines` lines of the file at `path`.
+pub(crate) fn tail_lines(path: &Path, lines: usize) -> io::Result<Vec<String>> {
+    let content = std::fs::read(path)?;
+    let content = String::from_utf8_lossy(&content);
+    let all: Vec<&str> = content.lines().collect();
+    Ok(all[all.len().saturating_sub(lines)..]
+        .iter()
+        .map(|line| line.to_string())
+        .collect())
+}
+
+/// Prints the apps of the sma that is running the config at
+/// `config_file_path`.
+pub(crate) fn print_status(config_file_path: &Path) -> anyhow::Result<()> {
+    let Response::Status(apps) = send_request(config_file_path, &Request::Status)? else {
+        bail!("sma did not answer with a status.")
+    };
+    print!("{}", format_status(&apps));
+    Ok(())
+}
+
+/// Stops the sma that is running the config at `config_file_path`.
+pub(crate) fn stop(config_file_path: &Path) -> anyhow::Result<()> {
+    send_request(config_file_path, &Request::Stop)?;
+    println!("Stopped the config `{}`.", config_file_path.display());
+    Ok(())
+}
+
+/// Restarts `app` of the sma that is running the config at
+/// `config_file_path`.
+pub(crate) fn restart(config_file_path: &Path, app: AppRef) -> anyhow::Result<()> {
+    let Response::Restarted { pid } =
+        send_request(config_file_path, &Request::Restart { app: app.clone() })?
+    else {
+        bail!("sma did not answer with the restarted app.")
+    };
+    println!("Restarted the app `{app}`, its pid is `{pid}`.");
+    Ok(())
+}
+
+/// Prints the last `lines` lines of the log files of `app` of the sma that is
+/// running the config at `config_file_path`.
+pub(crate) fn print_logs(config_file_path: &Path, app: AppRef, lines: usize) -> anyhow::Result<()> {
+    let Response::Logs(tails) = send_request(config_file_path, &Request::Logs { app, lines })?
+    else {
+        bail!("sma did not answer with the logs.")
+    };
+    for (i, tail) in tails.iter().enumerate() {
+        if tails.len() > 1 {
+            if i > 0 {
+                println!();
+            }
+            println!("==> {} <==", tail.path.display());
+        }
+        for line in &tail.lines {
+            println!("{line}");
+        }
+    }
+    Ok(())
+}
+
+/// Returns the apps as a table with a row for every app.
+fn format_status(apps: &[AppStatus]) -> String {
+    let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".into());
+    let mut rows = vec![[
+        "INDEX".to_string(),
+        "NAME".into(),
+        "PID".into(),
+        "STATE".into(),
+        "UPTIME".into(),
+        "LAST EXIT".into(),
+        "RESTARTS".into(),
+    ]];
+    for app in apps {
+        let state = match app.state {
+            AppState::Running => "running",
+            AppState::Restarting => "restarting",
+            AppState::Exited => "exited",
+            AppState::Disabled => "disabled",
+        };
+        rows.push([
+            app.index.to_string(),
+            or_dash(app.name.clone()),
+            or_dash(app.pid.map(|pid| pid.to_string())),
+            state.into(),
+            or_dash(
+                app.uptime_ms
+                    .map(|uptime| format_uptime(Duration::from_millis(uptime))),
+            ),
+            or_dash(app.last_exit_code.map(|code| code.to_string())),
+            app.restarts.to_string(),
+        ]);
+    }
+
+    let mut widths = [0; 7];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    rows.iter()
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect();
+            format!("{}\n", cells.join("  ").trim_end())
+        })
+        .collect()
+}
+
+/// Returns `uptime` in its two biggest units, e.g. `2h 5m`.
+fn format_uptime(uptime: Duration) -> String {
+    let seconds = uptime.as_secs();
+    let units = [
+        (seconds / 86400, "d"),
+        (seconds / 3600 % 24, "h"),
+        (seconds / 60 % 60, "m"),
+        (seconds % 60, "s"),
+    ];
+    let first = units
+        .iter()
+        .position(|(amount, _)| *amount > 0)
+        .unwrap_or(units.len() - 1);
+    units[first..]
+        .iter()
+        .take(2)
+        .map(|(amount, unit)| format!("{amount}{unit}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests_control {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_request_json() {
+        assert_eq!(
+            r#"{"command":"status"}"#,
+            serde_json::to_string(&Request::Status).unwrap()
+        );
+        assert_eq!(
+            Request::Logs {
+                app: "api".into(),
+                lines: 10
+            },
+            serde_json::from_str(r#"{"command":"logs","app":"api","lines":10}"#).unwrap()
+        );
+        assert_eq!(
+            Request::Restart { app: 1.into() },
+            serde_json::from_str(r#"{"command":"restart","app":1}"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_uptime() {
+        assert_eq!("0s", format_uptime(Duration::ZERO));
+        assert_eq!("59s", format_uptime(Duration::from_millis(59_999)));
+        assert_eq!("1m 0s", format_uptime(Duration::from_secs(60)));
+        assert_eq!(
+            "2h 5m",
+            format_uptime(Duration::from_secs(2 * 3600 + 5 * 60 + 7))
+        );
+        assert_eq!("3d 0h", format_uptime(Duration::from_secs(3 * 86400 + 59)));
+    }
+
+    #[test]
+    fn test_format_status() {
+        let apps = vec![
+            AppStatus {
+                index: 0,
+                name: Some("api".into()),
+                pid: Some(1234),
+                state: AppState::Running,
+                uptime_ms: Some(61_000),
+                last_exit_code: None,
+                restarts: 0,
+            },
+            AppStatus {
+                index: 1,
+                name: None,
+                pid: None,
+                state: AppState::Exited,
+                uptime_ms: None,
+                last_exit_code: Some(1),
+                restarts: 2,
+            },
+        ];
+        assert_eq!(
+            "INDEX  NAME  PID   STATE    UPTIME  LAST EXIT  RESTARTS\n\
+             0      api   1234  running  1m 1s   -          0\n\
+             1      -     -     exited   -       1          2\n",
+            format_status(&apps)
+        );
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        let temp_dir = TempDir::new("test_tail_lines").unwrap();
+        let path = temp_dir.path().join("app.log");
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+        assert_eq!(vec!["two", "three"], tail_lines(&path, 2).unwrap());
+        assert_eq!(vec!["one", "two", "three"], tail_lines(&path, 10).unwrap());
+    }
+
+    #[test]
+    fn test_listen_and_send_request() {
+        let temp_dir = TempDir::new("test_listen_and_send_request").unwrap();
+        let config_file_path = temp_dir.path().join("config.json");
+        std::fs::write(&config_file_path, "{}").unwrap();
+
+        let requests = listen(&config_file_path).unwrap();
+        // Only one sma can run a config.
+        assert!(listen(&config_file_path).is_err());
+
+        std::thread::spawn(move || {
+            while let Ok((request, response)) = requests.recv() {
+                let _ = response.send(match request {
+                    Request::Status => Response::Status(vec![]),
+                    _ => Response::Error("Not supported.".into()),
+                });
+            }
+        });
+
+        assert_eq!(
+            Response::Status(vec![]),
+            send_request(&config_file_path, &Request::Status).unwrap()
+        );
+        assert_eq!(
+            "Not supported.",
+            send_request(&config_file_path, &Request::Stop)
+                .unwrap_err()
+                .to_string()
+        );
+
+        let other_config_file_path = temp_dir.path().join("other.json");
+        send_request(&other_config_file_path, &Request::Status).unwrap_err();
+    }
+}
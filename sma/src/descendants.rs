@@ -0,0 +1,349 @@
+use std::{
+    process::Child,
+    time::{Duration, Instant},
+};
+
+use sysinfo::{Pid, Process, ProcessRefreshKind, ProcessStatus, System, UpdateKind};
+
+use crate::platform;
+
+// How often the descendants of the apps are looked up again.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A process that was started by an app, or by a process the app started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Descendant {
+    pid: Pid,
+    // This tells the process apart from a later process with the same pid.
+    start_time: u64,
+    // This is the pid of the child of sma the process descends from, it is
+    // `None` if the process was first seen after it was reparented to sma.
+    root: Option<u32>,
+}
+
+/// Keeps track of the descendants of the children of sma.
+///
+/// A snapshot of the process tree misses the processes whose parent has
+/// already exited, because they are reparented. So a descendant is remembered
+/// from the first time it is seen until it exits. On Linux sma is a child
+/// subreaper as well (see [`platform::become_subreaper`]), so orphaned
+/// descendants are reparented to sma and reaped by it when they exit. Then
+/// every child of sma that is not an app is such an orphan, and it is tracked
+/// even if it was orphaned before it was ever seen.
+pub(crate) struct Descendants {
+    sys: System,
+    tracked: Vec<Descendant>,
+    // These are the pids of the children at the last lookup.
+    children: Vec<u32>,
+    refreshed_at: Option<Instant>,
+}
+
+impl Descendants {
+    pub(crate) fn new() -> Descendants {
+        Descendants {
+            sys: System::new(),
+            tracked: Vec::new(),
+            children: Vec::new(),
+            refreshed_at: None,
+        }
+    }
+
+    /// Looks up the descendants of `children` again. Unless `force` is true
+    /// this is only done when a child was replaced or when the last lookup is
+    /// older than [`REFRESH_INTERVAL`].
+    ///
+    /// Descendants that were reparented to sma and have exited are reaped.
+    /// Other children of sma are left alone, unless sma is a subreaper.
+    pub(crate) fn refresh(&mut self, children: &[Child], force: bool) {
+        let child_pids: Vec<u32> = children.iter().map(Child::id).collect();
+        let refresh_due = self
+            .refreshed_at
+            .is_none_or(|refreshed_at| refreshed_at.elapsed() >= REFRESH_INTERVAL);
+        if !force && !refresh_due && child_pids == self.children {
+            return;
+        }
+
+        self.sys.refresh_processes_specifics(
+            ProcessRefreshKind::new().with_cmd(UpdateKind::OnlyIfNotSet),
+        );
+        self.refreshed_at = Some(Instant::now());
+        self.children = child_pids;
+
+        let sys = &self.sys;
+        let me = Pid::from_u32(std::process::id());
+        let is_child = |pid: &Pid| self.children.contains(&pid.as_u32());
+        let mut tracked: Vec<Descendant> = self
+            .tracked
+            .iter()
+            .filter(|descendant| {
+                let Some(process) = sys.process(descendant.pid) else {
+                    return false;
+                };
+                if process.start_time() != descendant.start_time {
+                    return false;
+                }
+                // A descendant is not a child of sma unless it was reparented
+                // to it, then nothing else waits for it.
+                if process.status() == ProcessStatus::Zombie {
+                    platform::reap(descendant.pid.as_u32());
+                    return false;
+                }
+                true
+            })
+            .cloned()
+            .collect();
+
+        // When sma is a subreaper, only the orphans of the apps are reparented
+        // to it. They are not children of the apps anymore, so they are not
+        // found by the walk below.
+        if platform::is_subreaper() {
+            for (pid, process) in sys.processes() {
+                if process.parent() != Some(me)
+                    || is_child(pid)
+                    || process.thread_kind().is_some()
+                    || tracked.iter().any(|descendant| descendant.pid == *pid)
+                {
+                    continue;
+                }
+                if process.status() == ProcessStatus::Zombie {
+                    platform::reap(pid.as_u32());
+                    continue;
+                }
+                tracked.push(Descendant {
+                    pid: *pid,
+                    start_time: process.start_time(),
+                    root: None,
+                });
+            }
+        }
+
+        // The tree is walked layer by layer from the children, and from the
+        // descendants that are already known, as their parents may have
+        // exited. Other children of sma are not walked, unless they are the
+        // orphans that were tracked above.
+        let mut layer: Vec<(Pid, Option<u32>)> = self
+            .children
+            .iter()
+            .map(|&pid| (Pid::from_u32(pid), Some(pid)))
+            .chain(
+                tracked
+                    .iter()
+                    .map(|descendant| (descendant.pid, descendant.root)),
+            )
+            .collect();
+        while !layer.is_empty() {
+            let mut next_layer = Vec::new();
+            for (pid, process) in sys.processes() {
+                // On Linux the threads of a process are listed as its
+                // children, a signal sent to one of them would reach the
+                // whole process.
+                if *pid == me || is_child(pid) || process.thread_kind().is_some() {
+                    continue;
+                }
+                let Some(parent) = process.parent() else {
+                    continue;
+                };
+                let Some((_, root)) = layer.iter().find(|(layer_pid, _)| *layer_pid == parent)
+                else {
+                    continue;
+                };
+                if process.status() == ProcessStatus::Zombie
+                    || tracked.iter().any(|descendant| descendant.pid == *pid)
+                {
+                    continue;
+                }
+
+                next_layer.push((*pid, *root));
+                tracked.push(Descendant {
+                    pid: *pid,
+                    start_time: process.start_time(),
+                    root: *root,
+                });
+            }
+            layer = next_layer;
+        }
+
+        self.tracked = tracked;
+    }
+
+    /// Returns the pids of all the descendants.
+    pub(crate) fn get_pids(&self) -> Vec<Pid> {
+        self.tracked
+            .iter()
+            .map(|descendant| descendant.pid)
+            .collect()
+    }
+
+    /// Returns the pids of the descendants of the child with `child_pid`.
+    pub(crate) fn get_pids_of(&self, child_pid: u32) -> Vec<Pid> {
+        self.tracked
+            .iter()
+            .filter(|descendant| descendant.root == Some(child_pid))
+            .map(|descendant| descendant.pid)
+            .collect()
+    }
+
+    /// Returns how the process with `pid` looked at the last lookup.
+    pub(crate) fn get_process(&self, pid: Pid) -> Option<&Process> {
+        self.sys.process(pid)
+    }
+}
+
+#[cfg(test)]
+mod tests_descendants {
+    use std::process::Command;
+
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_descendants_are_remembered() {
+        let mut children = vec![Command::new("sh")
+            .args(["-c", "sleep 34 & sleep 34 & wait"])
+            .spawn()
+            .unwrap()];
+        std::thread::sleep(Duration::from_millis(200));
+
+        let mut descendants = Descendants::new();
+        descendants.refresh(&children, false);
+        let shell_pid = children[0].id();
+        let pids = descendants.get_pids_of(shell_pid);
+        assert_eq!(2, pids.len());
+
+        // The sleeps outlive the shell, and are still known after they were
+        // reparented.
+        children[0].kill().unwrap();
+        children[0].wait().unwrap();
+        descendants.refresh(&children, true);
+        assert_eq!(pids, descendants.get_pids_of(shell_pid));
+
+        for pid in &pids {
+            platform::send_signal(pid.as_u32(), config::StopSignal::Kill).unwrap();
+        }
+        std::thread::sleep(Duration::from_millis(200));
+        descendants.refresh(&children, true);
+        assert!(descendants.get_pids_of(shell_pid).is_empty());
+    }
+
+    // This is set when the test is run in a process of its own.
+    #[cfg(target_os = "linux")]
+    const SUBREAPER_TEST_VAR: &str = "SMA_SUBREAPER_TEST";
+
+    // Being a subreaper lasts for the whole process, and would reparent the
+    // orphans of the other tests to it, so the test runs in a process of its
+    // own.
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_orphans_are_reparented_to_sma() {
+        let output = Command::new(std::env::current_exe().unwrap())
+            .args([
+                "descendants::tests_descendants::orphans_are_reparented_to_sma",
+                "--exact",
+                "--ignored",
+            ])
+            .env(SUBREAPER_TEST_VAR, "1")
+            .output()
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{stdout}");
+        assert!(stdout.contains("1 passed"), "{stdout}");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[ignore = "it is run by test_orphans_are_reparented_to_sma"]
+    fn orphans_are_reparented_to_sma() {
+        if std::env::var_os(SUBREAPER_TEST_VAR).is_none() {
+            return;
+        }
+        platform::become_subreaper().unwrap();
+        // The shell waits until its stdin is closed, so the sleep is seen as
+        // its descendant before it is orphaned.
+        let mut shell = Command::new("sh")
+            .args(["-c", "sleep 35 > /dev/null & echo $!; read _"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut sleep_pid = String::new();
+        std::io::BufRead::read_line(
+            &mut std::io::BufReader::new(shell.stdout.as_mut().unwrap()),
+            &mut sleep_pid,
+        )
+        .unwrap();
+        let sleep_pid = Pid::from_u32(sleep_pid.trim().parse().unwrap());
+
+        let mut descendants = Descendants::new();
+        let mut children = vec![shell];
+        descendants.refresh(&children, true);
+        assert!(descendants.get_pids().contains(&sleep_pid));
+
+        drop(children[0].stdin.take());
+        children[0].wait().unwrap();
+        children.clear();
+        descendants.refresh(&children, true);
+        assert!(descendants.get_pids().contains(&sleep_pid));
+        assert_eq!(Some(std::process::id()), parent_pid(sleep_pid));
+
+        platform::send_signal(sleep_pid.as_u32(), config::StopSignal::Kill).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        // The sleep is reaped by sma, so it does not linger as a zombie.
+        descendants.refresh(&children, true);
+        assert!(!descendants.get_pids().contains(&sleep_pid));
+        assert_eq!(None, parent_pid(sleep_pid));
+
+        // The inner shell exits right away, so the sleep is orphaned before
+        // it was ever seen.
+        let mut shell = Command::new("sh")
+            .args(["-c", "sh -c 'sleep 36 > /dev/null & echo $!'; read _"])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut sleep_pid = String::new();
+        std::io::BufRead::read_line(
+            &mut std::io::BufReader::new(shell.stdout.as_mut().unwrap()),
+            &mut sleep_pid,
+        )
+        .unwrap();
+        let sleep_pid = Pid::from_u32(sleep_pid.trim().parse().unwrap());
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(Some(std::process::id()), parent_pid(sleep_pid));
+
+        let mut descendants = Descendants::new();
+        let mut children = vec![shell];
+        descendants.refresh(&children, true);
+        assert!(descendants.get_pids().contains(&sleep_pid));
+
+        platform::send_signal(sleep_pid.as_u32(), config::StopSignal::Kill).unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        descendants.refresh(&children, true);
+        assert!(!descendants.get_pids().contains(&sleep_pid));
+        assert_eq!(None, parent_pid(sleep_pid));
+
+        drop(children[0].stdin.take());
+        children[0].wait().unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    fn parent_pid(pid: Pid) -> Option<u32> {
+        let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+        // The name of the process is in parentheses and may contain spaces.
+        let (_, fields) = stat.rsplit_once(')')?;
+        fields.split_whitespace().nth(1)?.parse().ok()
+    }
+
+    // The children of sma that are not descendants of the apps, like the
+    // children of the other tests, are not reaped.
+    #[cfg(unix)]
+    #[test]
+    fn test_other_children_are_not_reaped() {
+        let mut other = Command::new("true").spawn().unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+
+        let mut descendants = Descendants::new();
+        descendants.refresh(&[], true);
+        assert!(descendants.get_pids().is_empty());
+        assert!(other.wait().unwrap().success());
+    }
+}
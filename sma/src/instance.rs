@@ -0,0 +1,237 @@
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context};
+use config::{Config, SingleInstance, Verified};
+
+use crate::control::{self, Request, Response};
+
+// How long we wait for a replaced sma to exit after it stopped its apps.
+const REPLACE_TIMEOUT: Duration = Duration::from_secs(10);
+// How often we check if a replaced sma has exited.
+const REPLACE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The lock of a config file, the config is running for as long as the lock
+/// is held. The lock file holds the pid of the sma that holds the lock.
+#[derive(Debug)]
+pub(crate) struct InstanceLock {
+    // The lock is let go when the file is closed.
+    _file: File,
+}
+
+/// What [`claim`] decided.
+#[derive(Debug)]
+pub(crate) enum Claim {
+    /// The config should be started, the lock is `None` if the config is not
+    /// a file or if another sma holds the lock and `singleInstance` is
+    /// `allow`.
+    Start(Option<InstanceLock>),
+    /// The config was handed to the sma that is already running it.
+    Forwarded,
+}
+
+/// Takes the lock of the config file of `config`, when another sma already
+/// holds it the `singleInstance` policy of the config decides what happens.
+pub(crate) fn claim(config: &Config<Verified>) -> anyhow::Result<Claim> {
+    let Some(config_file_path) = config.get_config_file_path() else {
+        return Ok(Claim::Start(None));
+    };
+    let lock_path = lock_path(config_file_path);
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .read(true)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| anyhow!("Could not open the lock file `{}`.", lock_path.display()))?;
+    if try_lock(&file, &lock_path)? {
+        return Ok(Claim::Start(Some(InstanceLock::new(file)?)));
+    }
+
+    let running = match fs::read_to_string(&lock_path)
+        .ok()
+        .and_then(|pid| pid.trim().parse::<u32>().ok())
+    {
+        Some(pid) => format!("sma with pid `{pid}`"),
+        None => "another sma".to_string(),
+    };
+    match config.get_single_instance() {
+        SingleInstance::Allow => Ok(Claim::Start(None)),
+        SingleInstance::Refuse => bail!(
+            "The config `{}` is already running in {running}.",
+            config_file_path.display()
+        ),
+        SingleInstance::Forward => {
+            let Response::Started(started) =
+                control::send_request(config_file_path, &Request::Start).with_context(|| {
+                    anyhow!(
+                    "Could not hand the config `{}` to the {running} that is already running it.",
+                    config_file_path.display()
+                )
+                })?
+            else {
+                bail!("sma did not answer with the started apps.")
+            };
+            if started.is_empty() {
+                println!(
+                    "The config `{}` is already running in {running}, and all of its apps are running.",
+                    config_file_path.display()
+                );
+            } else {
+                let labels: Vec<String> = started
+                    .into_iter()
+                    .map(|index| config.get_app_label(index))
+                    .collect();
+                println!(
+                    "The config `{}` is already running in {running}, it started the apps {} in `start`.",
+                    config_file_path.display(),
+                    labels.join(", ")
+                );
+            }
+            Ok(Claim::Forwarded)
+        }
+        SingleInstance::Replace => {
+            control::send_request(config_file_path, &Request::Stop).with_context(|| {
+                anyhow!(
+                    "Could not stop the config `{}` that is already running in {running}.",
+                    config_file_path.display()
+                )
+            })?;
+            // The old sma lets go of the lock when it exits, which is right
+            // after it has stopped its apps.
+            let deadline = Instant::now() + REPLACE_TIMEOUT;
+            while !try_lock(&file, &lock_path)? {
+                if Instant::now() >= deadline {
+                    bail!(
+                        "The {running} that ran the config `{}` did not exit within {} ms.",
+                        config_file_path.display(),
+                        REPLACE_TIMEOUT.as_millis()
+                    )
+                }
+                std::thread::sleep(REPLACE_POLL_INTERVAL);
+            }
+            println!(
+                "Stopped the config `{}` that was already running in {running}.",
+                config_file_path.display()
+            );
+            Ok(Claim::Start(Some(InstanceLock::new(file)?)))
+        }
+    }
+}
+
+impl InstanceLock {
+    fn new(mut file: File) -> anyhow::Result<InstanceLock> {
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+        Ok(InstanceLock { _file: file })
+    }
+}
+
+/// Returns true if an sma holds the lock of the config at `config_file_path`.
+pub(crate) fn is_running(config_file_path: &Path) -> anyhow::Result<bool> {
+    let lock_path = lock_path(config_file_path);
+    let file = match File::open(&lock_path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => {
+            return Err(anyhow!(e).context(anyhow!(
+                "Could not open the lock file `{}`.",
+                lock_path.display()
+            )))
+        }
+    };
+    // The lock is let go again when `file` is dropped.
+    Ok(!try_lock(&file, &lock_path)?)
+}
+
+/// Returns true if the lock was taken, and false if another sma holds it.
+fn try_lock(file: &File, lock_path: &Path) -> anyhow::Result<bool> {
+    match file.try_lock() {
+        Ok(()) => Ok(true),
+        Err(TryLockError::WouldBlock) => Ok(false),
+        Err(TryLockError::Error(e)) => Err(anyhow!(e).context(anyhow!(
+            "Could not lock the lock file `{}`.",
+            lock_path.display()
+        ))),
+    }
+}
+
+/// Returns the path of the lock file of the config at `config_file_path`.
+fn lock_path(config_file_path: &Path) -> PathBuf {
+    std::env::temp_dir().join(format!("{}.lock", control::instance_id(config_file_path)))
+}
+
+#[cfg(test)]
+mod tests_instance {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    fn config_file(temp_dir: &TempDir, single_instance: SingleInstance) -> Config<Verified> {
+        let config_file_path = temp_dir.path().join("config.json");
+        Config::default()
+            .with_single_instance(single_instance)
+            .verify()
+            .unwrap()
+            .create_file(&config_file_path, true)
+            .unwrap();
+        Config::from_existing_config_file(config_file_path)
+            .unwrap()
+            .verify()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_claim_allow_and_refuse() {
+        let temp_dir = TempDir::new("test_claim_allow_and_refuse").unwrap();
+        let config = config_file(&temp_dir, SingleInstance::Refuse);
+
+        let Claim::Start(Some(_lock)) = claim(&config).unwrap() else {
+            panic!("Expected the lock to be taken");
+        };
+        let pid = fs::read_to_string(lock_path(config.get_config_file_path().unwrap())).unwrap();
+        assert_eq!(std::process::id().to_string(), pid);
+
+        let err = claim(&config).unwrap_err().to_string();
+        assert_eq!(
+            format!(
+                "The config `{}` is already running in sma with pid `{pid}`.",
+                config.get_config_file_path().unwrap().display()
+            ),
+            err
+        );
+
+        let config = config_file(&temp_dir, SingleInstance::Allow);
+        assert!(matches!(claim(&config).unwrap(), Claim::Start(None)));
+    }
+
+    #[test]
+    fn test_claim_after_lock_is_dropped() {
+        let temp_dir = TempDir::new("test_claim_after_lock_is_dropped").unwrap();
+        let config = config_file(&temp_dir, SingleInstance::Refuse);
+
+        drop(claim(&config).unwrap());
+        assert!(matches!(claim(&config).unwrap(), Claim::Start(Some(_))));
+    }
+
+    #[test]
+    fn test_claim_forward() {
+        let temp_dir = TempDir::new("test_claim_forward").unwrap();
+        let config = config_file(&temp_dir, SingleInstance::Forward);
+
+        let _lock = claim(&config).unwrap();
+        let requests = control::listen(config.get_config_file_path().unwrap()).unwrap();
+        let handler = std::thread::spawn(move || {
+            let (request, response) = requests.recv().unwrap();
+            response.send(Response::Started(vec![])).unwrap();
+            request
+        });
+
+        assert!(matches!(claim(&config).unwrap(), Claim::Forwarded));
+        assert_eq!(Request::Start, handler.join().unwrap());
+    }
+}
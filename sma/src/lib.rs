@@ -1,25 +1,140 @@
-use config::{Config, Verified};
+mod control;
+mod descendants;
+mod instance;
+mod log_file;
+mod output;
+mod platform;
+mod ready;
+mod state;
+
+use config::{
+    App, AppRef, Cgroups, Config, Diagnostic, ExitCondition, Limits, LogConfig, MigrationOutcome,
+    ReadyCheck, ReadyProbe, Restart, RestartPolicy, SingleInstance, StopPolicy, StopSignal,
+    UnVerified, Verified,
+};
+use control::{AppState, AppStatus, ControlRequest, LogTail, Request, Response};
+use descendants::Descendants;
+use log_file::LogFile;
+use output::OutputTarget;
+use state::StateRecorder;
 
 use std::{
     ffi::OsStr,
-    os::windows::process::CommandExt,
-    path::PathBuf,
-    process::{Child, Command},
+    path::{Path, PathBuf},
+    process::{Child, Command, ExitStatus, Stdio},
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, bail, Context, Ok};
-use sysinfo::{
-    Pid, Process, ProcessRefreshKind, RefreshKind, System,
-};
+use cli::{DiagnosticsFormat, OutputArgs, OutputMode};
+use regex::Regex;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, System};
+
+// How often we check if the processes we are stopping have exited.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(50);
+// How long we wait for the last output of the apps before we exit.
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
 
 pub fn run() -> anyhow::Result<()> {
-    let config = match cli::parse_args(std::env::args())? {
-        cli::Commands::Start { start, exit_on } => {
-            Config::new(None, false, start, exit_on).verify()
+    let (config, output) = match cli::parse_args(std::env::args())? {
+        cli::Commands::Start {
+            start,
+            exit_on,
+            exit_on_all,
+            exit_on_any_failed,
+            exit_on_all_exited,
+            log_path,
+            log_max_size,
+            log_keep,
+            write_config,
+            output,
+            options,
+        } => {
+            let mut exit_conditions = Vec::new();
+            if !exit_on.is_empty() {
+                exit_conditions.push(ExitCondition::Any(
+                    exit_on
+                        .iter()
+                        .map(|app_ref| app_ref.parse())
+                        .collect::<Result<_, _>>()?,
+                ));
+            }
+            if !exit_on_all.is_empty() {
+                exit_conditions.push(ExitCondition::All(
+                    exit_on_all
+                        .iter()
+                        .map(|app_ref| app_ref.parse())
+                        .collect::<Result<_, _>>()?,
+                ));
+            }
+            if exit_on_any_failed {
+                exit_conditions.push(ExitCondition::AnyFailed);
+            }
+            if exit_on_all_exited {
+                exit_conditions.push(ExitCondition::AllExited);
+            }
+            let config = Config::new(
+                options.cwd.clone(),
+                options.cascade_kill,
+                with_app_options(start, &options.apps)?,
+                exit_conditions,
+            );
+            let config = match log_path {
+                Some(log_path) => {
+                    let mut logs = LogConfig::new(log_path);
+                    if log_max_size.is_some() || log_keep.is_some() {
+                        let max_size = match log_max_size {
+                            Some(max_size) => max_size.parse()?,
+                            None => logs.get_max_size(),
+                        };
+                        let keep = log_keep.unwrap_or(logs.get_keep());
+                        logs = logs.with_rotation(max_size, keep);
+                    }
+                    config.with_logs(logs)
+                }
+                None => config,
+            };
+            let config = with_start_options(config, *options);
+            if let Some(file_path) = write_config {
+                // Writes the config and wants to exit the program gracefully
+                config
+                    .verify()
+                    .with_context(|| anyhow!("Failed to verify the config."))?
+                    .create_file(&file_path, false)?;
+                println!("Wrote the config to `{}`.", file_path.display());
+                return Ok(());
+            }
+            (config.verify(), output)
+        }
+        cli::Commands::Config {
+            command: Some(cli::ConfigCommands::Migrate { file_path }),
+            ..
+        } => {
+            // Migrates the file and wants to exit the program gracefully
+            return migrate_config_file(&file_path);
         }
-        cli::Commands::Config { file_path } => {
-            Config::from_existing_config_file(file_path)?.verify()
+        cli::Commands::Config {
+            file_path,
+            print_resolved: true,
+            command: None,
+            ..
+        } => {
+            println!("{}", Config::resolve_config_file(file_path)?.trim_end());
+            return Ok(());
         }
+        cli::Commands::Config {
+            file_path,
+            output,
+            command: None,
+            ..
+        } => (
+            Config::from_existing_config_file(file_path)?.verify(),
+            output,
+        ),
         cli::Commands::CreateConfig {
             file_path,
             force_overide,
@@ -27,18 +142,354 @@ pub fn run() -> anyhow::Result<()> {
             // Creates a new file and wants to exit the program gracefully
             return Config::new_config_to_file(file_path, force_overide);
         }
-    }
-    .with_context(|| anyhow!("Failed to verify the config."))?;
+        cli::Commands::Convert {
+            input,
+            output,
+            force_overide,
+        } => {
+            // Writes the converted file and wants to exit the program gracefully
+            return Config::convert_config_file(input, output, force_overide);
+        }
+        cli::Commands::Schema => {
+            println!("{}", Config::json_schema());
+            return Ok(());
+        }
+        cli::Commands::Validate { file_path, format } => {
+            return validate_config_file(&file_path, format)
+        }
+        cli::Commands::Status { file_path } => return control::print_status(&file_path),
+        cli::Commands::Stop { file_path } => return control::stop(&file_path),
+        cli::Commands::Cleanup { file_path } => return state::cleanup(&file_path),
+        cli::Commands::Restart { app, file_path } => {
+            return control::restart(&file_path, app.parse()?)
+        }
+        cli::Commands::Logs {
+            app,
+            file_path,
+            lines,
+        } => return control::print_logs(&file_path, app.parse()?, lines),
+    };
+    let config = config.with_context(|| anyhow!("Failed to verify the config."))?;
+
+    // The lock is held until sma exits.
+    let instance_lock = match instance::claim(&config)? {
+        instance::Claim::Start(lock) => lock,
+        instance::Claim::Forwarded => return Ok(()),
+    };
+
+    // A config file can be controlled with `sma status` and the other
+    // control commands while it runs.
+    let control = config
+        .get_config_file_path()
+        .and_then(|config_file_path| match control::listen(config_file_path) {
+            Result::Ok(requests) => Some(requests),
+            Err(e) => {
+                eprintln!("The config can not be controlled while it runs: {e:#}");
+                None
+            }
+        });
+
+    // Only the sma that holds the lock records what it spawns, so it can be
+    // cleaned up with `sma cleanup` if sma is killed.
+    let mut state = instance_lock
+        .as_ref()
+        .and(config.get_config_file_path())
+        .map(StateRecorder::new);
 
     change_cwd(&config)?;
 
-    let mut children = spawn_processes(&config)?;
+    // Orphaned descendants of the apps are reparented to sma, so they can
+    // still be stopped with the apps.
+    if let Err(e) = platform::become_subreaper() {
+        eprintln!("Could not make sma the subreaper of the apps: {e}");
+    }
+
+    // Descendants can not escape the cgroups the apps are put in, without
+    // them the descendants are found by their parent pids.
+    if config.get_cgroups() != Cgroups::Off {
+        let created = platform::Cgroup::of_apps(None).and_then(|cgroup| cgroup.create());
+        if let Err(e) = created {
+            // The memory of an app can only be limited through its cgroup.
+            if config
+                .get_start()
+                .iter()
+                .any(|app| app.is_enabled() && app.get_limits().get_max_memory().is_some())
+            {
+                return Err(anyhow!(e).context(
+                    "Could not create a cgroup for the apps, which the `maxMemory` of the apps needs.",
+                ));
+            }
+            eprintln!("Could not create a cgroup for the apps, their descendants are found by their parent pids instead: {e}");
+        }
+    }
+
+    let mut children = spawn_processes(&config, &output)?;
+
+    wait_and_kill(
+        &config,
+        &output,
+        &mut children,
+        control.as_ref(),
+        state.as_mut(),
+    )?;
+
+    if let Some(state) = state {
+        state.remove();
+    }
+    // The cgroup is left behind if apps are still running in it.
+    if let Some(cgroup) = contained_in(&config, None) {
+        let _ = cgroup.remove();
+    }
+
+    output::wait_for_forwarders(OUTPUT_DRAIN_TIMEOUT);
+
+    Ok(())
+}
+
+/// Applies the settings of a config file that were given to `sma start`.
+fn with_start_options(
+    mut config: Config<UnVerified>,
+    options: cli::StartOptions,
+) -> Config<UnVerified> {
+    if let Some(cgroups) = options.cgroups {
+        config = config.with_cgroups(match cgroups {
+            cli::CgroupsArg::Off => Cgroups::Off,
+            cli::CgroupsArg::PerConfig => Cgroups::PerConfig,
+            cli::CgroupsArg::PerApp => Cgroups::PerApp,
+        });
+    }
+    if options.stop_signal.is_some() || options.stop_timeout.is_some() {
+        let default_stop = StopPolicy::default();
+        let signal = match options.stop_signal {
+            Some(cli::StopSignalArg::Term) => StopSignal::Term,
+            Some(cli::StopSignalArg::Int) => StopSignal::Int,
+            Some(cli::StopSignalArg::Hup) => StopSignal::Hup,
+            Some(cli::StopSignalArg::Quit) => StopSignal::Quit,
+            Some(cli::StopSignalArg::Kill) => StopSignal::Kill,
+            None => default_stop.get_signal(),
+        };
+        let timeout = options
+            .stop_timeout
+            .map_or(default_stop.get_timeout(), Duration::from_millis);
+        config = config.with_stop(StopPolicy::new(signal, timeout));
+    }
+    for (name, value) in options.env {
+        config = config.with_env(name, value);
+    }
+    for env_file in options.env_file {
+        config = config.with_env_file(env_file);
+    }
+    if options.clean_env {
+        config = config.with_clean_env(options.env_allow);
+    }
+    for (name, value) in options.var {
+        config = config.with_var(name, value);
+    }
+    if let Some(single_instance) = options.single_instance {
+        config = config.with_single_instance(match single_instance {
+            cli::SingleInstanceArg::Allow => SingleInstance::Allow,
+            cli::SingleInstanceArg::Refuse => SingleInstance::Refuse,
+            cli::SingleInstanceArg::Replace => SingleInstance::Replace,
+            cli::SingleInstanceArg::Forward => SingleInstance::Forward,
+        });
+    }
+    config
+}
+
+/// Makes the apps of `start` with the settings of the apps that were given to
+/// `sma start`. A setting that can only be given once per app takes its last
+/// value.
+fn with_app_options(start: Vec<String>, options: &cli::AppOptions) -> anyhow::Result<Vec<App>> {
+    fn of_app<T>(values: &[(usize, T)], index: usize) -> impl Iterator<Item = &T> {
+        values
+            .iter()
+            .filter(move |(app_index, _)| *app_index == index)
+            .map(|(_, value)| value)
+    }
+    fn check_indices<T>(option: &str, values: &[(usize, T)], len: usize) -> anyhow::Result<()> {
+        match values.iter().find(|(index, _)| *index >= len) {
+            Some((index, _)) => {
+                bail!("The index `{index}` of `--{option}` is not an index in `start`.")
+            }
+            None => Ok(()),
+        }
+    }
+
+    let len = start.len();
+    check_indices("name", &options.name, len)?;
+    check_indices("app-cwd", &options.app_cwd, len)?;
+    check_indices("depends-on", &options.depends_on, len)?;
+    check_indices("ready", &options.ready, len)?;
+    check_indices("ready-timeout", &options.ready_timeout, len)?;
+    check_indices("restart", &options.restart, len)?;
+    check_indices("max-retries", &options.max_retries, len)?;
+    check_indices("restart-backoff", &options.restart_backoff, len)?;
+    check_indices("restart-max-backoff", &options.restart_max_backoff, len)?;
+    check_indices("max-memory", &options.max_memory, len)?;
+    check_indices("nice", &options.nice, len)?;
+    check_indices("open-files", &options.open_files, len)?;
+    check_indices("core-dump-size", &options.core_dump_size, len)?;
+    check_indices("app-env", &options.app_env, len)?;
+    check_indices("app-env-file", &options.app_env_file, len)?;
+    check_indices("app-log-path", &options.app_log_path, len)?;
+
+    let mut apps = Vec::with_capacity(len);
+    for (index, command) in start.into_iter().enumerate() {
+        let mut app = App::new(command);
+        if let Some(name) = of_app(&options.name, index).last() {
+            app = app.with_name(name);
+        }
+        if let Some(cwd) = of_app(&options.app_cwd, index).last() {
+            app = app.with_cwd(cwd);
+        }
+        let depends_on: Vec<AppRef> = of_app(&options.depends_on, index)
+            .map(|app_ref| app_ref.parse())
+            .collect::<Result<_, _>>()?;
+        if !depends_on.is_empty() {
+            app = app.with_depends_on(depends_on);
+        }
+
+        let ready_timeout = of_app(&options.ready_timeout, index).last();
+        match of_app(&options.ready, index).last() {
+            Some(check) => {
+                let check = match check.clone() {
+                    cli::ReadyCheckArg::Tcp(address) => ReadyCheck::Tcp(address),
+                    cli::ReadyCheckArg::File(path) => ReadyCheck::File(path),
+                    cli::ReadyCheckArg::Stdout(pattern) => ReadyCheck::Stdout(pattern),
+                    cli::ReadyCheckArg::Stderr(pattern) => ReadyCheck::Stderr(pattern),
+                    cli::ReadyCheckArg::Http(url) => ReadyCheck::Http(url),
+                };
+                app = app.with_ready(match ready_timeout {
+                    Some(&timeout) => ReadyProbe::new(check, Duration::from_millis(timeout)),
+                    None => ReadyProbe::from(check),
+                });
+            }
+            None if ready_timeout.is_some() => {
+                bail!("The app at index `{index}` has a `--ready-timeout` but no `--ready`.")
+            }
+            None => (),
+        }
+
+        let policy = of_app(&options.restart, index).last();
+        let max_retries = of_app(&options.max_retries, index).last();
+        let backoff = of_app(&options.restart_backoff, index).last();
+        let max_backoff = of_app(&options.restart_max_backoff, index).last();
+        if let Some(policy) = policy {
+            let default_restart = RestartPolicy::default();
+            let mut restart = RestartPolicy::new(match policy {
+                cli::RestartArg::Never => Restart::Never,
+                cli::RestartArg::OnFailure => Restart::OnFailure,
+                cli::RestartArg::Always => Restart::Always,
+            })
+            .with_backoff(
+                backoff.map_or(default_restart.get_backoff(0), |&ms| {
+                    Duration::from_millis(ms)
+                }),
+                max_backoff.map_or(default_restart.get_max_backoff(), |&ms| {
+                    Duration::from_millis(ms)
+                }),
+            );
+            if let Some(&max_retries) = max_retries {
+                restart = restart.with_max_retries(max_retries);
+            }
+            app = app.with_restart(restart);
+        } else if max_retries.is_some() || backoff.is_some() || max_backoff.is_some() {
+            bail!("The app at index `{index}` has settings of a restart policy but no `--restart`.")
+        }
+
+        let mut limits = Limits::new();
+        if let Some(max_memory) = of_app(&options.max_memory, index).last() {
+            limits = limits.with_max_memory(max_memory.parse()?);
+        }
+        if let Some(&nice) = of_app(&options.nice, index).last() {
+            limits = limits.with_nice(nice);
+        }
+        if let Some(&open_files) = of_app(&options.open_files, index).last() {
+            limits = limits.with_open_files(open_files);
+        }
+        if let Some(core_dump_size) = of_app(&options.core_dump_size, index).last() {
+            limits = limits.with_core_dump_size(core_dump_size.parse()?);
+        }
+        app = app.with_limits(limits);
+
+        for (name, value) in of_app(&options.app_env, index) {
+            app = app.with_env(name, value);
+        }
+        for env_file in of_app(&options.app_env_file, index) {
+            app = app.with_env_file(env_file);
+        }
+        if let Some(log_path) = of_app(&options.app_log_path, index).last() {
+            app = app.with_logs(LogConfig::new(log_path));
+        }
+        apps.push(app);
+    }
+    Ok(apps)
+}
 
-    wait_and_kill(&config, &mut children)?;
+fn migrate_config_file(file_path: &Path) -> anyhow::Result<()> {
+    match Config::migrate_config_file(file_path)? {
+        MigrationOutcome::UpToDate => {
+            println!(
+                "The config `{}` is already at the current config version.",
+                file_path.display()
+            )
+        }
+        MigrationOutcome::Migrated {
+            from_version,
+            backup_path,
+        } => {
+            println!(
+                "Migrated the config `{}` from version `{from_version}`, the old config was saved to `{}`.",
+                file_path.display(),
+                backup_path.display()
+            )
+        }
+    }
+    Ok(())
+}
 
+fn validate_config_file(file_path: &Path, format: DiagnosticsFormat) -> anyhow::Result<()> {
+    let diagnostics = Config::validate_file(file_path);
+    println!("{}", format_diagnostics(file_path, &diagnostics, format)?);
+    if !diagnostics.is_empty() {
+        bail!(
+            "Found {} problem(s) in the config `{}`.",
+            diagnostics.len(),
+            file_path.display()
+        )
+    }
     Ok(())
 }
 
+/// Formats the problems found in the config at `file_path`, the human
+/// format has a line for every problem like `config.json:3:12: message`.
+fn format_diagnostics(
+    file_path: &Path,
+    diagnostics: &[Diagnostic],
+    format: DiagnosticsFormat,
+) -> anyhow::Result<String> {
+    if format == DiagnosticsFormat::Json {
+        return Ok(serde_json::to_string_pretty(diagnostics)?);
+    }
+    if diagnostics.is_empty() {
+        return Ok(format!("The config `{}` is valid.", file_path.display()));
+    }
+    let lines: Vec<String> = diagnostics
+        .iter()
+        .map(
+            |diagnostic| match (diagnostic.get_line(), diagnostic.get_column()) {
+                (Some(line), Some(column)) => format!(
+                    "{}:{line}:{column}: {}",
+                    file_path.display(),
+                    diagnostic.get_message()
+                ),
+                _ => format!("{}: {}", file_path.display(), diagnostic.get_message()),
+            },
+        )
+        .collect();
+    Ok(lines.join("\n"))
+}
+
 fn change_cwd(config: &Config<Verified>) -> anyhow::Result<()> {
     if let Some(cwd) = config.get_cwd() {
         std::env::set_current_dir(cwd).with_context(|| {
@@ -57,143 +508,799 @@ fn change_cwd(config: &Config<Verified>) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn wait_and_kill(config: &Config<Verified>, children: &mut [Child]) -> anyhow::Result<()> {
-    if let Some(exit_on_index) = config.get_exit_on() {
-        wait_on(children, exit_on_index)?;
-        if config.get_cascade_kill() {
-            kill_remaining_children_cascade(children)?;
-        } else {
-            kill_remaining_children(children)?;
-        }
+/// Supervises the children until a condition in `exitOn` is met and then stops
+/// the remaining children. An app that exits is restarted when its restart
+/// policy says so.
+///
+/// The requests from `control` are answered while the children are
+/// supervised, a stop request stops the remaining children like `exitOn`.
+/// The children and their descendants are recorded in `state`.
+fn wait_and_kill(
+    config: &Config<Verified>,
+    output: &OutputArgs,
+    children: &mut [Child],
+    control: Option<&Receiver<ControlRequest>>,
+    state: Option<&mut StateRecorder>,
+) -> anyhow::Result<()> {
+    // `children` only holds the enabled apps, in the order of `start`.
+    let app_indices: Vec<usize> = config
+        .get_start()
+        .iter()
+        .enumerate()
+        .filter(|(_, app)| app.is_enabled())
+        .map(|(index, _)| index)
+        .collect();
+    let restarts_apps = app_indices
+        .iter()
+        .any(|&index| config.get_start()[index].get_restart().get_policy() != Restart::Never);
+    // The output of these apps is read by sma, so sma has to keep running
+    // for as long as they do.
+    let captures_output = app_indices
+        .iter()
+        .any(|&index| captures_output(config, output, &config.get_start()[index]));
+
+    if config.get_exit_on().is_empty() && !restarts_apps && !captures_output && control.is_none() {
+        return Ok(());
+    }
+
+    let mut descendants = Descendants::new();
+    let stop_response = supervise(
+        config,
+        output,
+        children,
+        &app_indices,
+        control,
+        state,
+        &mut descendants,
+    )?;
+
+    if !config.get_exit_on().is_empty() || stop_response.is_some() {
+        descendants.refresh(children, true);
+        stop_children(
+            config,
+            children,
+            &descendants.get_pids(),
+            contained_in(config, None).as_ref(),
+        )?;
+    }
+    if let Some(stop_response) = stop_response {
+        let _ = stop_response.send(Response::Stopped);
     }
 
     Ok(())
 }
 
-fn spawn_processes(config: &Config<Verified>) -> anyhow::Result<Vec<Child>> {
-    let mut children = Vec::new();
+/// Stops `children` with the stop policy of the config, and their
+/// descendants as well if `cascadeKill` is set. The descendants are the
+/// processes in `cgroup` if the children are contained in one, and
+/// `descendants` otherwise.
+fn stop_children(
+    config: &Config<Verified>,
+    children: &mut [Child],
+    descendants: &[Pid],
+    cgroup: Option<&platform::Cgroup>,
+) -> anyhow::Result<()> {
+    let report = if config.get_cascade_kill() {
+        kill_remaining_children_cascade(children, descendants, cgroup, config.get_stop())?
+    } else {
+        kill_remaining_children(children, config.get_stop())?
+    };
+    report.print(config.get_stop());
+    Ok(())
+}
 
-    let mut cmd_vecs = Vec::new();
+/// Returns the cgroup that contains the app at `index` in `start`, or all the
+/// apps if `index` is `None`. There is none if the apps are not put in
+/// cgroups, if the cgroups could not be created, or if the apps share one
+/// cgroup and `index` is given.
+fn contained_in(config: &Config<Verified>, index: Option<usize>) -> Option<platform::Cgroup> {
+    let index = match (config.get_cgroups(), index) {
+        (Cgroups::Off, _) | (Cgroups::PerConfig, Some(_)) => return None,
+        (_, index) => index,
+    };
+    platform::Cgroup::of_apps(index)
+        .ok()
+        .filter(platform::Cgroup::exists)
+}
 
-    for (index, cmd_str) in config.get_start().iter().enumerate() {
-        if cmd_str.is_empty() {
-            bail!("The program at index `{index}` in `start` is empty.")
+/// Returns the cgroup the app at `index` in `start` is spawned in, the cgroup
+/// of the app is created if every app gets its own.
+fn spawn_cgroup(
+    config: &Config<Verified>,
+    index: usize,
+) -> anyhow::Result<Option<platform::Cgroup>> {
+    let Some(cgroup) = contained_in(config, None) else {
+        return Ok(None);
+    };
+    if config.get_cgroups() != Cgroups::PerApp {
+        return Ok(Some(cgroup));
+    }
+    let cgroup = platform::Cgroup::of_apps(Some(index))?;
+    cgroup.create().with_context(|| {
+        anyhow!(
+            "Could not create the cgroup `{}`.",
+            cgroup.get_path().display()
+        )
+    })?;
+    Ok(Some(cgroup))
+}
+
+/// Returns true if sma reads the stdout or stderr of `app`.
+fn captures_output(config: &Config<Verified>, output: &OutputArgs, app: &App) -> bool {
+    output.output == OutputMode::Prefixed
+        || config.get_app_logs(app).is_some()
+        || matches!(
+            app.get_ready().map(|probe| probe.get_check()),
+            Some(ReadyCheck::Stdout(_) | ReadyCheck::Stderr(_))
+        )
+}
+
+/// An app in `start` that sma keeps running.
+struct SupervisedApp {
+    // This is the index of the app in `start`.
+    index: usize,
+    // This is how many times the app has been restarted.
+    restarts: u32,
+    state: SupervisedState,
+    // This is when the app was last spawned.
+    started_at: Instant,
+    // This is how the app exited the last time.
+    last_exit: Option<ExitStatus>,
+}
+
+#[derive(PartialEq)]
+enum SupervisedState {
+    Running,
+    // The app exited and is restarted at the given time.
+    RestartAt(Instant),
+    // The app exited and is not restarted again, this is if it succeeded.
+    Exited(bool),
+}
+
+/// Polls the children and restarts them according to their restart policy.
+/// Returns when a condition in `exitOn` is met, or when every child has exited
+/// and is not restarted again.
+///
+/// When a stop request is received from `control` the sender of its response
+/// is returned, the response is sent once the children are stopped.
+fn supervise(
+    config: &Config<Verified>,
+    output: &OutputArgs,
+    children: &mut [Child],
+    app_indices: &[usize],
+    control: Option<&Receiver<ControlRequest>>,
+    mut state: Option<&mut StateRecorder>,
+    descendants: &mut Descendants,
+) -> anyhow::Result<Option<Sender<Response>>> {
+    // This is how every app in `start` exited, see `Config::should_exit`.
+    let mut exits = vec![None; config.get_start().len()];
+    let mut apps: Vec<SupervisedApp> = app_indices
+        .iter()
+        .map(|&index| SupervisedApp {
+            index,
+            restarts: 0,
+            state: SupervisedState::Running,
+            started_at: Instant::now(),
+            last_exit: None,
+        })
+        .collect();
+
+    loop {
+        for (child, supervised) in children.iter_mut().zip(apps.iter_mut()) {
+            let app = &config.get_start()[supervised.index];
+            let policy = app.get_restart();
+            let label = config.get_app_label(supervised.index);
+
+            match supervised.state {
+                SupervisedState::Running => {
+                    let Some(status) = child.try_wait()? else {
+                        continue;
+                    };
+                    supervised.last_exit = Some(status);
+                    if policy.should_restart(status.success(), supervised.restarts) {
+                        let backoff = policy.get_backoff(supervised.restarts);
+                        println!(
+                            "The app {label} in `start` exited with {status}, restarting it in {} ms.",
+                            backoff.as_millis()
+                        );
+                        supervised.state = SupervisedState::RestartAt(Instant::now() + backoff);
+                    } else {
+                        if policy.get_policy() != Restart::Never {
+                            println!(
+                                "The app {label} in `start` exited with {status} and is not restarted again."
+                            );
+                        }
+                        supervised.state = SupervisedState::Exited(status.success());
+                    }
+                }
+                SupervisedState::RestartAt(at) if Instant::now() >= at => {
+                    supervised.restarts += 1;
+                    let cmd_vec = app.get_cmd_vec().unwrap();
+                    match spawn_app(config, output, supervised.index, &cmd_vec) {
+                        Result::Ok((new_child, _)) => {
+                            *child = new_child;
+                            supervised.state = SupervisedState::Running;
+                            supervised.started_at = Instant::now();
+                            match policy.get_max_retries() {
+                                Some(max_retries) => println!(
+                                    "Restarted the app {label} in `start` ({} of {max_retries}).",
+                                    supervised.restarts
+                                ),
+                                None => println!(
+                                    "Restarted the app {label} in `start` ({}).",
+                                    supervised.restarts
+                                ),
+                            }
+                        }
+                        Err(e) if policy.should_restart(false, supervised.restarts) => {
+                            let backoff = policy.get_backoff(supervised.restarts);
+                            eprintln!(
+                                "Could not restart the app {label} in `start`, trying again in {} ms: {e:#}",
+                                backoff.as_millis()
+                            );
+                            supervised.state = SupervisedState::RestartAt(Instant::now() + backoff);
+                        }
+                        Err(e) => {
+                            eprintln!("Could not restart the app {label} in `start`: {e:#}");
+                            supervised.state = SupervisedState::Exited(false);
+                        }
+                    }
+                }
+                SupervisedState::RestartAt(_) | SupervisedState::Exited(_) => (),
+            }
+
+            if let SupervisedState::Exited(success) = supervised.state {
+                exits[supervised.index] = Some(success);
+            }
+        }
+
+        descendants.refresh(children, false);
+        if let Some(recorder) = state.as_deref_mut() {
+            if let Err(e) = recorder.record(children, app_indices, descendants) {
+                eprintln!("The processes of the config are not recorded anymore: {e:#}");
+                state = None;
+            }
+        }
+
+        if let Some(control) = control {
+            while let Result::Ok((request, response)) = control.try_recv() {
+                if request == Request::Stop {
+                    return Ok(Some(response));
+                }
+                let answer = answer_request(
+                    config,
+                    output,
+                    request,
+                    children,
+                    &mut apps,
+                    &mut exits,
+                    descendants,
+                );
+                // The client might have given up waiting.
+                let _ = response.send(answer);
+            }
+        }
+
+        let all_exited = apps
+            .iter()
+            .all(|supervised| matches!(supervised.state, SupervisedState::Exited(_)));
+        if all_exited || config.should_exit(&exits) {
+            return Ok(None);
         }
 
-        if let Some(cmd_vec) = shlex::split(cmd_str) {
-            if cmd_str.contains('\\') {
-                if let Some(exe) = cmd_vec.get(0) {
-                    if !PathBuf::from(exe).exists() {
-                        bail!("There seems to be a problem with the finding the executable for the program at index `{index}`. The executable we looked for was {exe} which does not exist. This might be because \"\\\\\" was used instead of \"/\".")
+        std::thread::sleep(STOP_POLL_INTERVAL);
+    }
+}
+
+/// Answers a request from the control endpoint, other than a stop request.
+fn answer_request(
+    config: &Config<Verified>,
+    output: &OutputArgs,
+    request: Request,
+    children: &mut [Child],
+    apps: &mut [SupervisedApp],
+    exits: &mut [Option<bool>],
+    descendants: &mut Descendants,
+) -> Response {
+    let find_app = |app: &AppRef| match config.resolve_app_ref(app) {
+        Some(index) => Result::Ok(index),
+        None => Err(Response::Error(format!(
+            "The app `{app}` could not be found in `start`."
+        ))),
+    };
+
+    match request {
+        Request::Status => Response::Status(
+            config
+                .get_start()
+                .iter()
+                .enumerate()
+                .map(|(index, app)| {
+                    let supervised = apps
+                        .iter()
+                        .zip(children.iter())
+                        .find(|(supervised, _)| supervised.index == index);
+                    let (state, pid, uptime_ms) = match supervised {
+                        None => (AppState::Disabled, None, None),
+                        Some((supervised, child)) => match supervised.state {
+                            SupervisedState::Running => (
+                                AppState::Running,
+                                Some(child.id()),
+                                Some(supervised.started_at.elapsed().as_millis() as u64),
+                            ),
+                            SupervisedState::RestartAt(_) => (AppState::Restarting, None, None),
+                            SupervisedState::Exited(_) => (AppState::Exited, None, None),
+                        },
+                    };
+                    AppStatus {
+                        index,
+                        name: app.get_name().map(String::from),
+                        pid,
+                        state,
+                        uptime_ms,
+                        last_exit_code: supervised
+                            .and_then(|(supervised, _)| supervised.last_exit)
+                            .and_then(|status| status.code()),
+                        restarts: supervised.map_or(0, |(supervised, _)| supervised.restarts),
+                    }
+                })
+                .collect(),
+        ),
+        Request::Start => {
+            let mut started = Vec::new();
+            for (child, supervised) in children.iter_mut().zip(apps.iter_mut()) {
+                if supervised.state == SupervisedState::Running {
+                    continue;
+                }
+                let cmd_vec = config.get_start()[supervised.index].get_cmd_vec().unwrap();
+                match spawn_app(config, output, supervised.index, &cmd_vec) {
+                    Result::Ok((new_child, _)) => {
+                        *child = new_child;
+                        supervised.state = SupervisedState::Running;
+                        supervised.started_at = Instant::now();
+                        exits[supervised.index] = None;
+                        started.push(supervised.index);
+                    }
+                    Err(e) => {
+                        return Response::Error(format!(
+                            "Could not start the app {} in `start`: {e:#}",
+                            config.get_app_label(supervised.index)
+                        ))
+                    }
+                }
+            }
+            Response::Started(started)
+        }
+        Request::Restart { app } => {
+            let index = match find_app(&app) {
+                Result::Ok(index) => index,
+                Err(response) => return response,
+            };
+            let Some(position) = apps.iter().position(|supervised| supervised.index == index)
+            else {
+                return Response::Error(format!(
+                    "The app {} in `start` is disabled.",
+                    config.get_app_label(index)
+                ));
+            };
+
+            descendants.refresh(children, true);
+            let child = &mut children[position];
+            let supervised = &mut apps[position];
+            let child_descendants = descendants.get_pids_of(child.id());
+            let restarted = stop_children(
+                config,
+                std::slice::from_mut(child),
+                &child_descendants,
+                contained_in(config, Some(index)).as_ref(),
+            )
+            .and_then(|_| {
+                supervised.last_exit = child.try_wait()?;
+                let cmd_vec = config.get_start()[index].get_cmd_vec().unwrap();
+                let (new_child, _) = spawn_app(config, output, index, &cmd_vec)?;
+                Ok(new_child)
+            });
+            match restarted {
+                Result::Ok(new_child) => {
+                    *child = new_child;
+                    supervised.state = SupervisedState::Running;
+                    supervised.started_at = Instant::now();
+                    exits[index] = None;
+                    println!(
+                        "Restarted the app {} in `start` on request.",
+                        config.get_app_label(index)
+                    );
+                    Response::Restarted { pid: child.id() }
+                }
+                Err(e) => Response::Error(format!(
+                    "Could not restart the app {} in `start`: {e:#}",
+                    config.get_app_label(index)
+                )),
+            }
+        }
+        Request::Logs { app, lines } => {
+            let index = match find_app(&app) {
+                Result::Ok(index) => index,
+                Err(response) => return response,
+            };
+            let app = &config.get_start()[index];
+            let Some(logs) = config.get_app_logs(app) else {
+                return Response::Error(format!(
+                    "The app {} in `start` does not write its output to log files.",
+                    config.get_app_label(index)
+                ));
+            };
+
+            let mut paths = vec![logs.get_path(app.get_name(), index, "stdout")];
+            let stderr_path = logs.get_path(app.get_name(), index, "stderr");
+            if stderr_path != paths[0] {
+                paths.push(stderr_path);
+            }
+            let mut tails = Vec::new();
+            for path in paths {
+                match control::tail_lines(&path, lines) {
+                    Result::Ok(lines) => tails.push(LogTail { path, lines }),
+                    Err(e) => {
+                        return Response::Error(format!(
+                            "Could not read the log file `{}`: {e}",
+                            path.display()
+                        ))
                     }
                 }
             }
-            cmd_vecs.push(cmd_vec);
+            Response::Logs(tails)
+        }
+        Request::Stop => unreachable!("A stop request is handled by `supervise`"),
+    }
+}
+
+/// Spawns every enabled app in `start`, an app is only spawned after all the
+/// apps it depends on are ready. The children are returned in the order of
+/// `start`.
+///
+/// If an app does not become ready, the apps that were already spawned are
+/// stopped again.
+fn spawn_processes(config: &Config<Verified>, output: &OutputArgs) -> anyhow::Result<Vec<Child>> {
+    let mut cmd_vecs = vec![None; config.get_start().len()];
+
+    for index in config.get_start_order() {
+        let app = &config.get_start()[index];
+
+        if app.get_command().is_empty() {
+            bail!("The program at index `{index}` in `start` is empty.")
+        }
+
+        if let Some(cmd_vec) = app.get_cmd_vec() {
+            cmd_vecs[index] = Some(cmd_vec);
         } else {
             bail!("The program at index `{index}` in `start` is erroneous")
         }
     }
 
-    for (index, cmd_vec) in cmd_vecs.iter().enumerate() {
-        children.push(spawn_process(cmd_vec).with_context(|| {
-            anyhow!(
-                "Could not spawn process `{}` at index `{index}` in `start`.",
-                cmd_vec[0]
-            )
-        })?);
+    let mut children = Vec::new();
+
+    if let Err(e) = start_apps(config, output, &cmd_vecs, &mut children) {
+        let mut children: Vec<Child> = children.into_iter().map(|(_, child)| child).collect();
+        let report = kill_remaining_children(&mut children, config.get_stop())?;
+        report.print(config.get_stop());
+        return Err(e);
     }
 
-    Ok(children)
-}
+    children.sort_by_key(|(index, _)| *index);
 
-fn kill_remaining_children_cascade(children: &mut [Child]) -> anyhow::Result<()> {
-    let mut sys = System::new_with_specifics(
-        RefreshKind::new().with_processes(ProcessRefreshKind::everything()),
-    );
+    Ok(children.into_iter().map(|(_, child)| child).collect())
+}
 
-    sys.refresh_processes();
+/// Spawns the apps in the order of [`Config::get_start_order`], an app is
+/// only spawned once every app before it is ready, every spawned app is pushed
+/// to `children`.
+fn start_apps(
+    config: &Config<Verified>,
+    output: &OutputArgs,
+    cmd_vecs: &[Option<Vec<String>>],
+    children: &mut Vec<(usize, Child)>,
+) -> anyhow::Result<()> {
+    let order = config.get_start_order();
+    let mut spawned = vec![false; cmd_vecs.len()];
+    let mut ready = vec![false; cmd_vecs.len()];
+    let mut exited: Vec<Option<Instant>> = vec![None; cmd_vecs.len()];
+    let (ready_tx, ready_rx) = mpsc::channel::<(usize, anyhow::Result<()>)>();
+
+    loop {
+        // Apps without a ready probe are ready as soon as they are spawned,
+        // so keep going until no more apps can be spawned.
+        let mut progress = true;
+        while progress {
+            progress = false;
+            for (position, &index) in order.iter().enumerate() {
+                if spawned[index] {
+                    continue;
+                }
+                // The apps an app depends on are always before it in the
+                // order, so waiting on every app before it also waits on them.
+                if order[..position].iter().any(|&before| !ready[before]) {
+                    break;
+                }
 
-    let me = Pid::from_u32(std::process::id());
+                let app = &config.get_start()[index];
+                let cmd_vec = cmd_vecs[index].as_ref().unwrap();
+                let (child, line_matched) = spawn_app(config, output, index, cmd_vec)
+                    .with_context(|| {
+                        anyhow!(
+                            "Could not spawn process `{}` at index `{index}` in `start`.",
+                            cmd_vec[0]
+                        )
+                    })?;
+                spawned[index] = true;
+                children.push((index, child));
+
+                if let Some(probe) = app.get_ready() {
+                    let probe = probe.clone();
+                    let cwd = config.get_app_cwd(app).unwrap_or_default();
+                    let ready_tx = ready_tx.clone();
+                    std::thread::spawn(move || {
+                        let result = ready::wait_until_ready(&probe, &cwd, line_matched);
+                        // The receiver is gone when another app already failed.
+                        let _ = ready_tx.send((index, result));
+                    });
+                } else {
+                    ready[index] = true;
+                    progress = true;
+                }
+            }
+        }
 
-    let mut cascaded_pids = vec![vec![]];
-    let mut updated = false;
-    let mut layer = 0;
-    let processes: Vec<(&Pid, &Process)> = sys
-        .processes()
-        .iter()
-        .filter(|(_, proc)| sys.process(me).unwrap().start_time() <= proc.start_time())
-        .collect();
+        if order.iter().all(|&index| ready[index]) {
+            return Ok(());
+        }
 
-    for (pid, proc) in &processes {
-        for child in children.iter() {
-            if let Some(parent) = proc.parent().map(|parent| parent.as_u32()) {
-                if parent == child.id() && me.as_u32() != parent {
-                    cascaded_pids[layer].push(pid.as_u32());
-                    updated = true;
+        match ready_rx.recv_timeout(STOP_POLL_INTERVAL) {
+            Result::Ok((index, result)) => {
+                result.with_context(|| {
+                    anyhow!(
+                        "The app {} in `start` did not become ready.",
+                        config.get_app_label(index)
+                    )
+                })?;
+                ready[index] = true;
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                // An app that exits before it is ready will never become ready.
+                for (index, child) in children.iter_mut() {
+                    if ready[*index] {
+                        continue;
+                    }
+                    let Some(status) = child.try_wait()? else {
+                        continue;
+                    };
+                    let exited_at = *exited[*index].get_or_insert_with(Instant::now);
+                    if exited_at.elapsed() >= ready::EXIT_GRACE {
+                        bail!(
+                            "The app {} in `start` exited with {status} before it became ready.",
+                            config.get_app_label(*index)
+                        )
+                    }
                 }
             }
+            Err(RecvTimeoutError::Disconnected) => unreachable!("`ready_tx` is still alive"),
         }
     }
+}
 
-    while updated {
-        updated = false;
-        layer += 1;
-        let mut new_pids_found = vec![];
-        for cascade_pid in &cascaded_pids[layer - 1] {
-            for (pid, proc) in &processes {
-                if let Some(parent) = proc.parent().map(|parent| parent.as_u32()) {
-                    if parent == *cascade_pid {
-                        new_pids_found.push(pid.as_u32());
-                        updated = true;
-                    }
-                }
+/// Spawns the app at `index` in `start`, the returned receiver is told when
+/// the output of the app matches the regex of its ready probe.
+fn spawn_app(
+    config: &Config<Verified>,
+    output: &OutputArgs,
+    index: usize,
+    cmd_vec: &[String],
+) -> anyhow::Result<(Child, Option<Receiver<()>>)> {
+    let app = &config.get_start()[index];
+    let mut cmd = new_command(cmd_vec)?;
+    if let Some(cwd) = config.get_app_cwd(app) {
+        cmd.current_dir(cwd);
+    }
+    // Without `cleanEnv` the app inherits the environment of sma, and only
+    // the variables the config sets are added to it.
+    if config.get_clean_env() {
+        cmd.env_clear().envs(config.get_app_env(index)?);
+    } else {
+        cmd.envs(config.get_app_configured_env(index)?);
+    }
+    platform::apply_limits(&mut cmd, app.get_limits());
+    let cgroup = spawn_cgroup(config, index)?;
+    if let Some(max_memory) = app.get_limits().get_max_memory() {
+        let Some(cgroup) = &cgroup else {
+            bail!("The `maxMemory` of the app at index `{index}` can not be applied, because the app is not in a cgroup.");
+        };
+        cgroup
+            .limit_memory(max_memory.get_bytes())
+            .with_context(|| {
+                anyhow!(
+                    "Could not limit the memory of the app at index `{index}` with the cgroup `{}`.",
+                    cgroup.get_path().display()
+                )
+            })?;
+    }
+    if let Some(cgroup) = cgroup {
+        cgroup.add_on_spawn(&mut cmd)?;
+    }
+
+    match output.output {
+        OutputMode::Detached => platform::detach(&mut cmd),
+        OutputMode::Inherit => (),
+        OutputMode::Prefixed => {
+            cmd.stdin(Stdio::null());
+        }
+    }
+
+    let (stdout_pattern, stderr_pattern) = match app.get_ready().map(|probe| probe.get_check()) {
+        Some(ReadyCheck::Stdout(pattern)) => (Some(Regex::new(pattern)?), None),
+        Some(ReadyCheck::Stderr(pattern)) => (None, Some(Regex::new(pattern)?)),
+        _ => (None, None),
+    };
+    let logs = config.get_app_logs(app);
+    let prefixed = output.output == OutputMode::Prefixed;
+    if logs.is_some() || prefixed || stdout_pattern.is_some() {
+        cmd.stdout(Stdio::piped());
+    }
+    if logs.is_some() || prefixed || stderr_pattern.is_some() {
+        cmd.stderr(Stdio::piped());
+    }
+
+    let mut stdout_targets = Vec::new();
+    let mut stderr_targets = Vec::new();
+    if let Some(logs) = logs {
+        let (stdout_file, stderr_file) = open_log_files(logs, app.get_name(), index)?;
+        stdout_targets.push(stdout_file);
+        stderr_targets.push(stderr_file);
+    }
+    match output.output {
+        // The output that is not written to log files stays on the console,
+        // like the output of the apps that are not captured.
+        OutputMode::Inherit if logs.is_none() => {
+            stdout_targets.push(OutputTarget::Stdout);
+            stderr_targets.push(OutputTarget::Stderr);
+        }
+        OutputMode::Prefixed => {
+            let name = app
+                .get_name()
+                .map_or_else(|| index.to_string(), String::from);
+            let width = prefix_width(config);
+            for (targets, stderr) in [(&mut stdout_targets, false), (&mut stderr_targets, true)] {
+                targets.push(OutputTarget::Prefixed {
+                    prefix: output::line_prefix(&name, index, width, stderr),
+                    stderr,
+                    timestamps: output.timestamps,
+                });
             }
         }
-        cascaded_pids.push(new_pids_found);
+        OutputMode::Inherit | OutputMode::Detached => (),
+    }
+
+    let mut child = spawn_command(cmd).map_err(|e| platform::limits_error(app.get_limits(), e))?;
+
+    let mut line_matched = None;
+    let mut with_matched = |pattern: Option<Regex>| {
+        pattern.map(|pattern| {
+            let (matched_tx, matched_rx) = mpsc::channel();
+            line_matched = Some(matched_rx);
+            (pattern, matched_tx)
+        })
+    };
+    if let Some(stdout) = child.stdout.take() {
+        output::forward_lines(stdout, stdout_targets, with_matched(stdout_pattern));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        output::forward_lines(stderr, stderr_targets, with_matched(stderr_pattern));
     }
 
-    kill_remaining_children(children)?;
+    Ok((child, line_matched))
+}
 
-    sys.refresh_processes();
-    for proc in cascaded_pids
+/// Returns the width of the longest prefix, so the prefixes of all apps can
+/// be padded to it.
+fn prefix_width(config: &Config<Verified>) -> usize {
+    config
+        .get_start()
         .iter()
-        .flatten()
-        .filter_map(|pid| sys.process(Pid::from_u32(*pid)))
-    {
-        #[cfg(debug_assertions)]
-        println!("Killing sub child: [{}] {}", proc.pid(), proc.name());
+        .enumerate()
+        .filter(|(_, app)| app.is_enabled())
+        .map(|(index, app)| {
+            app.get_name()
+                .map_or_else(|| index.to_string().len(), str::len)
+        })
+        .max()
+        .unwrap_or(0)
+}
 
-        proc.kill();
-    }
+/// Opens the log files for the stdout and stderr of the app at `index`, the
+/// streams share the file if they have the same path.
+fn open_log_files(
+    logs: &LogConfig,
+    name: Option<&str>,
+    index: usize,
+) -> anyhow::Result<(OutputTarget, OutputTarget)> {
+    let open = |path: PathBuf| {
+        LogFile::open(path.clone(), logs.get_max_size(), logs.get_keep())
+            .map(|file| Arc::new(Mutex::new(file)))
+            .with_context(|| anyhow!("Could not open the log file `{}`.", path.display()))
+    };
+
+    let stdout_path = logs.get_path(name, index, "stdout");
+    let stderr_path = logs.get_path(name, index, "stderr");
+    let stdout_file = open(stdout_path.clone())?;
+    let stderr_file = if stderr_path == stdout_path {
+        stdout_file.clone()
+    } else {
+        open(stderr_path)?
+    };
 
-    Ok(())
+    Ok((
+        OutputTarget::File(stdout_file),
+        OutputTarget::File(stderr_file),
+    ))
+}
+
+/// Stops `children` and their descendants.
+///
+/// If the children are contained in `cgroup` their descendants are the other
+/// processes in it, and whatever is still in it afterwards is killed, so no
+/// descendant can escape. Otherwise `descendants` are stopped, see
+/// [`Descendants`].
+fn kill_remaining_children_cascade(
+    children: &mut [Child],
+    descendants: &[Pid],
+    cgroup: Option<&platform::Cgroup>,
+    stop: &StopPolicy,
+) -> anyhow::Result<StopReport> {
+    let Some(cgroup) = cgroup else {
+        return stop_processes(children, descendants, stop);
+    };
+
+    let child_pids: Vec<u32> = children.iter().map(Child::id).collect();
+    let in_cgroup: Vec<Pid> = cgroup
+        .get_pids()
+        .with_context(|| {
+            anyhow!(
+                "Could not read the processes in the cgroup `{}`.",
+                cgroup.get_path().display()
+            )
+        })?
+        .into_iter()
+        .filter(|pid| !child_pids.contains(pid))
+        .map(Pid::from_u32)
+        .collect();
+    let report = stop_processes(children, &in_cgroup, stop)?;
+    // This also kills the processes that were started while the others were
+    // being stopped.
+    cgroup.kill().with_context(|| {
+        anyhow!(
+            "Could not kill the processes in the cgroup `{}`.",
+            cgroup.get_path().display()
+        )
+    })?;
+    Ok(report)
 }
 
-fn spawn_process<S: AsRef<OsStr>>(cmd_vec: &[S]) -> anyhow::Result<Child> {
+fn new_command<S: AsRef<OsStr>>(cmd_vec: &[S]) -> anyhow::Result<Command> {
     let mut cmd: Command;
 
-    if let Some(prog) = cmd_vec.get(0) {
+    if let Some(prog) = cmd_vec.first() {
         cmd = Command::new::<_>(prog.as_ref());
-        #[cfg(debug_assertions)]
-        dbg!(&cmd);
     } else {
         bail!("A program in `start` is empty.")
     }
 
-    // This cfg makes it possible to see what spawned processes print to the console.
-    // If false they will have there own std(in/out).
-    if cfg!(debug_assertions) {
-        cmd.args(&cmd_vec[1..]);
-    } else {
-        const DETACHED_PROCESS: u32 = 0x00000008;
-        const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
-        cmd.creation_flags(
-            // TODO: swap this out to the windows crate instead of winapi
-            // winapi::um::winbase::DETACHED_PROCESS | winapi::um::winbase::CREATE_NEW_PROCESS_GROUP,
-            DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP,
-        )
-        .args(&cmd_vec[1..]);
-    }
+    cmd.args(&cmd_vec[1..]);
+
+    Ok(cmd)
+}
+
+fn spawn_command(mut cmd: Command) -> anyhow::Result<Child> {
+    // The environment is left out, it can have secrets in it.
+    #[cfg(debug_assertions)]
+    dbg!(cmd.get_program(), cmd.get_args().collect::<Vec<_>>());
 
     let child = cmd.spawn()?;
 
@@ -203,26 +1310,137 @@ fn spawn_process<S: AsRef<OsStr>>(cmd_vec: &[S]) -> anyhow::Result<Child> {
     Ok(child)
 }
 
-fn wait_on<T: Into<usize>>(children: &mut [Child], index: T) -> anyhow::Result<()> {
-    if let Some(child) = children.get_mut(index.into()) {
-        child.wait()?;
+fn kill_remaining_children(
+    children: &mut [Child],
+    stop: &StopPolicy,
+) -> anyhow::Result<StopReport> {
+    stop_processes(children, &[], stop)
+}
+
+/// What happened while we stopped the remaining processes.
+#[derive(Debug, Default)]
+struct StopReport {
+    // The processes that did not exit within the grace period, as `(pid, name)`.
+    force_killed: Vec<(u32, String)>,
+}
+
+impl StopReport {
+    fn print(&self, stop: &StopPolicy) {
+        if self.force_killed.is_empty() {
+            return;
+        }
+        eprintln!(
+            "The following processes did not exit within {} ms and were force killed:",
+            stop.get_timeout().as_millis()
+        );
+        for (pid, name) in &self.force_killed {
+            eprintln!("    [{pid}] {name}");
+        }
     }
-    Ok(())
 }
 
-fn kill_remaining_children(children: &mut [Child]) -> anyhow::Result<()> {
+/// Asks every process that is still alive to exit with the signal of `stop`,
+/// waits for its grace period and then force kills whatever is left.
+fn stop_processes(
+    children: &mut [Child],
+    descendants: &[Pid],
+    stop: &StopPolicy,
+) -> anyhow::Result<StopReport> {
+    let mut sys = System::new();
+    let mut report = StopReport::default();
+
+    if stop.get_signal() != StopSignal::Kill {
+        let mut delivered = false;
+        for child in children.iter_mut() {
+            if child.try_wait()?.is_none() {
+                #[cfg(debug_assertions)]
+                println!("Stopping child: {}", child.id());
+
+                delivered |=
+                    platform::send_signal(child.id(), stop.get_signal()).with_context(|| {
+                        anyhow!("Could not send a signal to child with pid `{}`", child.id())
+                    })?;
+            }
+        }
+        for pid in descendants {
+            if descendant_is_alive(&mut sys, *pid) {
+                #[cfg(debug_assertions)]
+                println!("Stopping sub child: {pid}");
+
+                // Not being able to ask a sub child to stop is not fatal, it
+                // will be force killed when the grace period is over.
+                delivered |=
+                    platform::send_signal(pid.as_u32(), stop.get_signal()).unwrap_or(false);
+            }
+        }
+
+        if delivered {
+            let deadline = Instant::now() + stop.get_timeout();
+            while Instant::now() < deadline {
+                let mut any_alive = false;
+                for child in children.iter_mut() {
+                    any_alive |= child.try_wait()?.is_none();
+                }
+                for pid in descendants {
+                    any_alive |= descendant_is_alive(&mut sys, *pid);
+                }
+                if !any_alive {
+                    break;
+                }
+                std::thread::sleep(STOP_POLL_INTERVAL);
+            }
+        }
+    }
+
     for child in children.iter_mut() {
         if child.try_wait()?.is_none() {
             #[cfg(debug_assertions)]
             println!("Killing child: {}", child.id());
 
+            let name = process_name(&mut sys, Pid::from_u32(child.id()));
             if let Err(e) = child.kill() {
                 bail!(anyhow!("{e}")
                     .context(anyhow!("Could not kill child with pid `{}`", child.id())))
             }
+            // Reap the child so it does not linger as a zombie.
+            child.wait()?;
+            if stop.get_signal() != StopSignal::Kill {
+                report.force_killed.push((child.id(), name));
+            }
         }
     }
-    Ok(())
+
+    for pid in descendants {
+        if descendant_is_alive(&mut sys, *pid) {
+            if let Some(proc) = sys.process(*pid) {
+                #[cfg(debug_assertions)]
+                println!("Killing sub child: [{}] {}", proc.pid(), proc.name());
+
+                proc.kill();
+                if stop.get_signal() != StopSignal::Kill {
+                    report
+                        .force_killed
+                        .push((pid.as_u32(), proc.name().to_string()));
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+fn descendant_is_alive(sys: &mut System, pid: Pid) -> bool {
+    sys.refresh_process_specifics(pid, ProcessRefreshKind::new())
+        && sys
+            .process(pid)
+            .is_some_and(|proc| proc.status() != ProcessStatus::Zombie)
+}
+
+fn process_name(sys: &mut System, pid: Pid) -> String {
+    sys.refresh_process_specifics(pid, ProcessRefreshKind::new());
+    sys.process(pid)
+        .map(|proc| proc.name().to_string())
+        .unwrap_or_default()
 }
 
 #[cfg(test)]
@@ -230,10 +1448,15 @@ mod test_sma {
 
     use std::time::Duration;
 
+    use config::{ByteSize, Limits, ReadyProbe, RestartPolicy};
     use tempdir::TempDir;
 
     use super::*;
 
+    fn spawn_process<S: AsRef<OsStr>>(cmd_vec: &[S]) -> anyhow::Result<Child> {
+        spawn_command(new_command(cmd_vec)?)
+    }
+
     mod testbin {
         #![allow(non_upper_case_globals)]
         use test_binary::build_test_binary_once;
@@ -251,17 +1474,16 @@ mod test_sma {
         let config = Config::new(
             None,
             false,
-            vec![format!("{} SLEEP 1", test_bin_path.clone())],
-            None,
+            vec![format!("{} SLEEP 1", test_bin_path.clone()).into()],
+            vec![],
         )
         .verify()
         .unwrap();
         let now = std::time::Instant::now();
-        let success = spawn_processes(&config)
+        let success = spawn_processes(&config, &OutputArgs::default())
             .unwrap()
             .iter_mut()
-            .map(|child| child.wait().unwrap().success())
-            .all(|child| child);
+            .all(|child| child.wait().unwrap().success());
         let after = std::time::Instant::now();
         assert!(success);
         let diff_millis = (after - now).as_millis();
@@ -271,31 +1493,592 @@ mod test_sma {
     #[test]
     fn test_spawn_processes_fail_bad_executable_path() {
         let test_bin_path = testbin::path_to_test().into_string().unwrap();
+        let wrong_path = test_bin_path.replace(std::path::MAIN_SEPARATOR, "\\");
 
-        let config = Config::new(
+        let err = Config::new(
             None,
             false,
-            vec![format!("{} SLEEP 1", test_bin_path.clone())],
-            None,
+            vec![format!("{} SLEEP 1", wrong_path).into()],
+            vec![],
         )
         .verify()
-        .unwrap();
-        let err = spawn_processes(&config).unwrap_err().to_string();
-        let wrong_path = test_bin_path.replace('\\', "");
-        assert_eq!(
-            err,
-            format!("There seems to be a problem with the finding the executable for the program at index `0`. The executable we looked for was {wrong_path} which does not exist. This might be because \"\\\\\" was used instead of \"/\".")
-        )
+        .unwrap_err()
+        .to_string();
+        assert!(err.starts_with(
+            "The programs of the following apps in `start` can not be started:\n    index `0`: "
+        ));
+        assert!(err.ends_with("This might be because \"\\\\\" was used instead of \"/\"."));
     }
 
     #[test]
     fn test_spawn_processes_empty() {
         let config = Config::default().verify().unwrap();
-        let children = spawn_processes(&config).unwrap();
+        let children = spawn_processes(&config, &OutputArgs::default()).unwrap();
         assert_eq!(config.get_start().len(), children.len());
         assert_eq!(0, children.len());
     }
 
+    #[test]
+    fn test_spawn_processes_skips_disabled() {
+        let test_bin_path = testbin::path_to_test()
+            .into_string()
+            .unwrap()
+            .replace('\\', "/");
+
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                App::new(format!("{test_bin_path} SLEEP 0")).with_enabled(false),
+                App::new(test_bin_path).with_args(vec!["SLEEP".into(), "0".into()]),
+            ],
+            vec![],
+        )
+        .verify()
+        .unwrap();
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        assert_eq!(1, children.len());
+        assert!(children[0].wait().unwrap().success());
+    }
+
+    #[test]
+    fn test_spawn_processes_dependency_order() {
+        let test_bin_path = testbin::path_to_test().into_string().unwrap();
+        let app = || App::new(test_bin_path.as_str()).with_args(vec!["SLEEP".into(), "0".into()]);
+
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                app().with_depends_on(vec!["second".into()]),
+                app().with_name("second").with_depends_on(vec![2.into()]),
+                app(),
+            ],
+            vec![],
+        )
+        .verify()
+        .unwrap();
+        assert_eq!(vec![2, 1, 0], config.get_start_order());
+
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        assert_eq!(3, children.len());
+        assert!(children
+            .iter_mut()
+            .all(|child| child.wait().unwrap().success()));
+    }
+
+    #[test]
+    fn test_spawn_processes_app_cwd() {
+        let test_bin_path = testbin::path_to_test().into_string().unwrap();
+        let temp_dir = TempDir::new("test_spawn_processes_app_cwd").unwrap();
+        let file_name = "test_file_name";
+
+        let config = Config::new(
+            None,
+            false,
+            vec![App::new(test_bin_path)
+                .with_args(vec!["WRITE".into(), file_name.into(), "test".into()])
+                .with_cwd(temp_dir.path())],
+            vec![],
+        )
+        .verify()
+        .unwrap();
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        assert!(children[0].wait().unwrap().success());
+        assert_eq!(
+            "test",
+            std::fs::read_to_string(temp_dir.path().join(file_name)).unwrap()
+        );
+
+        // cleanup
+        drop(temp_dir)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_spawn_processes_app_env() {
+        let temp_dir = TempDir::new("test_spawn_processes_app_env").unwrap();
+        let file_path = temp_dir.path().join("env");
+
+        let config = Config::new(
+            None,
+            false,
+            vec![App::new("sh")
+                .with_args(vec![
+                    "-c".into(),
+                    format!("printf %s \"$SMA_TEST\" > '{}'", file_path.display()),
+                ])
+                .with_env("SMA_TEST", "hello")],
+            vec![],
+        )
+        .verify()
+        .unwrap();
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        assert!(children[0].wait().unwrap().success());
+        assert_eq!("hello", std::fs::read_to_string(file_path).unwrap());
+
+        // cleanup
+        drop(temp_dir)
+    }
+
+    #[cfg(unix)]
+    fn counting_app(file_path: &Path, exit_code: u8, restart: RestartPolicy) -> App {
+        App::new("sh")
+            .with_args(vec![
+                "-c".into(),
+                format!("echo run >> '{}'; exit {exit_code}", file_path.display()),
+            ])
+            .with_restart(restart)
+    }
+
+    #[cfg(unix)]
+    fn count_runs(file_path: &Path) -> usize {
+        std::fs::read_to_string(file_path).unwrap().lines().count()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_and_kill_restart_on_failure() {
+        let temp_dir = TempDir::new("test_wait_and_kill_restart_on_failure").unwrap();
+        let file_path = temp_dir.path().join("runs");
+        let restart = RestartPolicy::new(Restart::OnFailure)
+            .with_max_retries(2)
+            .with_backoff(Duration::from_millis(10), Duration::from_millis(100));
+
+        let config = Config::new(
+            None,
+            false,
+            vec![counting_app(&file_path, 1, restart)],
+            vec![ExitCondition::Any(vec![0.into()])],
+        )
+        .verify()
+        .unwrap();
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        wait_and_kill(&config, &OutputArgs::default(), &mut children, None, None).unwrap();
+        // The first run and two restarts.
+        assert_eq!(3, count_runs(&file_path));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_and_kill_on_failure_not_restarted_on_success() {
+        let temp_dir = TempDir::new("test_wait_and_kill_on_failure_not_restarted").unwrap();
+        let file_path = temp_dir.path().join("runs");
+        let restart = RestartPolicy::new(Restart::OnFailure)
+            .with_backoff(Duration::from_millis(10), Duration::from_millis(100));
+
+        let config = Config::new(
+            None,
+            false,
+            vec![counting_app(&file_path, 0, restart)],
+            vec![],
+        )
+        .verify()
+        .unwrap();
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        wait_and_kill(&config, &OutputArgs::default(), &mut children, None, None).unwrap();
+        assert_eq!(1, count_runs(&file_path));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_and_kill_restart_always_until_exit_on() {
+        let temp_dir = TempDir::new("test_wait_and_kill_restart_always").unwrap();
+        let file_path = temp_dir.path().join("runs");
+        let restart = RestartPolicy::new(Restart::Always)
+            .with_backoff(Duration::from_millis(50), Duration::from_millis(50));
+        let test_bin_path = testbin::path_to_test().into_string().unwrap();
+
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                counting_app(&file_path, 0, restart),
+                App::new(test_bin_path).with_args(vec!["SLEEP".into(), "1".into()]),
+            ],
+            vec![ExitCondition::Any(vec![1.into()])],
+        )
+        .verify()
+        .unwrap();
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        wait_and_kill(&config, &OutputArgs::default(), &mut children, None, None).unwrap();
+
+        let runs = count_runs(&file_path);
+        assert!(runs >= 3, "{runs}");
+        // Nothing is restarted after the `exitOn` app has exited.
+        std::thread::sleep(Duration::from_millis(200));
+        assert_eq!(runs, count_runs(&file_path));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_and_kill_exit_on_any_failed() {
+        let test_bin_path = testbin::path_to_test().into_string().unwrap();
+
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                App::new(test_bin_path).with_args(vec!["SLEEP".into(), "5".into()]),
+                App::new("sh").with_args(vec!["-c".into(), "exit 3".into()]),
+            ],
+            vec![ExitCondition::AnyFailed],
+        )
+        .verify()
+        .unwrap();
+
+        let now = Instant::now();
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        wait_and_kill(&config, &OutputArgs::default(), &mut children, None, None).unwrap();
+        assert!(now.elapsed() < Duration::from_secs(2));
+        assert!(!children[0].wait().unwrap().success());
+    }
+
+    #[test]
+    fn test_wait_and_kill_exit_on_all() {
+        let test_bin_path = testbin::path_to_test().into_string().unwrap();
+        let app = |seconds: &str| {
+            App::new(test_bin_path.as_str()).with_args(vec!["SLEEP".into(), seconds.into()])
+        };
+
+        let config = Config::new(
+            None,
+            false,
+            vec![app("0").with_name("fast"), app("1"), app("5")],
+            vec![ExitCondition::All(vec!["fast".into(), 1.into()])],
+        )
+        .verify()
+        .unwrap();
+
+        let now = Instant::now();
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        wait_and_kill(&config, &OutputArgs::default(), &mut children, None, None).unwrap();
+        let elapsed = now.elapsed();
+        assert!(
+            (Duration::from_secs(1)..Duration::from_secs(3)).contains(&elapsed),
+            "{elapsed:?}"
+        );
+        assert!(children[0].wait().unwrap().success());
+        assert!(children[1].wait().unwrap().success());
+    }
+
+    #[test]
+    fn test_wait_and_kill_control_requests() {
+        let test_bin_path = testbin::path_to_test().into_string().unwrap();
+        let app = App::new(test_bin_path.as_str()).with_args(vec!["SLEEP".into(), "10".into()]);
+
+        let config = Config::new(
+            None,
+            false,
+            vec![app.clone().with_name("api"), app.with_enabled(false)],
+            vec![],
+        )
+        .verify()
+        .unwrap();
+
+        let (control_tx, control_rx) = mpsc::channel();
+        let send = |request: Request| {
+            let (response_tx, response_rx) = mpsc::channel();
+            control_tx.send((request, response_tx)).unwrap();
+            response_rx
+        };
+        let status = send(Request::Status);
+        let restarted = send(Request::Restart { app: "api".into() });
+        let disabled = send(Request::Restart { app: 1.into() });
+        let missing = send(Request::Logs {
+            app: "web".into(),
+            lines: 10,
+        });
+        let stopped = send(Request::Stop);
+
+        let now = Instant::now();
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        let first_pid = children[0].id();
+        wait_and_kill(
+            &config,
+            &OutputArgs::default(),
+            &mut children,
+            Some(&control_rx),
+            None,
+        )
+        .unwrap();
+        assert!(now.elapsed() < Duration::from_secs(5));
+
+        let Response::Status(apps) = status.recv().unwrap() else {
+            panic!("Expected a status");
+        };
+        assert_eq!(2, apps.len());
+        assert_eq!(Some(first_pid), apps[0].pid);
+        assert_eq!(AppState::Running, apps[0].state);
+        assert_eq!(AppState::Disabled, apps[1].state);
+
+        let Response::Restarted { pid } = restarted.recv().unwrap() else {
+            panic!("Expected the app to be restarted");
+        };
+        assert_ne!(first_pid, pid);
+        assert_eq!(
+            Response::Error("The app index `1` in `start` is disabled.".into()),
+            disabled.recv().unwrap()
+        );
+        assert_eq!(
+            Response::Error("The app `web` could not be found in `start`.".into()),
+            missing.recv().unwrap()
+        );
+        assert_eq!(Response::Stopped, stopped.recv().unwrap());
+        assert!(children[0].try_wait().unwrap().is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_and_kill_writes_logs() {
+        let temp_dir = TempDir::new("test_wait_and_kill_writes_logs").unwrap();
+        let app = |name: &str| {
+            App::new("sh")
+                .with_args(vec!["-c".into(), "echo out; echo err >&2".into()])
+                .with_name(name)
+        };
+
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                app("split"),
+                app("shared").with_logs(LogConfig::new(
+                    temp_dir.path().join("shared.log").to_str().unwrap(),
+                )),
+            ],
+            vec![],
+        )
+        .with_logs(LogConfig::new(
+            temp_dir
+                .path()
+                .join("logs/{name}.{stream}.log")
+                .to_str()
+                .unwrap(),
+        ))
+        .verify()
+        .unwrap();
+
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        wait_and_kill(&config, &OutputArgs::default(), &mut children, None, None).unwrap();
+        output::wait_for_forwarders(Duration::from_secs(5));
+
+        let read = |path: &str| std::fs::read_to_string(temp_dir.path().join(path)).unwrap();
+        assert_eq!("out\n", read("logs/split.stdout.log"));
+        assert_eq!("err\n", read("logs/split.stderr.log"));
+        let shared = read("shared.log");
+        assert!(shared == "out\nerr\n" || shared == "err\nout\n", "{shared}");
+    }
+
+    fn ready_app(args: &[&str], check: ReadyCheck, timeout_ms: u64) -> App {
+        let test_bin_path = testbin::path_to_test().into_string().unwrap();
+        App::new(test_bin_path)
+            .with_args(args.iter().map(|arg| arg.to_string()).collect())
+            .with_ready(ReadyProbe::new(check, Duration::from_millis(timeout_ms)))
+    }
+
+    #[test]
+    fn test_spawn_processes_ready_file() {
+        let tmp_dir = TempDir::new("test_spawn_processes_ready_file").unwrap();
+        let ready_file = tmp_dir.path().join("ready");
+        let dependent_file = tmp_dir.path().join("dependent");
+        let ready_file_str = ready_file.to_str().unwrap();
+        let test_bin_path = testbin::path_to_test().into_string().unwrap();
+
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                App::new(test_bin_path)
+                    .with_args(vec![
+                        "WRITE".into(),
+                        dependent_file.to_str().unwrap().into(),
+                        "x".into(),
+                    ])
+                    .with_depends_on(vec!["server".into()]),
+                ready_app(
+                    &["SLEEP", "1", "WRITE", ready_file_str, "x"],
+                    ReadyCheck::File(ready_file.clone()),
+                    5000,
+                )
+                .with_name("server"),
+            ],
+            vec![],
+        )
+        .verify()
+        .unwrap();
+
+        let now = Instant::now();
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        assert!(now.elapsed() >= Duration::from_secs(1));
+        assert!(ready_file.exists());
+        assert!(children
+            .iter_mut()
+            .all(|child| child.wait().unwrap().success()));
+        assert!(dependent_file.exists());
+    }
+
+    #[test]
+    fn test_spawn_processes_ready_before_next() {
+        let tmp_dir = TempDir::new("test_spawn_processes_ready_before_next").unwrap();
+        let ready_file = tmp_dir.path().join("ready");
+        let next_file = tmp_dir.path().join("next");
+        let test_bin_path = testbin::path_to_test().into_string().unwrap();
+
+        // The second app does not depend on the first one, but it is still not
+        // started before the first one is ready.
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                ready_app(
+                    &["SLEEP", "1", "WRITE", ready_file.to_str().unwrap(), "x"],
+                    ReadyCheck::File(ready_file.clone()),
+                    5000,
+                ),
+                App::new(test_bin_path).with_args(vec![
+                    "WRITE".into(),
+                    next_file.to_str().unwrap().into(),
+                    "x".into(),
+                ]),
+            ],
+            vec![],
+        )
+        .verify()
+        .unwrap();
+
+        let started = std::time::SystemTime::now();
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        assert!(children
+            .iter_mut()
+            .all(|child| child.wait().unwrap().success()));
+        // The first app is ready after a second, the file times are coarser
+        // than the clock so leave some room.
+        let written = std::fs::metadata(&next_file).unwrap().modified().unwrap();
+        assert!(written >= started + Duration::from_millis(900));
+    }
+
+    #[test]
+    fn test_spawn_processes_ready_stdout() {
+        let config = Config::new(
+            None,
+            false,
+            vec![ready_app(
+                &["DEBUG", "SLEEP", "1", "SLEEP", "1"],
+                ReadyCheck::Stdout("Woken up after 1 sec$".into()),
+                5000,
+            )],
+            vec![],
+        )
+        .verify()
+        .unwrap();
+
+        let now = Instant::now();
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        let elapsed = now.elapsed();
+        assert!((Duration::from_secs(1)..Duration::from_secs(2)).contains(&elapsed));
+        assert!(children[0].wait().unwrap().success());
+    }
+
+    #[test]
+    fn test_spawn_processes_ready_tcp_and_http() {
+        use std::{
+            io::{Read, Write},
+            net::TcpListener,
+        };
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let _ = stream.read(&mut [0; 1024]);
+                let _ = stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n");
+            }
+        });
+
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                ready_app(&["SLEEP", "0"], ReadyCheck::Tcp(address.to_string()), 5000),
+                ready_app(
+                    &["SLEEP", "0"],
+                    ReadyCheck::Http(format!("http://{address}/health")),
+                    5000,
+                ),
+            ],
+            vec![],
+        )
+        .verify()
+        .unwrap();
+
+        let mut children = spawn_processes(&config, &OutputArgs::default()).unwrap();
+        assert_eq!(2, children.len());
+        assert!(children
+            .iter_mut()
+            .all(|child| child.wait().unwrap().success()));
+    }
+
+    #[test]
+    fn test_spawn_processes_ready_timeout() {
+        let tmp_dir = TempDir::new("test_spawn_processes_ready_timeout").unwrap();
+        let never_file = tmp_dir.path().join("never");
+        let dependent_file = tmp_dir.path().join("dependent");
+        let test_bin_path = testbin::path_to_test().into_string().unwrap();
+
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                ready_app(&["SLEEP", "5"], ReadyCheck::File(never_file.clone()), 300),
+                App::new(test_bin_path)
+                    .with_args(vec![
+                        "WRITE".into(),
+                        dependent_file.to_str().unwrap().into(),
+                        "x".into(),
+                    ])
+                    .with_depends_on(vec![0.into()]),
+            ],
+            vec![],
+        )
+        .verify()
+        .unwrap();
+
+        let now = Instant::now();
+        let err = spawn_processes(&config, &OutputArgs::default()).unwrap_err();
+        // The app that did not become ready is stopped again.
+        assert!(now.elapsed() < Duration::from_secs(2));
+        assert_eq!(
+            format!(
+                "The app index `0` in `start` did not become ready.: `{}` did not appear within 300 ms.",
+                never_file.display()
+            ),
+            format!("{err:#}")
+        );
+        assert!(!dependent_file.exists());
+    }
+
+    #[test]
+    fn test_spawn_processes_ready_exited_before_ready() {
+        let config = Config::new(
+            None,
+            false,
+            vec![
+                ready_app(&["SLEEP", "0"], ReadyCheck::Tcp("127.0.0.1:1".into()), 5000)
+                    .with_name("server"),
+            ],
+            vec![],
+        )
+        .verify()
+        .unwrap();
+
+        let err = spawn_processes(&config, &OutputArgs::default())
+            .unwrap_err()
+            .to_string();
+        assert!(err.starts_with("The app `server` in `start` exited with "));
+        assert!(err.ends_with(" before it became ready."));
+    }
+
     #[test]
     fn test_spawn_process_empty() {
         let cmd: Vec<String> = vec![];
@@ -348,10 +2131,276 @@ mod test_sma {
         drop(temp_dir)
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_detach_new_session() {
+        let mut cmd = Command::new(testbin::path_to_test());
+        cmd.args(["SLEEP", "1"]);
+        platform::detach(&mut cmd);
+        let mut child = cmd.spawn().unwrap();
+        let pid = child.id() as libc::pid_t;
+        let sid = unsafe { libc::getsid(pid) };
+        let pgid = unsafe { libc::getpgid(pid) };
+        assert!(child.wait().unwrap().success());
+        assert_eq!(pid, sid);
+        assert_eq!(pid, pgid);
+    }
+
+    #[test]
+    fn test_kill_remaining_children_graceful() {
+        let test_bin_path = testbin::path_to_test().into_string().unwrap();
+        let mut children = vec![spawn_process(&[test_bin_path.as_str(), "SLEEP", "5"]).unwrap()];
+        let stop = StopPolicy::new(StopSignal::Term, Duration::from_secs(3));
+
+        let now = std::time::Instant::now();
+        let report = kill_remaining_children(&mut children, &stop).unwrap();
+        let after = std::time::Instant::now();
+
+        assert!(report.force_killed.is_empty());
+        assert!(children[0].try_wait().unwrap().is_some());
+        assert!((after - now).as_millis() < 1000);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_apply_limits() {
+        let mut cmd = new_command(&["sh", "-c", "ulimit -n; ulimit -c; nice"]).unwrap();
+        platform::apply_limits(
+            &mut cmd,
+            &Limits::new()
+                .with_open_files(64)
+                .with_core_dump_size(ByteSize::new(0))
+                .with_nice(5),
+        );
+        let output = cmd.output().unwrap();
+        assert!(output.status.success());
+        assert_eq!("64\n0\n5\n", String::from_utf8(output.stdout).unwrap());
+    }
+
+    #[test]
+    fn test_max_memory_without_cgroup() {
+        if platform::Cgroup::of_apps(None).is_ok_and(|cgroup| cgroup.exists()) {
+            // The apps of this process are in a cgroup.
+            return;
+        }
+        let exe = std::env::current_exe().unwrap();
+        let config = Config::new(
+            None,
+            false,
+            vec![App::new(exe.to_str().unwrap())
+                .with_args(vec![])
+                .with_limits(Limits::new().with_max_memory(ByteSize::new(1024 * 1024)))],
+            vec![],
+        )
+        .with_cgroups(Cgroups::PerApp)
+        .verify()
+        .unwrap();
+        let cmd_vec = config.get_start()[0].get_cmd_vec().unwrap();
+        let err = spawn_app(&config, &OutputArgs::default(), 0, &cmd_vec).unwrap_err();
+        assert_eq!(
+            "The `maxMemory` of the app at index `0` can not be applied, because the app is not in a cgroup.",
+            err.to_string()
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_limits_error() {
+        let not_permitted = || anyhow::Error::from(std::io::Error::from_raw_os_error(libc::EPERM));
+        let err = platform::limits_error(&Limits::new().with_nice(-21), not_permitted());
+        assert!(err
+            .to_string()
+            .starts_with("The `nice` `-21` is lower than"));
+        assert!(err.downcast_ref::<std::io::Error>().is_some());
+
+        // The error is only explained by a limit that is above the limit of sma.
+        let err = platform::limits_error(&Limits::new().with_nice(19), not_permitted());
+        assert_eq!(not_permitted().to_string(), err.to_string());
+    }
+
+    #[test]
+    fn test_with_start_options() {
+        let options = cli::StartOptions {
+            cgroups: Some(cli::CgroupsArg::PerConfig),
+            stop_signal: Some(cli::StopSignalArg::Hup),
+            env: vec![("PORT".into(), "80".into())],
+            clean_env: true,
+            env_allow: vec!["PATH".into()],
+            var: vec![("ROOT".into(), "/srv".into())],
+            single_instance: Some(cli::SingleInstanceArg::Replace),
+            ..Default::default()
+        };
+        let config = with_start_options(Config::default(), options);
+        assert_eq!(SingleInstance::Replace, config.get_single_instance());
+        assert_eq!(Cgroups::PerConfig, config.get_cgroups());
+        assert_eq!(
+            &StopPolicy::new(StopSignal::Hup, StopPolicy::default().get_timeout()),
+            config.get_stop()
+        );
+        assert_eq!("80", config.get_env()["PORT"]);
+        assert!(config.get_clean_env());
+        assert_eq!(["PATH"], config.get_env_allowlist());
+        assert_eq!("/srv", config.get_vars()["ROOT"]);
+    }
+
+    #[test]
+    fn test_with_app_options() {
+        let options = cli::AppOptions {
+            name: vec![(0, "api".into()), (1, "old".into()), (1, "web".into())],
+            depends_on: vec![(1, "api".into())],
+            ready: vec![(0, cli::ReadyCheckArg::Tcp("localhost:8080".into()))],
+            restart: vec![(1, cli::RestartArg::Always)],
+            max_retries: vec![(1, 3)],
+            open_files: vec![(0, 64)],
+            app_env: vec![(0, ("PORT".into(), "8080".into()))],
+            app_log_path: vec![(1, "logs/web.{stream}.log".into())],
+            ..Default::default()
+        };
+        let apps = with_app_options(vec!["api".into(), "web".into()], &options).unwrap();
+        assert_eq!(
+            App::new("api")
+                .with_name("api")
+                .with_ready(ReadyProbe::from(ReadyCheck::Tcp("localhost:8080".into())))
+                .with_limits(Limits::new().with_open_files(64))
+                .with_env("PORT", "8080"),
+            apps[0]
+        );
+        assert_eq!(
+            App::new("web")
+                .with_name("web")
+                .with_depends_on(vec![AppRef::Name("api".into())])
+                .with_restart(RestartPolicy::new(Restart::Always).with_max_retries(3))
+                .with_logs(LogConfig::new("logs/web.{stream}.log")),
+            apps[1]
+        );
+
+        for (options, err) in [
+            (
+                cli::AppOptions {
+                    name: vec![(2, "db".into())],
+                    ..Default::default()
+                },
+                "The index `2` of `--name` is not an index in `start`.",
+            ),
+            (
+                cli::AppOptions {
+                    ready_timeout: vec![(0, 100)],
+                    ..Default::default()
+                },
+                "The app at index `0` has a `--ready-timeout` but no `--ready`.",
+            ),
+        ] {
+            assert_eq!(
+                err,
+                with_app_options(vec!["api".into(), "web".into()], &options)
+                    .unwrap_err()
+                    .to_string()
+            );
+        }
+    }
+
+    #[test]
+    fn test_format_diagnostics() {
+        let temp_dir = TempDir::new("test_format_diagnostics").unwrap();
+        let file_path = temp_dir.path().join("config.json");
+        std::fs::write(&file_path, "{\n  \"version\": 3,\n  \"start\": [,]\n}").unwrap();
+        let diagnostics = Config::validate_file(&file_path);
+
+        assert_eq!(
+            format!("{}:3:13: expected value", file_path.display()),
+            format_diagnostics(&file_path, &diagnostics, DiagnosticsFormat::Human).unwrap()
+        );
+        let json: serde_json::Value = serde_json::from_str(
+            &format_diagnostics(&file_path, &diagnostics, DiagnosticsFormat::Json).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            serde_json::json!([{ "message": "expected value", "line": 3, "column": 13 }]),
+            json
+        );
+        assert_eq!(
+            format!("The config `{}` is valid.", file_path.display()),
+            format_diagnostics(&file_path, &[], DiagnosticsFormat::Human).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_contained_in() {
+        let config = Config::default().verify().unwrap();
+        assert_eq!(None, contained_in(&config, None));
+        assert!(spawn_cgroup(&config, 0).unwrap().is_none());
+
+        // The apps share the cgroup of the config, they have none of their
+        // own.
+        let config = Config::default()
+            .with_cgroups(Cgroups::PerConfig)
+            .verify()
+            .unwrap();
+        assert_eq!(None, contained_in(&config, Some(0)));
+    }
+
+    #[test]
+    fn test_kill_remaining_children_cascade_graceful() {
+        let test_bin_path = testbin::path_to_test().into_string().unwrap();
+        let mut children = vec![spawn_process(&[
+            test_bin_path.as_str(),
+            "SPAWN",
+            "SLEEP",
+            "5",
+            "stop",
+            "SLEEP",
+            "5",
+        ])
+        .unwrap()];
+        std::thread::sleep(Duration::from_millis(200));
+        let stop = StopPolicy::new(StopSignal::Term, Duration::from_secs(3));
+        let mut descendants = Descendants::new();
+        descendants.refresh(&children, true);
+        let child_descendants = descendants.get_pids_of(children[0].id());
+        assert_eq!(1, child_descendants.len());
+
+        let report =
+            kill_remaining_children_cascade(&mut children, &child_descendants, None, &stop)
+                .unwrap();
+
+        assert!(report.force_killed.is_empty());
+        assert!(children[0].try_wait().unwrap().is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_remaining_children_force_killed() {
+        let mut children =
+            vec![
+                spawn_process(&["sh", "-c", "trap '' TERM; while true; do sleep 0.1; done"])
+                    .unwrap(),
+            ];
+        std::thread::sleep(Duration::from_millis(200));
+        let stop = StopPolicy::new(StopSignal::Term, Duration::from_millis(300));
+
+        let report = kill_remaining_children(&mut children, &stop).unwrap();
+
+        assert_eq!(1, report.force_killed.len());
+        assert_eq!(children[0].id(), report.force_killed[0].0);
+        assert!(children[0].try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_kill_remaining_children_sigkill_is_not_reported() {
+        let test_bin_path = testbin::path_to_test().into_string().unwrap();
+        let mut children = vec![spawn_process(&[test_bin_path.as_str(), "SLEEP", "5"]).unwrap()];
+        let stop = StopPolicy::new(StopSignal::Kill, Duration::from_secs(3));
+
+        let report = kill_remaining_children(&mut children, &stop).unwrap();
+
+        assert!(report.force_killed.is_empty());
+        assert!(children[0].try_wait().unwrap().is_some());
+    }
+
     #[test]
     fn test_change_cwd_config_path_none_cwd_none() {
         let cwd = std::env::current_dir().unwrap();
-        let config = Config::new(None, false, vec![], None).verify().unwrap();
+        let config = Config::new(None, false, vec![], vec![]).verify().unwrap();
         change_cwd(&config).unwrap();
         assert_eq!(cwd, std::env::current_dir().unwrap());
     }
@@ -365,7 +2414,7 @@ mod test_sma {
             Some(config_path.parent().unwrap().to_path_buf()),
             false,
             vec![],
-            None,
+            vec![],
         )
         .verify()
         .unwrap();
@@ -410,7 +2459,7 @@ mod test_sma {
         let dir_2 = TempDir::new("test_change_cwd_config_path_some_cwd_none_2").unwrap();
         let config_path = dir_1.path().join("config.json");
         let new_cwd = dir_2.path();
-        Config::new(Some(new_cwd.to_path_buf()), false, vec![], None)
+        Config::new(Some(new_cwd.to_path_buf()), false, vec![], vec![])
             .verify()
             .unwrap()
             .create_file(&config_path, false)
@@ -435,7 +2484,7 @@ mod test_sma {
     fn test_change_cwd_to_none_existing() {
         let cwd = std::env::current_dir().unwrap();
         let temp_dir = TempDir::new("test_change_cwd_to_none_existing").unwrap();
-        let config = Config::new(Some(temp_dir.path().to_path_buf()), false, vec![], None)
+        let config = Config::new(Some(temp_dir.path().to_path_buf()), false, vec![], vec![])
             .verify()
             .unwrap();
 
@@ -465,7 +2514,7 @@ mod test_sma {
         let cwd = std::env::current_dir().unwrap();
         let temp_dir = TempDir::new("test_change_cwd_to_none_existing").unwrap();
         let file_name = "config.json";
-        Config::new(None, false, vec![], None)
+        Config::new(None, false, vec![], vec![])
             .verify()
             .unwrap()
             .create_file(temp_dir.path().join(file_name), false)
@@ -0,0 +1,117 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use config::ByteSize;
+
+/// A log file that is rotated when it would grow bigger than its max size.
+///
+/// The rotated files are named after the log file with a number added, where
+/// `app.log.1` is the newest one.
+pub(crate) struct LogFile {
+    path: PathBuf,
+    file: File,
+    // This is the size of `file` in bytes.
+    size: u64,
+    max_size: u64,
+    // This is how many rotated files are kept.
+    keep: u32,
+}
+
+impl LogFile {
+    /// Opens the log file at `path` and appends to it, the directories of
+    /// `path` are created if they do not exist.
+    pub(crate) fn open(path: PathBuf, max_size: ByteSize, keep: u32) -> io::Result<LogFile> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(LogFile {
+            path,
+            file,
+            size,
+            max_size: max_size.get_bytes(),
+            keep,
+        })
+    }
+
+    /// Writes `buf` to the log file, the file is rotated first if `buf` does
+    /// not fit in it.
+    pub(crate) fn write(&mut self, buf: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(buf)?;
+        self.size += buf.len() as u64;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.keep > 0 {
+            let oldest = self.rotated_path(self.keep);
+            if oldest.exists() {
+                fs::remove_file(oldest)?;
+            }
+            for number in (1..self.keep).rev() {
+                let rotated = self.rotated_path(number);
+                if rotated.exists() {
+                    fs::rename(rotated, self.rotated_path(number + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = File::create(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn rotated_path(&self, number: u32) -> PathBuf {
+        let mut path = OsString::from(self.path.as_os_str());
+        path.push(format!(".{number}"));
+        path.into()
+    }
+}
+
+#[cfg(test)]
+mod tests_log_file {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_log_file_rotates() {
+        let temp_dir = TempDir::new("test_log_file_rotates").unwrap();
+        let path = temp_dir.path().join("logs").join("app.log");
+        let mut log_file = LogFile::open(path.clone(), ByteSize::new(10), 2).unwrap();
+
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            log_file.write(line.as_bytes()).unwrap();
+        }
+
+        let read = |name: &str| fs::read_to_string(temp_dir.path().join("logs").join(name));
+        assert_eq!("fourth\n", read("app.log").unwrap());
+        assert_eq!("third\n", read("app.log.1").unwrap());
+        assert_eq!("second\n", read("app.log.2").unwrap());
+        // Only `keep` rotated files are kept.
+        assert!(read("app.log.3").is_err());
+    }
+
+    #[test]
+    fn test_log_file_appends_and_keeps_nothing() {
+        let temp_dir = TempDir::new("test_log_file_appends").unwrap();
+        let path = temp_dir.path().join("app.log");
+        fs::write(&path, "old\n").unwrap();
+
+        let mut log_file = LogFile::open(path.clone(), ByteSize::new(8), 0).unwrap();
+        log_file.write(b"new\n").unwrap();
+        assert_eq!("old\nnew\n", fs::read_to_string(&path).unwrap());
+
+        log_file.write(b"newer\n").unwrap();
+        assert_eq!("newer\n", fs::read_to_string(&path).unwrap());
+        assert!(!temp_dir.path().join("app.log.1").exists());
+    }
+}
@@ -1,10 +1,14 @@
+#[cfg(windows)]
 use std::{os::windows::process::CommandExt, process::Command};
+#[cfg(windows)]
 use sysinfo::{Pid, ProcessRefreshKind, RefreshKind, System};
+#[cfg(windows)]
 use winapi::um::{
     wincon::GetConsoleWindow,
     winuser::{ShowWindow, SW_HIDE},
 };
 
+#[cfg(windows)]
 fn hide_console_window() {
     // TODO: swap this out to the windows crate instead of winapi
     let window = unsafe { GetConsoleWindow() };
@@ -16,6 +20,7 @@ fn hide_console_window() {
     }
 }
 
+#[cfg(windows)]
 fn is_started_by_double_click() -> bool {
     let me = Pid::from_u32(std::process::id());
     let sys =
@@ -29,6 +34,7 @@ fn is_started_by_double_click() -> bool {
     parent_process_name == "explorer.exe"
 }
 
+#[cfg(windows)]
 fn start_gui_if_exist() -> anyhow::Result<bool> {
     let gui_path = std::env::current_exe()?
         .parent()
@@ -50,6 +56,7 @@ fn start_gui_if_exist() -> anyhow::Result<bool> {
     }
 }
 
+#[cfg(windows)]
 fn main() -> anyhow::Result<()> {
     if !is_started_by_double_click() {
         hide_console_window();
@@ -61,3 +68,8 @@ fn main() -> anyhow::Result<()> {
     }
     Ok(())
 }
+
+#[cfg(not(windows))]
+fn main() -> anyhow::Result<()> {
+    sma::run()
+}
@@ -0,0 +1,179 @@
+use std::{
+    io::{BufRead, BufReader, IsTerminal, Read, Write},
+    sync::{mpsc::Sender, Arc, Mutex},
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use regex::Regex;
+
+use crate::log_file::LogFile;
+
+// These are the ANSI colors the prefixes of the apps cycle through.
+const PREFIX_COLORS: [u8; 6] = [36, 33, 32, 35, 34, 31];
+
+/// Where the lines of a captured output stream end up.
+#[derive(Clone)]
+pub(crate) enum OutputTarget {
+    Stdout,
+    Stderr,
+    // Every line is written after `prefix` to the stdout of sma, or to its
+    // stderr if `stderr` is true.
+    Prefixed {
+        prefix: String,
+        stderr: bool,
+        timestamps: bool,
+    },
+    // The file can be shared by the stdout and stderr of an app.
+    File(Arc<Mutex<LogFile>>),
+}
+
+// These are the threads that forward output, so sma can let them finish
+// before it exits.
+static FORWARDERS: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+
+/// Reads `reader` line by line on a new thread and writes every line to all
+/// `targets`. When `ready_pattern` is given, its sender is told about the
+/// first line that matches its regex.
+pub(crate) fn forward_lines<R: Read + Send + 'static>(
+    reader: R,
+    targets: Vec<OutputTarget>,
+    ready_pattern: Option<(Regex, Sender<()>)>,
+) {
+    let handle = std::thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut ready_pattern = ready_pattern;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => (),
+            }
+
+            if let Some((pattern, matched)) = &ready_pattern {
+                let text = String::from_utf8_lossy(&line);
+                if pattern.is_match(text.trim_end_matches(['\r', '\n'])) {
+                    let _ = matched.send(());
+                    ready_pattern = None;
+                }
+            }
+
+            // Failing to forward a line is not a reason to stop reading, the
+            // app would block when its pipe is full.
+            for target in &targets {
+                let _ = match target {
+                    OutputTarget::Stdout => std::io::stdout().lock().write_all(&line),
+                    OutputTarget::Stderr => std::io::stderr().lock().write_all(&line),
+                    OutputTarget::Prefixed {
+                        prefix,
+                        stderr,
+                        timestamps,
+                    } => {
+                        let line = prefixed_line(prefix, *timestamps, &line);
+                        if *stderr {
+                            std::io::stderr().lock().write_all(&line)
+                        } else {
+                            std::io::stdout().lock().write_all(&line)
+                        }
+                    }
+                    OutputTarget::File(file) => match file.lock() {
+                        Ok(mut file) => file.write(&line),
+                        Err(_) => Ok(()),
+                    },
+                };
+            }
+        }
+    });
+
+    if let Ok(mut forwarders) = FORWARDERS.lock() {
+        forwarders.retain(|forwarder| !forwarder.is_finished());
+        forwarders.push(handle);
+    }
+}
+
+/// Waits at most `timeout` for the forwarded output to reach its targets.
+///
+/// A forwarder only finishes when every process that holds the stream has
+/// closed it, which a process that is not stopped by sma might never do.
+pub(crate) fn wait_for_forwarders(timeout: Duration) {
+    let deadline = Instant::now() + timeout;
+    let Ok(mut forwarders) = FORWARDERS.lock() else {
+        return;
+    };
+    while Instant::now() < deadline {
+        forwarders.retain(|forwarder| !forwarder.is_finished());
+        if forwarders.is_empty() {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    }
+}
+
+/// Returns the prefix of the lines of an app, `name` is padded to `width` so
+/// the output of all apps lines up. Lines from stderr use `!` instead of `|`.
+pub(crate) fn line_prefix(name: &str, index: usize, width: usize, stderr: bool) -> String {
+    let separator = if stderr { '!' } else { '|' };
+    let prefix = format!("{name:<width$} {separator} ");
+    if std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal() {
+        let color = PREFIX_COLORS[index % PREFIX_COLORS.len()];
+        format!("\x1b[{color}m{prefix}\x1b[0m")
+    } else {
+        prefix
+    }
+}
+
+/// Returns `line` after the prefix, and the current time if `timestamps` is
+/// true. The returned line always ends with a newline.
+fn prefixed_line(prefix: &str, timestamps: bool, line: &[u8]) -> Vec<u8> {
+    let mut prefixed = Vec::with_capacity(prefix.len() + line.len() + 14);
+    if timestamps {
+        prefixed.extend_from_slice(timestamp(SystemTime::now()).as_bytes());
+    }
+    prefixed.extend_from_slice(prefix.as_bytes());
+    prefixed.extend_from_slice(line);
+    if !line.ends_with(b"\n") {
+        prefixed.push(b'\n');
+    }
+    prefixed
+}
+
+/// Returns the time of day of `time` in UTC, e.g. `13:37:00.042 `.
+fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs() % (24 * 60 * 60);
+    format!(
+        "{:02}:{:02}:{:02}.{:03} ",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests_output {
+    use super::*;
+
+    #[test]
+    fn test_prefixed_line() {
+        assert_eq!(
+            b"api   | listening\n".to_vec(),
+            prefixed_line("api   | ", false, b"listening\n")
+        );
+        // The last line of an app might not end with a newline.
+        assert_eq!(
+            b"api   ! failed\n".to_vec(),
+            prefixed_line("api   ! ", false, b"failed")
+        );
+
+        let line = String::from_utf8(prefixed_line("0 | ", true, b"x\n")).unwrap();
+        assert!(line.ends_with(" 0 | x\n"), "{line}");
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let time = UNIX_EPOCH + Duration::from_millis(((13 * 60 + 37) * 60 + 5) * 1000 + 42);
+        assert_eq!("13:37:05.042 ", timestamp(time));
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    ffi::CString,
+    fs, io,
+    os::unix::{ffi::OsStrExt, process::CommandExt},
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, Instant},
+};
+
+use config::StopSignal;
+
+// How long we wait for the processes in a killed cgroup to exit.
+const KILL_TIMEOUT: Duration = Duration::from_secs(5);
+// How often we check if a killed cgroup is empty.
+const KILL_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// A cgroup v2 that apps are put in, every process an app starts ends up in
+/// the same cgroup and can not leave it without the rights sma has.
+///
+/// The cgroups are created inside the cgroup of sma, which therefore has to
+/// be delegated to the user running sma.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Cgroup {
+    path: PathBuf,
+}
+
+impl Cgroup {
+    /// Returns the cgroup the apps of this sma are put in, or the cgroup
+    /// inside it of the app at `index` in `start`.
+    pub(crate) fn of_apps(index: Option<usize>) -> io::Result<Cgroup> {
+        let mut path = own_cgroup_path()?.join(format!("sma-{}", std::process::id()));
+        if let Some(index) = index {
+            path.push(format!("app-{index}"));
+        }
+        Ok(Cgroup { path })
+    }
+
+    pub(crate) fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn exists(&self) -> bool {
+        self.path.is_dir()
+    }
+
+    /// Creates the cgroup if it does not exist yet.
+    pub(crate) fn create(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.path)
+    }
+
+    /// Makes the process spawned by `cmd` move itself into the cgroup before
+    /// the program is started, so none of its descendants start outside it.
+    pub(crate) fn add_on_spawn(&self, cmd: &mut Command) -> io::Result<()> {
+        let procs = CString::new(self.path.join("cgroup.procs").as_os_str().as_bytes())?;
+
+        // SAFETY: `open`, `write` and `close` are async-signal-safe, and the
+        // path was allocated before the fork.
+        unsafe {
+            cmd.pre_exec(move || {
+                let fd = libc::open(procs.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+                if fd == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                // Writing 0 moves the process that writes it.
+                let written = libc::write(fd, b"0".as_ptr().cast(), 1);
+                let err = io::Error::last_os_error();
+                libc::close(fd);
+                if written == -1 {
+                    return Err(err);
+                }
+                Ok(())
+            });
+        }
+        Ok(())
+    }
+
+    /// Limits the memory the processes in the cgroup can use together to
+    /// `bytes`.
+    ///
+    /// The memory controller is enabled for the cgroup first, which needs the
+    /// controller to be available in the cgroup of sma.
+    pub(crate) fn limit_memory(&self, bytes: u64) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            let subtree_control = parent.join("cgroup.subtree_control");
+            let enabled = fs::read_to_string(&subtree_control)?;
+            if !enabled
+                .split_whitespace()
+                .any(|controller| controller == "memory")
+            {
+                fs::write(&subtree_control, "+memory")?;
+            }
+        }
+        fs::write(self.path.join("memory.max"), bytes.to_string())
+    }
+
+    /// Returns the pids of the processes in the cgroup and in the cgroups
+    /// inside it.
+    pub(crate) fn get_pids(&self) -> io::Result<Vec<u32>> {
+        let mut pids: Vec<u32> = fs::read_to_string(self.path.join("cgroup.procs"))?
+            .lines()
+            .filter_map(|pid| pid.trim().parse().ok())
+            .collect();
+        for cgroup in self.get_children()? {
+            pids.extend(cgroup.get_pids()?);
+        }
+        Ok(pids)
+    }
+
+    /// Force kills every process in the cgroup and in the cgroups inside it,
+    /// and waits for them to exit.
+    pub(crate) fn kill(&self) -> io::Result<()> {
+        // `cgroup.kill` was added in Linux 5.14, before that the processes
+        // are killed one by one until none are left.
+        let kill_file = match fs::write(self.path.join("cgroup.kill"), "1") {
+            Ok(()) => true,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => false,
+            Err(e) => return Err(e),
+        };
+
+        let deadline = Instant::now() + KILL_TIMEOUT;
+        while self.is_populated()? {
+            if Instant::now() >= deadline {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    format!(
+                        "the processes in the cgroup did not exit within {} ms",
+                        KILL_TIMEOUT.as_millis()
+                    ),
+                ));
+            }
+            if !kill_file {
+                for pid in self.get_pids()? {
+                    super::send_signal(pid, StopSignal::Kill)?;
+                }
+            }
+            std::thread::sleep(KILL_POLL_INTERVAL);
+        }
+        Ok(())
+    }
+
+    /// Removes the cgroup and the cgroups inside it, this only works once no
+    /// processes are left in them.
+    pub(crate) fn remove(&self) -> io::Result<()> {
+        for cgroup in self.get_children()? {
+            cgroup.remove()?;
+        }
+        fs::remove_dir(&self.path)
+    }
+
+    /// Returns true if there are processes in the cgroup or in the cgroups
+    /// inside it.
+    fn is_populated(&self) -> io::Result<bool> {
+        Ok(fs::read_to_string(self.path.join("cgroup.events"))?
+            .lines()
+            .any(|line| line == "populated 1"))
+    }
+
+    fn get_children(&self) -> io::Result<Vec<Cgroup>> {
+        let mut children = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                children.push(Cgroup { path: entry.path() });
+            }
+        }
+        Ok(children)
+    }
+}
+
+/// Returns the path of the cgroup v2 sma is in.
+fn own_cgroup_path() -> io::Result<PathBuf> {
+    let unavailable = |reason: &str| io::Error::new(io::ErrorKind::Unsupported, reason.to_string());
+
+    // The cgroup v2 hierarchy is the line with the id 0, like `0::/user.slice`.
+    let cgroups = fs::read_to_string("/proc/self/cgroup")?;
+    let cgroup = cgroups
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .ok_or_else(|| unavailable("sma is not in a cgroup v2"))?;
+
+    // A line of the mountinfo looks like
+    // `42 32 0:38 <root> <mount point> <options> - cgroup2 <source> <options>`.
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo")?;
+    let (root, mount_point) = mountinfo
+        .lines()
+        .find_map(|line| {
+            let (fields, fs_fields) = line.split_once(" - ")?;
+            if fs_fields.split(' ').next() != Some("cgroup2") {
+                return None;
+            }
+            let mut fields = fields.split(' ').skip(3);
+            Some((fields.next()?, fields.next()?))
+        })
+        .ok_or_else(|| unavailable("no cgroup v2 hierarchy is mounted"))?;
+
+    let relative = Path::new(cgroup)
+        .strip_prefix(root)
+        .map_err(|_| unavailable("the cgroup of sma is not inside the mounted cgroup v2"))?;
+    Ok(Path::new(mount_point).join(relative))
+}
+
+#[cfg(test)]
+mod tests_cgroup {
+    use super::*;
+
+    #[test]
+    fn test_of_apps() {
+        let Result::Ok(cgroup) = Cgroup::of_apps(Some(2)) else {
+            // There is no cgroup v2 to put the apps in.
+            return;
+        };
+        let config_cgroup = Cgroup::of_apps(None).unwrap();
+        assert_eq!(Some(config_cgroup.get_path()), cgroup.get_path().parent());
+        assert_eq!(
+            Some(format!("sma-{}", std::process::id()).as_ref()),
+            config_cgroup.get_path().file_name()
+        );
+    }
+
+    #[test]
+    fn test_spawn_and_kill() {
+        let Some(cgroup) = Cgroup::of_apps(Some(0))
+            .ok()
+            .filter(|cgroup| cgroup.create().is_ok())
+        else {
+            // The cgroup of sma is not delegated to us.
+            return;
+        };
+
+        // The sleep forks off, but it can not leave the cgroup.
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sleep 36 & wait"]);
+        cgroup.add_on_spawn(&mut cmd).unwrap();
+        let mut child = cmd.spawn().unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+        let pids = cgroup.get_pids().unwrap();
+        assert_eq!(2, pids.len());
+        assert!(pids.contains(&child.id()));
+
+        let config_cgroup = Cgroup::of_apps(None).unwrap();
+        assert_eq!(pids.len(), config_cgroup.get_pids().unwrap().len());
+        config_cgroup.kill().unwrap();
+        assert!(child.try_wait().unwrap().is_some());
+        assert!(config_cgroup.get_pids().unwrap().is_empty());
+
+        config_cgroup.remove().unwrap();
+        assert!(!config_cgroup.exists());
+    }
+}
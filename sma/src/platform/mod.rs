@@ -0,0 +1,24 @@
+//! Everything that differs between the operating systems sma can run on.
+//!
+//! Each backend exposes the same set of functions so the rest of the crate
+//! never has to reach for `cfg` attributes itself.
+
+#[cfg(unix)]
+mod unix;
+#[cfg(unix)]
+pub(crate) use unix::*;
+
+#[cfg(windows)]
+mod windows;
+#[cfg(windows)]
+pub(crate) use windows::*;
+
+#[cfg(target_os = "linux")]
+mod cgroup;
+#[cfg(target_os = "linux")]
+pub(crate) use cgroup::Cgroup;
+
+#[cfg(not(target_os = "linux"))]
+mod no_cgroup;
+#[cfg(not(target_os = "linux"))]
+pub(crate) use no_cgroup::Cgroup;
@@ -0,0 +1,61 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// A cgroup v2 that apps are put in.
+///
+/// Only Linux has cgroups, on the other systems there is never a cgroup and
+/// the descendants of the apps are found by their parent pids instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Cgroup {
+    path: PathBuf,
+}
+
+impl Cgroup {
+    /// Returns the cgroup the apps of this sma are put in, which fails as
+    /// there are no cgroups.
+    pub(crate) fn of_apps(_index: Option<usize>) -> io::Result<Cgroup> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn get_path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn exists(&self) -> bool {
+        false
+    }
+
+    pub(crate) fn create(&self) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn add_on_spawn(&self, _cmd: &mut Command) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn limit_memory(&self, _bytes: u64) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn get_pids(&self) -> io::Result<Vec<u32>> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn kill(&self) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(crate) fn remove(&self) -> io::Result<()> {
+        Err(unsupported())
+    }
+}
+
+fn unsupported() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        "cgroups are only supported on Linux",
+    )
+}
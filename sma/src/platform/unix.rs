@@ -0,0 +1,186 @@
+use config::{Limits, StopSignal};
+
+use std::{
+    io,
+    os::unix::process::CommandExt,
+    process::{Command, Stdio},
+};
+
+/// Makes the spawned process independent of sma.
+///
+/// The child is put in a new session (and therefore its own process group)
+/// with `setsid`, so signals sent to the terminal or to sma's process group
+/// do not reach it. Its std(in/out/err) are detached the same way a
+/// `DETACHED_PROCESS` has no console on Windows.
+pub(crate) fn detach(cmd: &mut Command) {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+
+    // SAFETY: `setsid` is async-signal-safe and we do not touch any memory
+    // owned by the parent in the closure.
+    unsafe {
+        cmd.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Makes the process spawned by `cmd` apply `limits` to itself before the
+/// program is started, everything the program starts inherits them.
+///
+/// The `maxMemory` is not applied here, it is the limit of the cgroup of the
+/// app (see [`super::Cgroup::limit_memory`]).
+pub(crate) fn apply_limits(cmd: &mut Command, limits: &Limits) {
+    let rlimits: Vec<(_, libc::rlim_t)> = [
+        (libc::RLIMIT_NOFILE, limits.get_open_files()),
+        (
+            libc::RLIMIT_CORE,
+            limits.get_core_dump_size().map(|size| size.get_bytes()),
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(resource, limit)| limit.map(|limit| (resource, limit as libc::rlim_t)))
+    .collect();
+    let nice = limits.get_nice();
+    if rlimits.is_empty() && nice.is_none() {
+        return;
+    }
+
+    // SAFETY: `setrlimit` and `setpriority` are async-signal-safe, and the
+    // limits were collected before the fork.
+    unsafe {
+        cmd.pre_exec(move || {
+            for &(resource, limit) in &rlimits {
+                let rlimit = libc::rlimit {
+                    rlim_cur: limit,
+                    rlim_max: limit,
+                };
+                if libc::setrlimit(resource, &rlimit) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            if let Some(nice) = nice {
+                if libc::setpriority(libc::PRIO_PROCESS as _, 0, nice) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Adds the limit in `limits` that could not be applied to `err`, the error
+/// of spawning an app with them.
+///
+/// A process may only lower its own limits and priority, so the limit that
+/// is above the limit of sma is the one that failed.
+pub(crate) fn limits_error(limits: &Limits, err: anyhow::Error) -> anyhow::Error {
+    let not_permitted = err
+        .downcast_ref::<io::Error>()
+        .is_some_and(|e| e.raw_os_error() == Some(libc::EPERM));
+    if !not_permitted {
+        return err;
+    }
+
+    let hard_limit = |resource| {
+        let mut rlimit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        // SAFETY: `getrlimit` only writes to the given `rlimit`.
+        (unsafe { libc::getrlimit(resource, &mut rlimit) } == 0).then_some(rlimit.rlim_max)
+    };
+    if let Some(nice) = limits.get_nice() {
+        // SAFETY: `getpriority` has no memory safety requirements.
+        let own_nice = unsafe { libc::getpriority(libc::PRIO_PROCESS as _, 0) };
+        if nice < own_nice {
+            return err.context(format!("The `nice` `{nice}` is lower than the niceness `{own_nice}` of sma, only a privileged user can raise the priority of an app."));
+        }
+    }
+    for (name, limit, resource) in [
+        ("openFiles", limits.get_open_files(), libc::RLIMIT_NOFILE),
+        (
+            "coreDumpSize",
+            limits.get_core_dump_size().map(|size| size.get_bytes()),
+            libc::RLIMIT_CORE,
+        ),
+    ] {
+        if let (Some(limit), Some(hard_limit)) = (limit, hard_limit(resource)) {
+            if limit as libc::rlim_t > hard_limit {
+                return err.context(format!("The `{name}` `{limit}` is more than the hard limit `{hard_limit}` of sma, only a privileged user can raise it."));
+            }
+        }
+    }
+    err
+}
+
+/// Sends `signal` to the process with `pid`.
+///
+/// Returns `false` if the signal could not be delivered because the process
+/// does not exist anymore.
+pub(crate) fn send_signal(pid: u32, signal: StopSignal) -> io::Result<bool> {
+    let signal = match signal {
+        StopSignal::Term => libc::SIGTERM,
+        StopSignal::Int => libc::SIGINT,
+        StopSignal::Hup => libc::SIGHUP,
+        StopSignal::Quit => libc::SIGQUIT,
+        StopSignal::Kill => libc::SIGKILL,
+    };
+    // SAFETY: `kill` has no memory safety requirements.
+    if unsafe { libc::kill(pid as libc::pid_t, signal) } == -1 {
+        let err = io::Error::last_os_error();
+        if err.raw_os_error() == Some(libc::ESRCH) {
+            return Ok(false);
+        }
+        return Err(err);
+    }
+    Ok(true)
+}
+
+/// Makes sma the child subreaper of all its descendants, so a descendant
+/// whose parent exits is reparented to sma instead of to init.
+///
+/// This is only supported on Linux, on the other systems nothing happens.
+pub(crate) fn become_subreaper() -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        // SAFETY: `prctl` with `PR_SET_CHILD_SUBREAPER` only takes integers.
+        if unsafe { libc::prctl(libc::PR_SET_CHILD_SUBREAPER, 1, 0, 0, 0) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if sma is the child subreaper of its descendants.
+pub(crate) fn is_subreaper() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        let mut is_subreaper: libc::c_int = 0;
+        // SAFETY: `PR_GET_CHILD_SUBREAPER` writes an int to the pointer.
+        let result = unsafe {
+            libc::prctl(
+                libc::PR_GET_CHILD_SUBREAPER,
+                &mut is_subreaper as *mut libc::c_int,
+                0,
+                0,
+                0,
+            )
+        };
+        result == 0 && is_subreaper != 0
+    }
+    #[cfg(not(target_os = "linux"))]
+    false
+}
+
+/// Reaps the process with `pid` if it is a child of sma that has exited.
+pub(crate) fn reap(pid: u32) {
+    // SAFETY: `waitpid` may be given a null pointer for the status.
+    unsafe {
+        libc::waitpid(pid as libc::pid_t, std::ptr::null_mut(), libc::WNOHANG);
+    }
+}
@@ -0,0 +1,55 @@
+use config::{Limits, StopSignal};
+
+use std::{io, os::windows::process::CommandExt, process::Command};
+
+/// Makes the spawned process independent of sma.
+///
+/// The child gets no console and is put in a new process group, so it is not
+/// affected by anything that happens to sma's console.
+pub(crate) fn detach(cmd: &mut Command) {
+    const DETACHED_PROCESS: u32 = 0x00000008;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x00000200;
+    cmd.creation_flags(
+        // TODO: swap this out to the windows crate instead of winapi
+        // winapi::um::winbase::DETACHED_PROCESS | winapi::um::winbase::CREATE_NEW_PROCESS_GROUP,
+        DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP,
+    );
+}
+
+/// Makes the spawned process apply `limits` to itself.
+///
+/// Windows has no rlimits or niceness, so the limits are ignored.
+pub(crate) fn apply_limits(_cmd: &mut Command, _limits: &Limits) {}
+
+/// Adds the limit in `limits` that could not be applied to `err`.
+///
+/// The limits are ignored on Windows, so `err` is returned as it is.
+pub(crate) fn limits_error(_limits: &Limits, err: anyhow::Error) -> anyhow::Error {
+    err
+}
+
+/// Sends `signal` to the process with `pid`.
+///
+/// Windows has no signals that can be sent to a detached process, so this
+/// never delivers anything and the process will be force killed instead.
+pub(crate) fn send_signal(_pid: u32, _signal: StopSignal) -> io::Result<bool> {
+    Ok(false)
+}
+
+/// Makes sma the child subreaper of all its descendants.
+///
+/// Windows does not reparent processes, so there is nothing to do.
+pub(crate) fn become_subreaper() -> io::Result<()> {
+    Ok(())
+}
+
+/// Returns true if sma is the child subreaper of its descendants, which it
+/// never is on Windows.
+pub(crate) fn is_subreaper() -> bool {
+    false
+}
+
+/// Reaps the process with `pid` if it is a child of sma that has exited.
+///
+/// Windows does not keep exited processes around, so there is nothing to do.
+pub(crate) fn reap(_pid: u32) {}
@@ -0,0 +1,127 @@
+use config::{HttpTarget, ReadyCheck, ReadyProbe};
+
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::Path,
+    sync::mpsc::{Receiver, RecvTimeoutError},
+    time::{Duration, Instant},
+};
+
+use anyhow::bail;
+
+// How often a probe checks if the app has become ready.
+const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+// How long the probe of an app that exited may still report it ready, the app
+// can have become ready right before it exited.
+pub(crate) const EXIT_GRACE: Duration = Duration::from_millis(250);
+// How long a single connection attempt of a probe may take.
+const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Blocks until `probe` reports that the app is ready, or fails when the
+/// timeout of the probe is reached.
+///
+/// `cwd` is the current working directory of the app, and `line_matched`
+/// receives a message when the output of the app matches the regex of a
+/// [`ReadyCheck::Stdout`] or [`ReadyCheck::Stderr`] probe.
+pub(crate) fn wait_until_ready(
+    probe: &ReadyProbe,
+    cwd: &Path,
+    line_matched: Option<Receiver<()>>,
+) -> anyhow::Result<()> {
+    let timeout = probe.get_timeout();
+
+    if let ReadyCheck::Stdout(pattern) | ReadyCheck::Stderr(pattern) = probe.get_check() {
+        let Some(line_matched) = line_matched else {
+            bail!("The output of the app is not captured, so `{pattern}` can never match.")
+        };
+        return match line_matched.recv_timeout(timeout) {
+            Ok(()) => Ok(()),
+            Err(RecvTimeoutError::Timeout) => {
+                bail!(
+                    "No line matched `{pattern}` within {} ms.",
+                    timeout.as_millis()
+                )
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                bail!("The output of the app ended before a line matched `{pattern}`.")
+            }
+        };
+    }
+
+    let deadline = Instant::now() + timeout;
+    loop {
+        if is_ready(probe.get_check(), cwd) {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            break;
+        }
+        std::thread::sleep(PROBE_INTERVAL);
+    }
+
+    match probe.get_check() {
+        ReadyCheck::Tcp(address) => bail!(
+            "`{address}` did not accept connections within {} ms.",
+            timeout.as_millis()
+        ),
+        ReadyCheck::File(path) => bail!(
+            "`{}` did not appear within {} ms.",
+            cwd.join(path).display(),
+            timeout.as_millis()
+        ),
+        ReadyCheck::Http(url) => bail!(
+            "`{url}` did not respond with a 2xx status code within {} ms.",
+            timeout.as_millis()
+        ),
+        ReadyCheck::Stdout(_) | ReadyCheck::Stderr(_) => unreachable!(),
+    }
+}
+
+fn is_ready(check: &ReadyCheck, cwd: &Path) -> bool {
+    match check {
+        ReadyCheck::Tcp(address) => connect(address).is_some(),
+        ReadyCheck::File(path) => cwd.join(path).exists(),
+        ReadyCheck::Http(url) => HttpTarget::parse(url).is_some_and(|target| http_ok(&target)),
+        ReadyCheck::Stdout(_) | ReadyCheck::Stderr(_) => false,
+    }
+}
+
+fn connect<A: ToSocketAddrs>(address: A) -> Option<TcpStream> {
+    address
+        .to_socket_addrs()
+        .ok()?
+        .find_map(|addr| TcpStream::connect_timeout(&addr, CONNECT_TIMEOUT).ok())
+}
+
+/// Sends a `GET` request to `target` and returns true if the response has a
+/// 2xx status code.
+fn http_ok(target: &HttpTarget) -> bool {
+    let Some(mut stream) = connect((target.host.as_str(), target.port)) else {
+        return false;
+    };
+    let request = format!(
+        "GET {} HTTP/1.0\r\nHost: {}:{}\r\nConnection: close\r\n\r\n",
+        target.path, target.host, target.port
+    );
+    if stream.set_read_timeout(Some(CONNECT_TIMEOUT)).is_err()
+        || stream.write_all(request.as_bytes()).is_err()
+    {
+        return false;
+    }
+
+    // We only care about the status line, e.g. `HTTP/1.1 200 OK`.
+    let mut status_line = Vec::new();
+    let mut byte = [0];
+    while status_line.len() < 64 && !status_line.ends_with(b"\r\n") {
+        match stream.read(&mut byte) {
+            Ok(1) => status_line.push(byte[0]),
+            _ => break,
+        }
+    }
+    String::from_utf8_lossy(&status_line)
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .is_some_and(|code| (200..300).contains(&code))
+}
@@ -0,0 +1,263 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Child,
+};
+
+use anyhow::{anyhow, bail, Context};
+use config::Config;
+use serde::{Deserialize, Serialize};
+use sysinfo::{Pid, ProcessRefreshKind, System, UpdateKind};
+
+use crate::{control, descendants::Descendants, instance};
+
+/// Everything a running sma has spawned, so the processes can be found again
+/// if sma does not get to stop them itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct State {
+    // This is the pid of the sma that wrote the state.
+    sma_pid: u32,
+    processes: Vec<RecordedProcess>,
+}
+
+/// A process as it was seen by sma, the pid together with the start time and
+/// the command line tells it apart from a process that got the same pid
+/// later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecordedProcess {
+    pid: u32,
+    // This is when the process started, in seconds since the unix epoch.
+    start_time: u64,
+    cmd: Vec<String>,
+    // This is the index in `start` of the app, it is `None` for a process
+    // that was started by an app.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index: Option<usize>,
+}
+
+/// Keeps the state file of a config up to date while sma runs it.
+pub(crate) struct StateRecorder {
+    path: PathBuf,
+    state: State,
+}
+
+impl StateRecorder {
+    pub(crate) fn new(config_file_path: &Path) -> StateRecorder {
+        StateRecorder {
+            path: state_path(config_file_path),
+            state: State {
+                sma_pid: std::process::id(),
+                processes: Vec::new(),
+            },
+        }
+    }
+
+    /// Records `children` and their `descendants`, as they were at the last
+    /// lookup of the descendants. `app_indices` are the indices in `start` of
+    /// the children.
+    pub(crate) fn record(
+        &mut self,
+        children: &[Child],
+        app_indices: &[usize],
+        descendants: &Descendants,
+    ) -> anyhow::Result<()> {
+        let children = children
+            .iter()
+            .zip(app_indices)
+            .map(|(child, &index)| (Pid::from_u32(child.id()), Some(index)));
+        let processes: Vec<RecordedProcess> = children
+            .chain(descendants.get_pids().into_iter().map(|pid| (pid, None)))
+            .filter_map(|(pid, index)| {
+                descendants.get_process(pid).map(|process| RecordedProcess {
+                    pid: pid.as_u32(),
+                    start_time: process.start_time(),
+                    cmd: process.cmd().to_vec(),
+                    index,
+                })
+            })
+            .collect();
+
+        if processes != self.state.processes {
+            self.state.processes = processes;
+            self.write()?;
+        }
+        Ok(())
+    }
+
+    /// Removes the state file, this is done when sma exits on its own.
+    pub(crate) fn remove(self) {
+        let _ = fs::remove_file(self.path);
+    }
+
+    fn write(&self) -> anyhow::Result<()> {
+        // The state is written to another file first, so a crash while it is
+        // written does not leave half a state behind.
+        let mut temp_path = self.path.clone().into_os_string();
+        temp_path.push(".tmp");
+        fs::write(&temp_path, serde_json::to_string(&self.state)?)
+            .and_then(|_| fs::rename(&temp_path, &self.path))
+            .with_context(|| anyhow!("Could not write the state file `{}`.", self.path.display()))
+    }
+}
+
+/// Stops the processes that an sma running the config at `config_file_path`
+/// left behind when it did not exit on its own.
+pub(crate) fn cleanup(config_file_path: &Path) -> anyhow::Result<()> {
+    if instance::is_running(config_file_path)? {
+        bail!(
+            "The config `{}` is still running, stop it with `sma stop` instead.",
+            config_file_path.display()
+        )
+    }
+
+    let path = state_path(config_file_path);
+    let state: State = match fs::read_to_string(&path) {
+        Ok(state) => serde_json::from_str(&state)
+            .with_context(|| anyhow!("The state file `{}` is invalid.", path.display()))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => State::default(),
+        Err(e) => {
+            return Err(anyhow!(e).context(anyhow!(
+                "Could not read the state file `{}`.",
+                path.display()
+            )))
+        }
+    };
+
+    // The config might not be valid anymore, the processes still have to be
+    // stopped then.
+    let stop = Config::from_existing_config_file(config_file_path)
+        .map(|config| config.get_stop().clone())
+        .unwrap_or_default();
+
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessRefreshKind::new().with_cmd(UpdateKind::Always));
+    let survivors: Vec<&RecordedProcess> = state
+        .processes
+        .iter()
+        .filter(|process| is_survivor(&sys, process))
+        .collect();
+
+    if survivors.is_empty() {
+        println!(
+            "No processes of the config `{}` are left running.",
+            config_file_path.display()
+        );
+    } else {
+        println!(
+            "Stopping the processes that were left running by the config `{}`:",
+            config_file_path.display()
+        );
+        for process in &survivors {
+            println!("    [{}] {}", process.pid, process.cmd.join(" "));
+        }
+        let pids: Vec<Pid> = survivors
+            .iter()
+            .map(|process| Pid::from_u32(process.pid))
+            .collect();
+        crate::stop_processes(&mut [], &pids, &stop)?.print(&stop);
+    }
+
+    if path.exists() {
+        fs::remove_file(&path)
+            .with_context(|| anyhow!("Could not remove the state file `{}`.", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Returns true if the process that was recorded is still running, and not
+/// just another process with the same pid.
+fn is_survivor(sys: &System, recorded: &RecordedProcess) -> bool {
+    sys.process(Pid::from_u32(recorded.pid))
+        .is_some_and(|process| {
+            process.start_time() == recorded.start_time
+                && (recorded.cmd.is_empty() || process.cmd() == recorded.cmd)
+        })
+}
+
+/// Returns the path of the state file of the config at `config_file_path`.
+fn state_path(config_file_path: &Path) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "{}.state.json",
+        control::instance_id(config_file_path)
+    ))
+}
+
+#[cfg(test)]
+mod tests_state {
+    use std::time::Duration;
+
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_is_survivor() {
+        let mut sys = System::new();
+        sys.refresh_processes_specifics(ProcessRefreshKind::new().with_cmd(UpdateKind::Always));
+        let me = sys.process(Pid::from_u32(std::process::id())).unwrap();
+        let recorded = RecordedProcess {
+            pid: std::process::id(),
+            start_time: me.start_time(),
+            cmd: me.cmd().to_vec(),
+            index: Some(0),
+        };
+        assert!(is_survivor(&sys, &recorded));
+
+        // The pid was given to another process.
+        let reused = RecordedProcess {
+            start_time: recorded.start_time - 1,
+            ..recorded.clone()
+        };
+        assert!(!is_survivor(&sys, &reused));
+        let reused = RecordedProcess {
+            cmd: vec!["other".into()],
+            ..recorded
+        };
+        assert!(!is_survivor(&sys, &reused));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_record_and_cleanup() {
+        let temp_dir = TempDir::new("test_record_and_cleanup").unwrap();
+        let config_file_path = temp_dir.path().join("config.json");
+        Config::default()
+            .verify()
+            .unwrap()
+            .create_file(&config_file_path, true)
+            .unwrap();
+
+        // The shell starts a sleep of its own, which has to be recorded too.
+        let mut child = std::process::Command::new("sh")
+            .args(["-c", "sleep 33 & wait"])
+            .spawn()
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+
+        let mut descendants = Descendants::new();
+        descendants.refresh(std::slice::from_ref(&child), true);
+        let mut recorder = StateRecorder::new(&config_file_path);
+        recorder
+            .record(std::slice::from_ref(&child), &[3], &descendants)
+            .unwrap();
+        let state: State =
+            serde_json::from_str(&fs::read_to_string(state_path(&config_file_path)).unwrap())
+                .unwrap();
+        assert_eq!(child.id(), state.processes[0].pid);
+        assert_eq!(Some(3), state.processes[0].index);
+        assert!(state.processes[1..]
+            .iter()
+            .any(|process| process.cmd == ["sleep", "33"] && process.index.is_none()));
+
+        // sma is gone without stopping its apps.
+        drop(recorder);
+        cleanup(&config_file_path).unwrap();
+        assert!(child.try_wait().unwrap().is_some());
+        assert!(!state_path(&config_file_path).exists());
+
+        // Nothing is left to clean up.
+        cleanup(&config_file_path).unwrap();
+    }
+}